@@ -1,16 +1,31 @@
-use axum::{routing::post, Json, Router};
+use axum::{extract::Path, http::HeaderMap, routing::{get, post}, Json, Router};
+use axum::{http::StatusCode, response::{IntoResponse, Response}};
 use serde::{Deserialize, Serialize};
-use crate::eol::types::{EcologicalJobSpec, UsageWindowId, FairUseReceipt};
+use crate::eol::types::{
+    ActorId, BudgetLevel, EcologicalJobSpec, UsageWindowId, ReservationId,
+};
 use crate::eol::orchestrator::EcologicalOrchestrator;
+use crate::eol::approval::{ApprovalRequest, ReviewVerdict};
+use crate::eol::error::{ErrorBody, OrchestratorError};
+use crate::eol::executor::LaunchCommand;
+use crate::eol::did::{DidAuthenticator, DidResolver};
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Deserialize)]
 pub struct PlanJobRequest {
     pub session_token: String,
-    pub window_id: String,
+    // Required unless the server derives windows itself; then it may be left
+    // out and, if given, must name the current window.
+    #[serde(default)]
+    pub window_id: Option<String>,
     pub job: EcologicalJobSpec,
-    pub expected_energy_kwh: f64,
-    pub expected_carbon_kg: f64,
+    // Optional: the orchestrator estimates energy and carbon itself and only
+    // cross-checks these when supplied.
+    #[serde(default)]
+    pub expected_energy_kwh: Option<f64>,
+    #[serde(default)]
+    pub expected_carbon_kg: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -18,42 +33,390 @@ pub struct PlanJobResponse {
     pub plan: crate::eol::types::JobExecutionPlan,
 }
 
+#[derive(Deserialize)]
+pub struct DidChallengeRequest {
+    pub session_token: String,
+    pub did: String,
+}
+
+#[derive(Deserialize)]
+pub struct DidProofRequest {
+    pub session_token: String,
+    pub nonce: String,
+    // hex Ed25519 signature over the challenge message
+    pub signature: String,
+}
+
+#[derive(Serialize)]
+pub struct DidProofResponse {
+    pub did: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    pub session_token: String,
+    #[serde(default)]
+    pub comment: String,
+}
+
+#[derive(Deserialize)]
+pub struct StartJobRequest {
+    pub session_token: String,
+    // Run through the server's executor; without it the caller runs the job
+    // and reports completion itself.
+    #[serde(default)]
+    pub command: Option<LaunchCommand>,
+}
+
+#[derive(Deserialize)]
+pub struct CompleteJobRequest {
+    pub session_token: String,
+    // FLOPs the executor counted; the reserved estimate is charged without it
+    #[serde(default)]
+    pub flops_used: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct CancelJobRequest {
+    pub session_token: String,
+    #[serde(default)]
+    pub reason: String,
+    // only used when the job was already running
+    #[serde(default)]
+    pub flops_used: Option<f64>,
+}
+
+/// Every failed request answers with the status for its error kind and an
+/// `ErrorBody`. Untyped errors are reported as internal, or as a storage
+/// failure when Postgres is behind them.
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: ErrorBody,
+}
+
+pub fn status_for(error: &OrchestratorError) -> StatusCode {
+    match error {
+        OrchestratorError::AuthenticationFailed(_) => StatusCode::UNAUTHORIZED,
+        OrchestratorError::Forbidden(_)
+        | OrchestratorError::TierNotAllowed { .. }
+        | OrchestratorError::PolicyDenied(_)
+        | OrchestratorError::AllowanceExpired(_) => StatusCode::FORBIDDEN,
+        OrchestratorError::NotFound(_) => StatusCode::NOT_FOUND,
+        OrchestratorError::Conflict(_) => StatusCode::CONFLICT,
+        OrchestratorError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+        OrchestratorError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        OrchestratorError::SegmentUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        OrchestratorError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        OrchestratorError::IdentityUnavailable(_) => StatusCode::BAD_GATEWAY,
+    }
+}
+
+impl From<OrchestratorError> for ApiError {
+    fn from(error: OrchestratorError) -> Self {
+        Self {
+            status: status_for(&error),
+            body: error.body(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(error) = OrchestratorError::find(&e) {
+            return error.clone().into();
+        }
+        let storage = e.chain().any(|cause| {
+            cause.is::<tokio_postgres::Error>() || cause.is::<deadpool_postgres::PoolError>()
+        });
+        if storage {
+            return OrchestratorError::Storage(e.to_string()).into();
+        }
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            body: ErrorBody::internal(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+// GET endpoints carry the session token in this header instead of a body.
+fn session_token(headers: &HeaderMap) -> Result<String, OrchestratorError> {
+    headers
+        .get("X-Session-Token")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            OrchestratorError::AuthenticationFailed("missing X-Session-Token header".into())
+        })
+}
+
+#[allow(clippy::type_complexity)]
 pub fn build_router(
     orchestrator: Arc<
         EcologicalOrchestrator<
-            impl crate::eol::identity::IdentityResolver,
-            impl crate::eol::identity::ZoneResolver,
-            impl crate::eol::quota::QuotaStore,
-            impl crate::eol::energy::SegmentTelemetry,
-            impl crate::eol::policy::PolicyEngine,
-            impl crate::eol::logging::ImmutableLogger,
+            impl crate::eol::identity::IdentityResolver + 'static,
+            impl crate::eol::identity::ZoneResolver + 'static,
+            impl crate::eol::quota::QuotaStore + 'static,
+            impl crate::eol::energy::SegmentTelemetry + 'static,
+            impl crate::eol::policy::PolicyEngine + 'static,
+            impl crate::eol::logging::ImmutableLogger + 'static,
+            impl crate::eol::approval::ApprovalStore + 'static,
+            impl crate::eol::jobs::JobStore + 'static,
         >,
     >,
 ) -> Router {
     let plan_orch = orchestrator.clone();
+    let deferred_orch = orchestrator.clone();
+    let pending_orch = orchestrator.clone();
+    let approval_orch = orchestrator.clone();
+    let approve_orch = orchestrator.clone();
+    let reject_orch = orchestrator.clone();
+    let keys_orch = orchestrator.clone();
+    let view_orch = orchestrator.clone();
+    let start_orch = orchestrator.clone();
+    let complete_orch = orchestrator.clone();
+    let cancel_orch = orchestrator.clone();
+    let receipt_orch = orchestrator.clone();
+    let usage_orch = orchestrator.clone();
+    let budget_orch = orchestrator.clone();
 
-    Router::new().route(
-        "/plan_job",
-        post(move |Json(req): Json<PlanJobRequest>| {
-            let orch = plan_orch.clone();
-            async move {
-                let window = UsageWindowId(req.window_id.clone());
-                let plan = orch
-                    .plan_job(
-                        &req.session_token,
-                        window,
-                        req.job,
-                        req.expected_energy_kwh,
-                        req.expected_carbon_kg,
-                    )
-                    .map_err(|e| {
-                        (
-                            axum::http::StatusCode::BAD_REQUEST,
-                            format!("planning error: {:?}", e),
+    Router::new()
+        .route(
+            "/plan_job",
+            post(move |Json(req): Json<PlanJobRequest>| {
+                let orch = plan_orch.clone();
+                async move {
+                    let window = req.window_id.map(UsageWindowId);
+                    let plan = orch
+                        .plan_job(
+                            &req.session_token,
+                            window,
+                            req.job,
+                            req.expected_energy_kwh,
+                            req.expected_carbon_kg,
+                        )
+                        .await?;
+                    Ok::<_, ApiError>(Json(PlanJobResponse { plan }))
+                }
+            }),
+        )
+        .route(
+            "/deferred/:reservation_id",
+            get(move |Path(reservation_id): Path<Uuid>, headers: HeaderMap| {
+                let orch = deferred_orch.clone();
+                async move {
+                    let token = session_token(&headers)?;
+                    let status = orch.deferral(&token, &ReservationId(reservation_id)).await?;
+                    Ok::<_, ApiError>(Json(status))
+                }
+            }),
+        )
+        .route(
+            "/actors/:actor_id/windows/:window_id/usage",
+            get(move |Path((actor_id, window_id)): Path<(String, String)>, headers: HeaderMap| {
+                let orch = usage_orch.clone();
+                async move {
+                    let token = session_token(&headers)?;
+                    let report = orch
+                        .window_usage(&token, &ActorId(actor_id), &UsageWindowId(window_id))
+                        .await?;
+                    Ok::<_, ApiError>(Json(report))
+                }
+            }),
+        )
+        .route(
+            "/budgets/:level/:group_id/windows/:window_id/usage",
+            get(
+                move |Path((level, group_id, window_id)): Path<(String, String, String)>,
+                      headers: HeaderMap| {
+                    let orch = budget_orch.clone();
+                    async move {
+                        let token = session_token(&headers)?;
+                        let level = BudgetLevel::parse(&level).map_err(|e| {
+                            OrchestratorError::InvalidRequest(e.to_string())
+                        })?;
+                        let report = orch
+                            .budget_usage(&token, level, &group_id, &UsageWindowId(window_id))
+                            .await?;
+                        Ok::<_, ApiError>(Json(report))
+                    }
+                },
+            ),
+        )
+        .route(
+            "/plans/:reservation_id",
+            get(move |Path(reservation_id): Path<Uuid>, headers: HeaderMap| {
+                let orch = view_orch.clone();
+                async move {
+                    let token = session_token(&headers)?;
+                    let view = orch.plan(&token, &ReservationId(reservation_id)).await?;
+                    Ok::<_, ApiError>(Json(view))
+                }
+            }),
+        )
+        .route(
+            "/jobs/:reservation_id/start",
+            post(move |Path(reservation_id): Path<Uuid>, Json(req): Json<StartJobRequest>| {
+                let orch = start_orch.clone();
+                async move {
+                    let reservation_id = ReservationId(reservation_id);
+                    let job = match req.command {
+                        Some(command) => {
+                            orch.launch_job(&req.session_token, &reservation_id, command).await?
+                        }
+                        None => orch.start_job(&req.session_token, &reservation_id).await?,
+                    };
+                    Ok::<_, ApiError>(Json(job))
+                }
+            }),
+        )
+        .route(
+            "/jobs/:reservation_id/complete",
+            post(move |Path(reservation_id): Path<Uuid>, Json(req): Json<CompleteJobRequest>| {
+                let orch = complete_orch.clone();
+                async move {
+                    let job = orch
+                        .finish_job(
+                            &req.session_token,
+                            &ReservationId(reservation_id),
+                            req.flops_used,
+                        )
+                        .await?;
+                    Ok::<_, ApiError>(Json(job))
+                }
+            }),
+        )
+        .route(
+            "/jobs/:reservation_id/cancel",
+            post(move |Path(reservation_id): Path<Uuid>, Json(req): Json<CancelJobRequest>| {
+                let orch = cancel_orch.clone();
+                async move {
+                    let job = orch
+                        .cancel_job(
+                            &req.session_token,
+                            &ReservationId(reservation_id),
+                            req.reason,
+                            req.flops_used,
                         )
-                    })?;
-                Ok::<_, (axum::http::StatusCode, String)>(Json(PlanJobResponse { plan }))
-            }
-        }),
-    )
+                        .await?;
+                    Ok::<_, ApiError>(Json(job))
+                }
+            }),
+        )
+        .route(
+            "/receipts/:reservation_id",
+            get(move |Path(reservation_id): Path<Uuid>, headers: HeaderMap| {
+                let orch = receipt_orch.clone();
+                async move {
+                    let token = session_token(&headers)?;
+                    let receipt = orch.receipt(&token, &ReservationId(reservation_id)).await?;
+                    Ok::<_, ApiError>(Json(receipt))
+                }
+            }),
+        )
+        .route(
+            "/receipt_keys",
+            get(move || {
+                let orch = keys_orch.clone();
+                async move {
+                    let keys = orch.receipt_public_keys();
+                    if keys.is_empty() {
+                        return Err(ApiError::from(OrchestratorError::NotFound(
+                            "receipt signing is not configured".into(),
+                        )));
+                    }
+                    Ok(Json(keys))
+                }
+            }),
+        )
+        .route(
+            "/approvals",
+            get(move |headers: HeaderMap| {
+                let orch = pending_orch.clone();
+                async move {
+                    let token = session_token(&headers)?;
+                    let pending = orch.pending_approvals(&token).await?;
+                    Ok::<_, ApiError>(Json(pending))
+                }
+            }),
+        )
+        .route(
+            "/approvals/:reservation_id",
+            get(move |Path(reservation_id): Path<Uuid>, headers: HeaderMap| {
+                let orch = approval_orch.clone();
+                async move {
+                    let token = session_token(&headers)?;
+                    let request = orch.approval(&token, &ReservationId(reservation_id)).await?;
+                    Ok::<_, ApiError>(Json(request))
+                }
+            }),
+        )
+        .route(
+            "/approvals/:reservation_id/approve",
+            post(move |Path(reservation_id): Path<Uuid>, Json(req): Json<ReviewRequest>| {
+                let orch = approve_orch.clone();
+                async move {
+                    let request: ApprovalRequest = orch
+                        .review_plan(
+                            &req.session_token,
+                            &ReservationId(reservation_id),
+                            ReviewVerdict::Approve,
+                            req.comment,
+                        )
+                        .await?;
+                    Ok::<_, ApiError>(Json(request))
+                }
+            }),
+        )
+        .route(
+            "/approvals/:reservation_id/reject",
+            post(move |Path(reservation_id): Path<Uuid>, Json(req): Json<ReviewRequest>| {
+                let orch = reject_orch.clone();
+                async move {
+                    let request: ApprovalRequest = orch
+                        .review_plan(
+                            &req.session_token,
+                            &ReservationId(reservation_id),
+                            ReviewVerdict::Reject,
+                            req.comment,
+                        )
+                        .await?;
+                    Ok::<_, ApiError>(Json(request))
+                }
+            }),
+        )
+}
+
+/// Routes for binding sessions to DIDs; merge them into `build_router` when
+/// the identity resolver is a `DidBoundResolver` over the same authenticator.
+/// Challenges are only issued to sessions that authenticator recognises.
+pub fn did_router(authenticator: Arc<DidAuthenticator<impl DidResolver + 'static>>) -> Router {
+    let prove_auth = authenticator.clone();
+
+    Router::new()
+        .route(
+            "/did/challenge",
+            post(move |Json(req): Json<DidChallengeRequest>| {
+                let auth = authenticator.clone();
+                async move {
+                    let challenge = auth.issue_challenge(&req.session_token, &req.did).await?;
+                    Ok::<_, ApiError>(Json(challenge))
+                }
+            }),
+        )
+        .route(
+            "/did/prove",
+            post(move |Json(req): Json<DidProofRequest>| {
+                let auth = prove_auth.clone();
+                async move {
+                    let did = auth.prove(&req.session_token, &req.nonce, &req.signature).await?;
+                    Ok::<_, ApiError>(Json(DidProofResponse { did: did.to_string() }))
+                }
+            }),
+        )
 }
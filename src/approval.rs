@@ -0,0 +1,134 @@
+use crate::error::OrchestratorError;
+use crate::types::{
+    ActorId, ApprovalStatus, CapabilityTier, EcologicalJobSpec, ReservationId, SegmentId,
+    UsageWindowId,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewVerdict {
+    Approve,
+    Reject,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRecord {
+    pub reviewer: ActorId,
+    pub reviewer_clearance: u8,
+    pub verdict: ReviewVerdict,
+    pub comment: String,
+    pub at: SystemTime,
+}
+
+/// A plan held back for human review. Its reservation stays `Pending` until
+/// `required_approvals` distinct reviewers approve; a single rejection vetoes
+/// it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub reservation_id: ReservationId,
+    pub actor_id: ActorId,
+    pub window_id: UsageWindowId,
+    pub segment_id: SegmentId,
+    pub approved_tier: CapabilityTier,
+    pub job: EcologicalJobSpec,
+    pub risk_score: f32,
+    pub notes: Vec<String>,
+    pub required_approvals: u8,
+    pub min_reviewer_clearance: u8,
+    pub reviews: Vec<ReviewRecord>,
+    pub status: ApprovalStatus,
+    pub created_at: SystemTime,
+}
+
+impl ApprovalRequest {
+    pub fn approvals(&self) -> usize {
+        self.reviews
+            .iter()
+            .filter(|r| r.verdict == ReviewVerdict::Approve)
+            .count()
+    }
+
+    /// Records a review and advances the status once quorum is reached.
+    /// Clearance is checked here too so stores cannot be bypassed.
+    pub fn apply_review(&mut self, review: ReviewRecord) -> Result<()> {
+        if self.status != ApprovalStatus::Pending {
+            return Err(OrchestratorError::Conflict(format!(
+                "approval for {} is already {:?}",
+                self.reservation_id.0, self.status
+            ))
+            .into());
+        }
+        if review.reviewer.0 == self.actor_id.0 {
+            return Err(
+                OrchestratorError::Forbidden("actors cannot review their own jobs".into()).into(),
+            );
+        }
+        if review.reviewer_clearance < self.min_reviewer_clearance {
+            return Err(OrchestratorError::Forbidden(format!(
+                "reviewer clearance {} below required {}",
+                review.reviewer_clearance, self.min_reviewer_clearance
+            ))
+            .into());
+        }
+        if self.reviews.iter().any(|r| r.reviewer.0 == review.reviewer.0) {
+            return Err(OrchestratorError::Conflict(format!(
+                "reviewer {} has already reviewed this job",
+                review.reviewer.0
+            ))
+            .into());
+        }
+
+        let verdict = review.verdict;
+        self.reviews.push(review);
+        match verdict {
+            ReviewVerdict::Reject => self.status = ApprovalStatus::Rejected,
+            ReviewVerdict::Approve => {
+                if self.approvals() >= self.required_approvals.max(1) as usize {
+                    self.status = ApprovalStatus::Approved;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects a pending request without a review, e.g. because its hold
+    /// expired before reviewers reached quorum. `reason` is kept in `notes`.
+    pub fn withdraw(&mut self, reason: &str) -> Result<()> {
+        if self.status != ApprovalStatus::Pending {
+            return Err(OrchestratorError::Conflict(format!(
+                "approval for {} is already {:?}",
+                self.reservation_id.0, self.status
+            ))
+            .into());
+        }
+        self.status = ApprovalStatus::Rejected;
+        self.notes.push(reason.to_string());
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ApprovalStore: Send + Sync {
+    async fn insert(&self, request: &ApprovalRequest) -> Result<()>;
+
+    async fn get(&self, reservation_id: &ReservationId) -> Result<ApprovalRequest>;
+
+    async fn list_pending(&self) -> Result<Vec<ApprovalRequest>>;
+
+    /// Applies `review` via `ApprovalRequest::apply_review` and persists the
+    /// result. Implementations must serialize concurrent reviews of the same
+    /// request so quorum is counted exactly once.
+    async fn record_review(
+        &self,
+        reservation_id: &ReservationId,
+        review: ReviewRecord,
+    ) -> Result<ApprovalRequest>;
+
+    /// Applies `ApprovalRequest::withdraw` and persists the result, with the
+    /// same serialization as `record_review`.
+    async fn withdraw(&self, reservation_id: &ReservationId, reason: &str)
+        -> Result<ApprovalRequest>;
+}
@@ -0,0 +1,44 @@
+//! Validates a policy document and reports malformed or conflicting rules.
+//!
+//! Usage: eol-policy-check <policy.toml|policy.json|policy.yaml>
+//!
+//! Exits 0 when the document is clean, 1 when it has conflicts only (it would
+//! still load), and 2 when it has errors or cannot be read.
+
+use ecological_orchestrator::eol::policy_rules::{IssueSeverity, PolicyDocument};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args_os().nth(1).map(PathBuf::from) else {
+        eprintln!("usage: eol-policy-check <policy.toml|policy.json|policy.yaml>");
+        return ExitCode::from(2);
+    };
+
+    let document = match PolicyDocument::load(&path) {
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let issues = document.validate();
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    println!(
+        "{}: {} rules, {} issues",
+        path.display(),
+        document.rules.len(),
+        issues.len()
+    );
+
+    if issues.iter().any(|i| i.severity == IssueSeverity::Error) {
+        ExitCode::from(2)
+    } else if issues.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
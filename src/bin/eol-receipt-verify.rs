@@ -0,0 +1,51 @@
+//! Verifies a signed FairUseReceipt offline against published receipt keys.
+//!
+//! Usage: eol-receipt-verify <signed-receipt.json> <receipt-keys.json>
+//!
+//! The keys file is the body of `GET /receipt_keys` (a JSON array), or a
+//! single key object. Exits 0 when the receipt is authentic, 1 when it is not,
+//! and 2 when the inputs cannot be read.
+
+use ecological_orchestrator::eol::receipts::{verify_receipt, ReceiptPublicKey, SignedReceipt};
+use std::process::ExitCode;
+
+fn read_inputs(
+    receipt_path: &str,
+    keys_path: &str,
+) -> anyhow::Result<(SignedReceipt, Vec<ReceiptPublicKey>)> {
+    let receipt = serde_json::from_str(&std::fs::read_to_string(receipt_path)?)?;
+    let keys_text = std::fs::read_to_string(keys_path)?;
+    let keys = serde_json::from_str::<Vec<ReceiptPublicKey>>(&keys_text)
+        .or_else(|_| serde_json::from_str::<ReceiptPublicKey>(&keys_text).map(|k| vec![k]))?;
+    Ok((receipt, keys))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [receipt_path, keys_path] = args.as_slice() else {
+        eprintln!("usage: eol-receipt-verify <signed-receipt.json> <receipt-keys.json>");
+        return ExitCode::from(2);
+    };
+
+    let (signed, keys) = match read_inputs(receipt_path, keys_path) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    match verify_receipt(&signed, &keys) {
+        Ok(()) => {
+            println!(
+                "valid: reservation {} signed by key {}",
+                signed.receipt.reservation_id.0, signed.key_id
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("INVALID: {}", e);
+            ExitCode::from(1)
+        }
+    }
+}
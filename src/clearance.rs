@@ -0,0 +1,165 @@
+//! Clearance and trust required for sensitive work.
+//!
+//! A `ClearancePolicy` sets the clearance an actor needs to run a job at a
+//! given `CapabilityTier` or carrying a high-risk domain tag, and the trust
+//! level a segment needs to host it. A tier rule also covers every tier
+//! above it, and where several rules apply to one job the strictest wins. Policies can be built in code or loaded from a TOML,
+//! JSON or YAML file:
+//!
+//! ```toml
+//! [[tiers]]
+//! tier = "Tier3"
+//! min_clearance = 3
+//! min_segment_trust = 3
+//!
+//! [[domain_tags]]
+//! tag = "geoengineering"
+//! min_clearance = 4
+//! min_segment_trust = 4
+//! ```
+
+use crate::identity::ActorProfile;
+use crate::policy_rules::PolicyFormat;
+use crate::types::{CapabilityTier, EcologicalJobSpec};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierRequirement {
+    pub tier: CapabilityTier,
+    #[serde(default)]
+    pub min_clearance: u8,
+    #[serde(default)]
+    pub min_segment_trust: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainTagRequirement {
+    pub tag: String,
+    #[serde(default)]
+    pub min_clearance: u8,
+    #[serde(default)]
+    pub min_segment_trust: u8,
+}
+
+/// Requires nothing until rules are added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClearancePolicy {
+    #[serde(default)]
+    tiers: Vec<TierRequirement>,
+    #[serde(default)]
+    domain_tags: Vec<DomainTagRequirement>,
+}
+
+/// What a job requires and whether its actor meets it.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClearanceAssessment {
+    pub required_clearance: u8,
+    pub min_segment_trust: u8,
+    // the rules that applied to the job, e.g. "Tier3: clearance 3, segment trust 3"
+    pub rules: Vec<String>,
+    // why the actor's clearance falls short; empty when it does not
+    pub denials: Vec<String>,
+}
+
+impl ClearanceAssessment {
+    pub fn is_cleared(&self) -> bool {
+        self.denials.is_empty()
+    }
+}
+
+impl ClearancePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tier(
+        mut self,
+        tier: CapabilityTier,
+        min_clearance: u8,
+        min_segment_trust: u8,
+    ) -> Self {
+        self.tiers.retain(|r| r.tier != tier);
+        self.tiers.push(TierRequirement {
+            tier,
+            min_clearance,
+            min_segment_trust,
+        });
+        self
+    }
+
+    pub fn with_domain_tag(mut self, tag: &str, min_clearance: u8, min_segment_trust: u8) -> Self {
+        self.domain_tags.retain(|r| r.tag != tag);
+        self.domain_tags.push(DomainTagRequirement {
+            tag: tag.to_string(),
+            min_clearance,
+            min_segment_trust,
+        });
+        self
+    }
+
+    /// Fails when a tier or tag is given more than one rule.
+    pub fn parse(text: &str, format: PolicyFormat) -> Result<Self> {
+        let policy: ClearancePolicy = match format {
+            PolicyFormat::Toml => toml::from_str(text)?,
+            PolicyFormat::Json => serde_json::from_str(text)?,
+            PolicyFormat::Yaml => serde_yaml::from_str(text)?,
+        };
+        for (i, rule) in policy.tiers.iter().enumerate() {
+            if policy.tiers[..i].iter().any(|r| r.tier == rule.tier) {
+                anyhow::bail!("tier {:?} has more than one clearance rule", rule.tier);
+            }
+        }
+        for (i, rule) in policy.domain_tags.iter().enumerate() {
+            if policy.domain_tags[..i].iter().any(|r| r.tag == rule.tag) {
+                anyhow::bail!("domain tag {} has more than one clearance rule", rule.tag);
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading clearance policy {}", path.display()))?;
+        Self::parse(&text, PolicyFormat::from_path(path)?)
+            .with_context(|| format!("parsing clearance policy {}", path.display()))
+    }
+
+    pub fn assess(&self, actor: &ActorProfile, job: &EcologicalJobSpec) -> ClearanceAssessment {
+        let mut assessment = ClearanceAssessment::default();
+        let tier_rules = self
+            .tiers
+            .iter()
+            .filter(|r| tier_rank(&job.requested_tier) >= tier_rank(&r.tier))
+            .map(|r| (format!("{:?}", r.tier), r.min_clearance, r.min_segment_trust));
+        let tag_rules = self
+            .domain_tags
+            .iter()
+            .filter(|r| job.domain_tags.contains(&r.tag))
+            .map(|r| (format!("domain tag {}", r.tag), r.min_clearance, r.min_segment_trust));
+        for (name, min_clearance, min_segment_trust) in tier_rules.chain(tag_rules) {
+            assessment.rules.push(format!(
+                "{}: clearance {}, segment trust {}",
+                name, min_clearance, min_segment_trust
+            ));
+            if actor.clearance_level < min_clearance {
+                assessment.denials.push(format!(
+                    "{} requires clearance {}, the actor has {}",
+                    name, min_clearance, actor.clearance_level
+                ));
+            }
+            assessment.required_clearance = assessment.required_clearance.max(min_clearance);
+            assessment.min_segment_trust = assessment.min_segment_trust.max(min_segment_trust);
+        }
+        assessment
+    }
+}
+
+fn tier_rank(tier: &CapabilityTier) -> u8 {
+    match tier {
+        CapabilityTier::Tier1 => 1,
+        CapabilityTier::Tier2 => 2,
+        CapabilityTier::Tier3 => 3,
+    }
+}
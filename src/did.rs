@@ -0,0 +1,503 @@
+//! Decentralised identifiers for actors.
+//!
+//! `Did::parse` validates `did:key` (Ed25519 keys only) and `did:web`
+//! identifiers. A `did:key` document is derived from the identifier itself;
+//! `did:web` documents are read from a local mirror of their https locations
+//! (see `LocalDidResolver`), so resolution works offline. `DidAuthenticator`
+//! lets an actor prove control of its DID by signing a one-time challenge,
+//! which binds the session the challenge was issued to to that DID, and
+//! `DidBoundResolver` refuses sessions whose actor has not done so.
+//! Challenges are only issued to authenticated sessions, for their actor's
+//! own DID, and how many may be pending is capped per DID and overall.
+
+use crate::error::OrchestratorError;
+use crate::identity::{ActorProfile, IdentityResolver};
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+// multicodec prefix of an Ed25519 public key
+const ED25519_PUB: [u8; 2] = [0xed, 0x01];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DidMethod {
+    Key,
+    Web,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Did {
+    method: DidMethod,
+    value: String,
+}
+
+fn invalid(did: &str, why: &str) -> anyhow::Error {
+    OrchestratorError::InvalidRequest(format!("invalid DID {:?}: {}", did, why)).into()
+}
+
+/// Decodes a `z`-prefixed (base58btc) multibase Ed25519 public key.
+fn ed25519_multibase(value: &str) -> Option<VerifyingKey> {
+    let bytes = bs58::decode(value.strip_prefix('z')?).into_vec().ok()?;
+    let key: [u8; 32] = bytes.strip_prefix(&ED25519_PUB)?.try_into().ok()?;
+    VerifyingKey::from_bytes(&key).ok()
+}
+
+fn valid_host(host: &str) -> bool {
+    let (name, port) = match host.split_once("%3A") {
+        Some((name, port)) => (name, Some(port)),
+        None => (host, None),
+    };
+    let label_ok = |l: &str| {
+        !l.is_empty()
+            && l.len() <= 63
+            && !l.starts_with('-')
+            && !l.ends_with('-')
+            && l.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    };
+    name.split('.').all(label_ok)
+        && port.is_none_or(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn valid_path_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && segment
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-' | b'~'))
+}
+
+impl Did {
+    pub fn parse(value: &str) -> Result<Self> {
+        let rest = value
+            .strip_prefix("did:")
+            .ok_or_else(|| invalid(value, "does not start with did:"))?;
+        let (method, specific) = rest
+            .split_once(':')
+            .ok_or_else(|| invalid(value, "has no method-specific identifier"))?;
+        let method = match method {
+            "key" => {
+                if ed25519_multibase(specific).is_none() {
+                    return Err(invalid(value, "is not a base58btc Ed25519 did:key"));
+                }
+                DidMethod::Key
+            }
+            "web" => {
+                let mut parts = specific.split(':');
+                if !parts.next().is_some_and(valid_host) {
+                    return Err(invalid(value, "does not name a valid host"));
+                }
+                if !parts.all(valid_path_segment) {
+                    return Err(invalid(value, "has an invalid path segment"));
+                }
+                DidMethod::Web
+            }
+            other => return Err(invalid(value, &format!("method {} is not supported", other))),
+        };
+        Ok(Self {
+            method,
+            value: value.to_string(),
+        })
+    }
+
+    pub fn method(&self) -> DidMethod {
+        self.method
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    fn specific_id(&self) -> &str {
+        let prefix = match self.method {
+            DidMethod::Key => "did:key:",
+            DidMethod::Web => "did:web:",
+        };
+        &self.value[prefix.len()..]
+    }
+}
+
+impl std::fmt::Display for Did {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub controller: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+}
+
+/// An `authentication` entry: a reference to a verification method or one
+/// embedded in place.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AuthenticationMethod {
+    Reference(String),
+    Embedded(VerificationMethod),
+}
+
+/// The parts of a DID document the orchestrator uses. Other properties are
+/// ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    pub id: String,
+    #[serde(default)]
+    pub verification_method: Vec<VerificationMethod>,
+    #[serde(default)]
+    pub authentication: Vec<AuthenticationMethod>,
+}
+
+impl DidDocument {
+    /// The document of a `did:key`, with its key as the only
+    /// authentication method.
+    pub fn for_did_key(did: &Did) -> Result<Self> {
+        if did.method() != DidMethod::Key {
+            anyhow::bail!("{} is not a did:key", did);
+        }
+        let key_id = format!("{}#{}", did, did.specific_id());
+        Ok(Self {
+            id: did.to_string(),
+            verification_method: vec![VerificationMethod {
+                id: key_id.clone(),
+                kind: "Ed25519VerificationKey2020".into(),
+                controller: did.to_string(),
+                public_key_multibase: Some(did.specific_id().to_string()),
+            }],
+            authentication: vec![AuthenticationMethod::Reference(key_id)],
+        })
+    }
+
+    /// Ed25519 keys the subject may authenticate with, by method id.
+    /// Methods of other types, or whose key cannot be decoded, are skipped.
+    pub fn authentication_keys(&self) -> Vec<(String, VerifyingKey)> {
+        let absolute = |id: &str| match id.strip_prefix('#') {
+            Some(fragment) => format!("{}#{}", self.id, fragment),
+            None => id.to_string(),
+        };
+        self.authentication
+            .iter()
+            .filter_map(|entry| match entry {
+                AuthenticationMethod::Embedded(method) => Some(method),
+                AuthenticationMethod::Reference(id) => self
+                    .verification_method
+                    .iter()
+                    .find(|m| absolute(&m.id) == absolute(id)),
+            })
+            .filter(|m| matches!(m.kind.as_str(), "Ed25519VerificationKey2020" | "Multikey"))
+            .filter_map(|m| {
+                let key = ed25519_multibase(m.public_key_multibase.as_deref()?)?;
+                Some((absolute(&m.id), key))
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+pub trait DidResolver: Send + Sync {
+    async fn resolve(&self, did: &Did) -> Result<DidDocument>;
+}
+
+#[async_trait::async_trait]
+impl<R: DidResolver + ?Sized> DidResolver for Arc<R> {
+    async fn resolve(&self, did: &Did) -> Result<DidDocument> {
+        (**self).resolve(did).await
+    }
+}
+
+/// Resolves `did:web` documents from a directory laid out like the https
+/// URLs they would be fetched from: `did:web:example.org` is read from
+/// `<root>/example.org/.well-known/did.json` and
+/// `did:web:example.org:labs:alice` from `<root>/example.org/labs/alice/did.json`.
+/// A port stays percent-encoded in the directory name.
+#[derive(Debug, Clone)]
+pub struct LocalDidResolver {
+    root: PathBuf,
+}
+
+impl LocalDidResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn document_path(&self, did: &Did) -> Option<PathBuf> {
+        if did.method() != DidMethod::Web {
+            return None;
+        }
+        let mut parts = did.specific_id().split(':');
+        let mut path = self.root.join(parts.next()?);
+        let mut nested = false;
+        for segment in parts {
+            path.push(segment);
+            nested = true;
+        }
+        if !nested {
+            path.push(".well-known");
+        }
+        path.push("did.json");
+        Some(path)
+    }
+}
+
+#[async_trait::async_trait]
+impl DidResolver for LocalDidResolver {
+    async fn resolve(&self, did: &Did) -> Result<DidDocument> {
+        let Some(path) = self.document_path(did) else {
+            return DidDocument::for_did_key(did);
+        };
+        let text = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(OrchestratorError::NotFound(format!(
+                    "no cached DID document for {}",
+                    did
+                ))
+                .into())
+            }
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let document: DidDocument = serde_json::from_str(&text)
+            .with_context(|| format!("parsing DID document {}", path.display()))?;
+        if document.id != did.as_str() {
+            anyhow::bail!("DID document {} describes {}, not {}", path.display(), document.id, did);
+        }
+        Ok(document)
+    }
+}
+
+/// The exact bytes a challenge response signs.
+pub fn challenge_message(did: &str, nonce: &str) -> String {
+    format!("ecological-orchestrator DID authentication\n{}\n{}", did, nonce)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DidChallenge {
+    pub did: String,
+    pub nonce: String,
+    // what to sign with an authentication key of `did`
+    pub message: String,
+    pub expires_at: SystemTime,
+}
+
+struct PendingChallenge {
+    session_token: String,
+    did: Did,
+    expires_at: SystemTime,
+}
+
+/// Issues and checks DID challenges, and remembers which session proved
+/// which DID. Challenges are single use and only count for the session they
+/// were issued to. `sessions` authenticates the sessions asking for one; it
+/// is the resolver a `DidBoundResolver` wraps, not the wrapper itself.
+pub struct DidAuthenticator<R: DidResolver> {
+    resolver: R,
+    sessions: Arc<dyn IdentityResolver>,
+    challenge_ttl: Duration,
+    binding_ttl: Duration,
+    max_pending_per_did: usize,
+    max_pending: usize,
+    pending: Mutex<HashMap<String, PendingChallenge>>,
+    bindings: Mutex<HashMap<String, (Did, SystemTime)>>,
+}
+
+impl<R: DidResolver> DidAuthenticator<R> {
+    pub fn new(resolver: R, sessions: Arc<dyn IdentityResolver>) -> Self {
+        Self {
+            resolver,
+            sessions,
+            challenge_ttl: Duration::from_secs(300),
+            binding_ttl: Duration::from_secs(12 * 3600),
+            max_pending_per_did: 5,
+            max_pending: 10_000,
+            pending: Mutex::new(HashMap::new()),
+            bindings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How many unanswered challenges may be pending for one DID and in
+    /// total; 5 and 10,000 by default.
+    pub fn with_challenge_limits(mut self, per_did: usize, total: usize) -> Self {
+        self.max_pending_per_did = per_did;
+        self.max_pending = total;
+        self
+    }
+
+    /// How long a challenge may be answered; five minutes by default.
+    pub fn with_challenge_ttl(mut self, ttl: Duration) -> Self {
+        self.challenge_ttl = ttl;
+        self
+    }
+
+    /// How long a proven binding lasts; twelve hours by default.
+    pub fn with_binding_ttl(mut self, ttl: Duration) -> Self {
+        self.binding_ttl = ttl;
+        self
+    }
+
+    /// Issues a challenge for `did` to an authenticated session whose actor
+    /// is identified by that DID. Expired challenges are dropped first; the
+    /// request is refused with `Conflict` while the DID or the authenticator
+    /// as a whole has as many pending as allowed.
+    pub async fn issue_challenge(&self, session_token: &str, did: &str) -> Result<DidChallenge> {
+        let did = Did::parse(did)?;
+        let actor = self.sessions.resolve_actor(session_token).await?;
+        if actor.actor_id.0 != did.as_str() {
+            return Err(OrchestratorError::Forbidden(format!(
+                "the session belongs to {}, not {}",
+                actor.actor_id.0, did
+            ))
+            .into());
+        }
+        let nonce = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let now = SystemTime::now();
+        let expires_at = now + self.challenge_ttl;
+        let challenge = DidChallenge {
+            did: did.to_string(),
+            message: challenge_message(did.as_str(), &nonce),
+            nonce: nonce.clone(),
+            expires_at,
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.expires_at > now);
+        if pending.values().filter(|p| p.did == did).count() >= self.max_pending_per_did {
+            return Err(OrchestratorError::Conflict(format!(
+                "{} already has {} unanswered challenges",
+                did, self.max_pending_per_did
+            ))
+            .into());
+        }
+        if pending.len() >= self.max_pending {
+            return Err(OrchestratorError::Conflict(
+                "too many unanswered challenges; try again later".into(),
+            )
+            .into());
+        }
+        pending.insert(
+            nonce,
+            PendingChallenge {
+                session_token: session_token.to_string(),
+                did,
+                expires_at,
+            },
+        );
+        Ok(challenge)
+    }
+
+    /// Checks `signature` (hex Ed25519) over the challenge `nonce` against
+    /// the DID's authentication keys and, if one matches, binds the session
+    /// to the DID.
+    pub async fn prove(&self, session_token: &str, nonce: &str, signature: &str) -> Result<Did> {
+        let rejected = |why: String| -> anyhow::Error {
+            OrchestratorError::AuthenticationFailed(why).into()
+        };
+        let challenge = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(nonce)
+            .ok_or_else(|| rejected("unknown or already answered challenge".into()))?;
+        if challenge.session_token != session_token {
+            return Err(rejected("the challenge was issued to another session".into()));
+        }
+        if challenge.expires_at <= SystemTime::now() {
+            return Err(rejected("the challenge has expired".into()));
+        }
+
+        let did = challenge.did;
+        let bytes: [u8; 64] = hex::decode(signature)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| rejected("signature is not 64 hex-encoded bytes".into()))?;
+        let signature = Signature::from_bytes(&bytes);
+        let message = challenge_message(did.as_str(), nonce);
+        let document = self.resolver.resolve(&did).await?;
+        let keys = document.authentication_keys();
+        if keys.is_empty() {
+            return Err(rejected(format!("{} has no Ed25519 authentication keys", did)));
+        }
+        if !keys
+            .iter()
+            .any(|(_, key)| key.verify_strict(message.as_bytes(), &signature).is_ok())
+        {
+            return Err(rejected(format!(
+                "signature matches no authentication key of {}",
+                did
+            )));
+        }
+
+        let mut bindings = self.bindings.lock().unwrap();
+        let now = SystemTime::now();
+        bindings.retain(|_, (_, expires)| *expires > now);
+        bindings.insert(session_token.to_string(), (did.clone(), now + self.binding_ttl));
+        Ok(did)
+    }
+
+    /// The DID the session has proven control of, if the binding is live.
+    pub fn bound_did(&self, session_token: &str) -> Option<Did> {
+        let bindings = self.bindings.lock().unwrap();
+        match bindings.get(session_token) {
+            Some((did, expires)) if *expires > SystemTime::now() => Some(did.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn unbind(&self, session_token: &str) {
+        self.bindings.lock().unwrap().remove(session_token);
+    }
+}
+
+/// Wraps an `IdentityResolver` so that a session only resolves once it has
+/// proven control of the DID its actor is identified by.
+pub struct DidBoundResolver<I: IdentityResolver, R: DidResolver> {
+    inner: I,
+    authenticator: Arc<DidAuthenticator<R>>,
+}
+
+impl<I: IdentityResolver, R: DidResolver> DidBoundResolver<I, R> {
+    pub fn new(inner: I, authenticator: Arc<DidAuthenticator<R>>) -> Self {
+        Self {
+            inner,
+            authenticator,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<I: IdentityResolver, R: DidResolver> IdentityResolver for DidBoundResolver<I, R> {
+    async fn resolve_actor(&self, session_token: &str) -> Result<ActorProfile> {
+        let profile = self.inner.resolve_actor(session_token).await?;
+        let actor = &profile.actor_id.0;
+        if Did::parse(actor).is_err() {
+            return Err(OrchestratorError::AuthenticationFailed(format!(
+                "actor id {} is not a valid DID",
+                actor
+            ))
+            .into());
+        }
+        match self.authenticator.bound_did(session_token) {
+            Some(did) if did.as_str() == actor => Ok(profile),
+            Some(did) => Err(OrchestratorError::AuthenticationFailed(format!(
+                "session is bound to {}, not {}",
+                did, actor
+            ))
+            .into()),
+            None => Err(OrchestratorError::AuthenticationFailed(format!(
+                "session has not proven control of {}",
+                actor
+            ))
+            .into()),
+        }
+    }
+}
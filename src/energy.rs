@@ -1,8 +1,11 @@
-use crate::types::{CapabilityTier, SegmentId, SegmentLoad, StabilityDecision};
+use crate::error::OrchestratorError;
+use crate::types::{CapabilityTier, EcologicalJobSpec, SegmentId, SegmentLoad, StabilityDecision};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
+#[async_trait::async_trait]
 pub trait SegmentTelemetry: Send + Sync {
-    fn get_segment_load(&self, segment_id: &SegmentId) -> Result<SegmentLoad>;
+    async fn get_segment_load(&self, segment_id: &SegmentId) -> Result<SegmentLoad>;
 }
 
 pub struct StabilityGuard<T: SegmentTelemetry> {
@@ -20,20 +23,32 @@ impl<T: SegmentTelemetry> StabilityGuard<T> {
         }
     }
 
-    pub fn check(
+    pub fn telemetry(&self) -> &T {
+        &self.telemetry
+    }
+
+    pub async fn check(
         &self,
         segment_id: &SegmentId,
-        proposed_flops: f64,
-        proposed_energy_kwh: f64,
+        _proposed_flops: f64,
+        _proposed_energy_kwh: f64,
         requested_tier: &CapabilityTier,
     ) -> Result<StabilityDecision> {
-        let load = self.telemetry.get_segment_load(segment_id)?;
+        let load = self.telemetry.get_segment_load(segment_id).await?;
+        Ok(self.check_load(&load, requested_tier))
+    }
 
+    /// Same decision as `check`, for callers that already hold a fresh load.
+    pub fn check_load(
+        &self,
+        load: &SegmentLoad,
+        requested_tier: &CapabilityTier,
+    ) -> StabilityDecision {
         if load.thermal_margin_pct < self.max_thermal_pct {
-            return Ok(StabilityDecision::Throttle {
+            return StabilityDecision::Throttle {
                 reason: "thermal margin too low".into(),
                 recommended_delay: std::time::Duration::from_secs(900),
-            });
+            };
         }
 
         if load.renewable_share_pct < self.min_renewable_pct {
@@ -45,17 +60,157 @@ impl<T: SegmentTelemetry> StabilityGuard<T> {
             };
 
             if let Some(d) = downgraded {
-                return Ok(StabilityDecision::Downgrade {
+                return StabilityDecision::Downgrade {
                     reason: "insufficient renewable share, downgraded tier".into(),
                     downgraded_tier: d,
-                });
+                };
             } else {
-                return Ok(StabilityDecision::Deny {
+                return StabilityDecision::Deny {
                     reason: "insufficient renewable share for even lowest tier".into(),
-                });
+                };
             }
         }
 
-        Ok(StabilityDecision::Ok)
+        StabilityDecision::Ok
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyEstimate {
+    pub energy_kwh: f64,
+    pub carbon_kg: f64,
+    pub kwh_per_pflop: f64,
+    pub carbon_intensity_kg_per_kwh: f64,
+}
+
+/// Derives a job's energy and carbon from its FLOPs and the segment's measured
+/// efficiency instead of trusting caller-declared figures.
+///
+/// Efficiency is the segment's current power draw over its current FLOP rate;
+/// segments that report no load fall back to `fallback_kwh_per_pflop`. Higher
+/// tiers pay an overhead factor for the extra accelerators and interconnect
+/// they hold. Carbon intensity blends grid and renewable intensities by the
+/// segment's renewable share.
+#[derive(Debug, Clone)]
+pub struct EnergyEstimator {
+    fallback_kwh_per_pflop: f64,
+    grid_carbon_kg_per_kwh: f64,
+    renewable_carbon_kg_per_kwh: f64,
+    tier_overhead: [f64; 3],
+    max_discrepancy: Option<f64>,
+}
+
+impl Default for EnergyEstimator {
+    fn default() -> Self {
+        Self::new(0.02, 0.45, 0.03)
+    }
+}
+
+impl EnergyEstimator {
+    pub fn new(
+        fallback_kwh_per_pflop: f64,
+        grid_carbon_kg_per_kwh: f64,
+        renewable_carbon_kg_per_kwh: f64,
+    ) -> Self {
+        Self {
+            fallback_kwh_per_pflop,
+            grid_carbon_kg_per_kwh,
+            renewable_carbon_kg_per_kwh,
+            tier_overhead: [1.0, 1.1, 1.25],
+            max_discrepancy: None,
+        }
+    }
+
+    /// Overhead multipliers for Tier1, Tier2 and Tier3.
+    pub fn with_tier_overhead(mut self, tier_overhead: [f64; 3]) -> Self {
+        self.tier_overhead = tier_overhead;
+        self
+    }
+
+    /// Reject caller-declared figures that differ from the estimate by more
+    /// than this fraction (e.g. `0.5` for 50%).
+    pub fn with_max_discrepancy(mut self, max_discrepancy: f64) -> Self {
+        self.max_discrepancy = Some(max_discrepancy);
+        self
+    }
+
+    pub fn estimate(
+        &self,
+        job: &EcologicalJobSpec,
+        tier: &CapabilityTier,
+        load: &SegmentLoad,
+    ) -> EnergyEstimate {
+        self.estimate_flops(job.expected_flops, tier, load)
+    }
+
+    /// `estimate` for a bare FLOP count, e.g. to re-estimate a reservation.
+    pub fn estimate_flops(
+        &self,
+        expected_flops: f64,
+        tier: &CapabilityTier,
+        load: &SegmentLoad,
+    ) -> EnergyEstimate {
+        // energy_rate_kw / current_flops is kW per FLOP/s, i.e. kJ per FLOP.
+        let kwh_per_pflop = if load.current_flops > 0.0 && load.energy_rate_kw > 0.0 {
+            load.energy_rate_kw / load.current_flops / 3600.0 * 1e15
+        } else {
+            self.fallback_kwh_per_pflop
+        };
+        let overhead = match tier {
+            CapabilityTier::Tier1 => self.tier_overhead[0],
+            CapabilityTier::Tier2 => self.tier_overhead[1],
+            CapabilityTier::Tier3 => self.tier_overhead[2],
+        };
+        let energy_kwh = expected_flops / 1e15 * kwh_per_pflop * overhead;
+
+        let carbon_intensity_kg_per_kwh = self.carbon_intensity(load.renewable_share_pct);
+
+        EnergyEstimate {
+            energy_kwh,
+            carbon_kg: energy_kwh * carbon_intensity_kg_per_kwh,
+            kwh_per_pflop,
+            carbon_intensity_kg_per_kwh,
+        }
+    }
+
+    /// kg CO2 per kWh at the given renewable share.
+    pub fn carbon_intensity(&self, renewable_share_pct: f64) -> f64 {
+        let renewable = (renewable_share_pct / 100.0).clamp(0.0, 1.0);
+        self.grid_carbon_kg_per_kwh * (1.0 - renewable)
+            + self.renewable_carbon_kg_per_kwh * renewable
+    }
+
+    /// Fails if a declared figure strays further from the estimate than the
+    /// configured tolerance. Does nothing when no tolerance is configured.
+    pub fn cross_check(
+        &self,
+        estimate: &EnergyEstimate,
+        declared_energy_kwh: Option<f64>,
+        declared_carbon_kg: Option<f64>,
+    ) -> Result<()> {
+        let Some(tolerance) = self.max_discrepancy else {
+            return Ok(());
+        };
+        let checks = [
+            ("energy", "kWh", declared_energy_kwh, estimate.energy_kwh),
+            ("carbon", "kg", declared_carbon_kg, estimate.carbon_kg),
+        ];
+        for (name, unit, declared, estimated) in checks {
+            let Some(declared) = declared else { continue };
+            let deviation = (declared - estimated).abs() / estimated.max(f64::EPSILON);
+            if deviation > tolerance {
+                return Err(OrchestratorError::InvalidRequest(format!(
+                    "declared {} {:.3} {} differs from estimate {:.3} {} by more than {:.0}%",
+                    name,
+                    declared,
+                    unit,
+                    estimated,
+                    unit,
+                    tolerance * 100.0
+                ))
+                .into());
+            }
+        }
+        Ok(())
     }
 }
@@ -1,48 +1,126 @@
-use crate::eol::types::{ActorId, SegmentId};
-use crate::eol::identity::{ActorProfile, ZoneResolution, IdentityResolver, ZoneResolver};
+use crate::eol::types::SegmentId;
+use crate::eol::error::OrchestratorError;
+use crate::eol::identity::{
+    ActorClaims, ActorProfile, IdentityResolver, ResolutionCache, ZoneResolution, ZoneResolver,
+};
 use anyhow::Result;
+use reqwest::StatusCode;
+use std::time::Duration;
 
-// Example: resolve DIDs from an internal service or map session tokens to DIDs.
+/// Resolves session tokens through the identity service's
+/// `GET {base_url}/resolve_actor`, which answers with `ActorClaims` JSON.
+/// A 401 fails authentication, a 403 is forbidden, and other error statuses
+/// and transport failures mean the service is unavailable. Claims in a
+/// successful response that do not match the schema fail authentication.
+/// Resolutions are cached for a minute by default. Connections give up after
+/// five seconds and whole requests after ten, so a hung service surfaces as
+/// unavailable rather than stalling every caller.
 pub struct HttpIdentityResolver {
     client: reqwest::Client,
     base_url: String,
+    cache: ResolutionCache,
 }
 
 impl HttpIdentityResolver {
     pub fn new(base_url: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: client(Duration::from_secs(5), Duration::from_secs(10)),
             base_url,
+            cache: ResolutionCache::new(Duration::from_secs(60)),
         }
     }
+
+    /// How long to wait for a connection, and for a whole request including
+    /// its body, before treating the identity service as unavailable.
+    pub fn with_timeouts(mut self, connect: Duration, request: Duration) -> Self {
+        self.client = client(connect, request);
+        self
+    }
+
+    /// How long a resolution is reused; zero resolves every call afresh.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache = ResolutionCache::new(ttl);
+        self
+    }
+
+    pub fn cache(&self) -> &ResolutionCache {
+        &self.cache
+    }
+}
+
+fn client(connect: Duration, request: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect)
+        .timeout(request)
+        .build()
+        .expect("the identity service client needs a TLS backend")
+}
+
+fn unavailable(e: reqwest::Error) -> OrchestratorError {
+    if e.is_timeout() {
+        OrchestratorError::IdentityUnavailable(format!("identity service timed out: {}", e))
+    } else {
+        OrchestratorError::IdentityUnavailable(e.to_string())
+    }
 }
 
+#[async_trait::async_trait]
 impl IdentityResolver for HttpIdentityResolver {
-    fn resolve_actor(&self, session_token: &str) -> Result<ActorProfile> {
+    async fn resolve_actor(&self, session_token: &str) -> Result<ActorProfile> {
+        if let Some(profile) = self.cache.get(session_token) {
+            return Ok(profile);
+        }
         let url = format!("{}/resolve_actor", self.base_url);
-        let resp: serde_json::Value = tokio::runtime::Handle::current().block_on(async {
-            self.client
-                .get(&url)
-                .header("X-Session-Token", session_token)
-                .send()
-                .await?
-                .json()
-                .await
+        let resp = self
+            .client
+            .get(&url)
+            .header("X-Session-Token", session_token)
+            .send()
+            .await
+            .map_err(unavailable)?;
+
+        let status = resp.status();
+        match status {
+            StatusCode::UNAUTHORIZED => {
+                return Err(OrchestratorError::AuthenticationFailed(
+                    "identity service rejected the session token".into(),
+                )
+                .into())
+            }
+            StatusCode::FORBIDDEN => {
+                return Err(OrchestratorError::Forbidden(
+                    "identity service refused to resolve the session".into(),
+                )
+                .into())
+            }
+            s if !s.is_success() => {
+                return Err(OrchestratorError::IdentityUnavailable(format!(
+                    "identity service answered {}",
+                    s
+                ))
+                .into())
+            }
+            _ => {}
+        }
+        let body = resp
+            .bytes()
+            .await
+            .map_err(unavailable)?;
+        // The service answered; claims it cannot vouch for are not an outage.
+        let claims: ActorClaims = serde_json::from_slice(&body).map_err(|e| {
+            OrchestratorError::AuthenticationFailed(format!("malformed identity claims: {}", e))
         })?;
-        // Map JSON into ActorProfile
-        Ok(ActorProfile {
-            actor_id: ActorId(resp["actor_id"].as_str().unwrap_or_default().to_string()),
-            roles: vec!["researcher".into()], // TODO: parse from JSON
-            clearance_level: resp["clearance"].as_u64().unwrap_or(1) as u8,
-            ecological_priority_score: resp["eco_priority"].as_f64().unwrap_or(0.5) as f32,
-        })
+        let profile = claims.into_profile()?;
+        self.cache.insert(session_token, &profile);
+        Ok(profile)
     }
 }
 
 pub struct StaticZoneResolver;
 
+#[async_trait::async_trait]
 impl ZoneResolver for StaticZoneResolver {
-    fn resolve_zone(&self, actor: &ActorProfile) -> Result<ZoneResolution> {
+    async fn resolve_zone(&self, actor: &ActorProfile) -> Result<ZoneResolution> {
         // Map actor roles / DIDs to predefined ALN segments.
         // Replace this with a real config or DB lookup.
         let segment = if actor.roles.contains(&"climate_lab".to_string()) {
@@ -56,4 +134,16 @@ impl ZoneResolver for StaticZoneResolver {
             trust_level: 3,
         })
     }
+
+    async fn resolve_zones(&self, actor: &ActorProfile) -> Result<Vec<ZoneResolution>> {
+        // Climate lab members may also spill over onto the general research pool.
+        let mut zones = vec![self.resolve_zone(actor).await?];
+        if zones[0].segment_id.0 != "segment_general_research" {
+            zones.push(ZoneResolution {
+                segment_id: SegmentId("segment_general_research".into()),
+                trust_level: 3,
+            });
+        }
+        Ok(zones)
+    }
 }
@@ -0,0 +1,160 @@
+//! Failures the API reports with their own status code and error code.
+//!
+//! Components keep returning `anyhow::Result`; these variants travel inside
+//! the `anyhow::Error` and are recovered with `OrchestratorError::find`.
+//! Anything untyped is reported as an internal error.
+
+use crate::types::{BudgetLevel, CapabilityTier};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaDimension {
+    Flops,
+    EnergyKwh,
+    CarbonKg,
+}
+
+impl QuotaDimension {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuotaDimension::Flops => "flops",
+            QuotaDimension::EnergyKwh => "energy_kwh",
+            QuotaDimension::CarbonKg => "carbon_kg",
+        }
+    }
+}
+
+/// What is left of a window's allowance once committed usage and outstanding
+/// holds are subtracted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemainingAllowance {
+    pub flops: f64,
+    pub energy_kwh: f64,
+    pub carbon_kg: f64,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum OrchestratorError {
+    #[error("authentication failed: {0}")]
+    AuthenticationFailed(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("requested tier {requested:?} is not allowed: {reason}")]
+    TierNotAllowed {
+        requested: CapabilityTier,
+        allowed: Vec<CapabilityTier>,
+        reason: String,
+    },
+    #[error("policy denied: {0}")]
+    PolicyDenied(String),
+    #[error("allowance expired: {0}")]
+    AllowanceExpired(String),
+    #[error("{} {} allowance exceeded", level.as_str(), dimension.as_str())]
+    QuotaExceeded {
+        level: BudgetLevel,
+        dimension: QuotaDimension,
+        requested: f64,
+        remaining: RemainingAllowance,
+    },
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("segment unavailable: {0}")]
+    SegmentUnavailable(String),
+    #[error("storage failure: {0}")]
+    Storage(String),
+    #[error("identity service unavailable: {0}")]
+    IdentityUnavailable(String),
+}
+
+impl OrchestratorError {
+    /// Machine-readable code; part of the API contract, so never rename one.
+    pub fn code(&self) -> &'static str {
+        match self {
+            OrchestratorError::AuthenticationFailed(_) => "authentication_failed",
+            OrchestratorError::Forbidden(_) => "forbidden",
+            OrchestratorError::TierNotAllowed { .. } => "tier_not_allowed",
+            OrchestratorError::PolicyDenied(_) => "policy_denied",
+            OrchestratorError::AllowanceExpired(_) => "allowance_expired",
+            OrchestratorError::QuotaExceeded { .. } => "quota_exceeded",
+            OrchestratorError::NotFound(_) => "not_found",
+            OrchestratorError::Conflict(_) => "conflict",
+            OrchestratorError::InvalidRequest(_) => "invalid_request",
+            OrchestratorError::SegmentUnavailable(_) => "segment_unavailable",
+            OrchestratorError::Storage(_) => "storage_failure",
+            OrchestratorError::IdentityUnavailable(_) => "identity_unavailable",
+        }
+    }
+
+    /// The first typed error anywhere in `err`'s context chain.
+    pub fn find(err: &anyhow::Error) -> Option<&OrchestratorError> {
+        err.chain().find_map(|cause| cause.downcast_ref::<OrchestratorError>())
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        let mut body = ErrorBody {
+            code: self.code().to_string(),
+            message: self.to_string(),
+            level: None,
+            dimension: None,
+            requested: None,
+            remaining: None,
+            allowed_tiers: Vec::new(),
+        };
+        match self {
+            OrchestratorError::QuotaExceeded {
+                level,
+                dimension,
+                requested,
+                remaining,
+            } => {
+                body.level = Some(*level);
+                body.dimension = Some(*dimension);
+                body.requested = Some(*requested);
+                body.remaining = Some(remaining.clone());
+            }
+            OrchestratorError::TierNotAllowed { allowed, .. } => {
+                body.allowed_tiers = allowed.clone();
+            }
+            _ => {}
+        }
+        body
+    }
+}
+
+/// JSON error body returned by every endpoint. Fields other than `code` and
+/// `message` appear only for the errors they describe.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    // budget level whose allowance was exceeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<BudgetLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<QuotaDimension>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining: Option<RemainingAllowance>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_tiers: Vec<CapabilityTier>,
+}
+
+impl ErrorBody {
+    pub fn internal(message: String) -> Self {
+        Self {
+            code: "internal".into(),
+            message,
+            level: None,
+            dimension: None,
+            requested: None,
+            remaining: None,
+            allowed_tiers: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Launching approved plans.
+//!
+//! An `Executor` runs the job behind a reservation and reports how it is
+//! doing; the orchestrator starts, watches and settles jobs through it (see
+//! `EcologicalOrchestrator::launch_job` and `run_executor_once`).
+//! `LocalProcessExecutor` is the reference implementation: it runs a command
+//! on this host under the CPU-time and wall-clock limits of the job's tier.
+
+use crate::error::OrchestratorError;
+use crate::jobs::JobRecord;
+use crate::preemption::{PreemptionExecutor, PreemptionSignal};
+use crate::types::{CapabilityTier, ReservationId};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRequest {
+    pub reservation_id: ReservationId,
+    pub tier: CapabilityTier,
+    pub command: LaunchCommand,
+    pub max_duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum ExecutionState {
+    Running,
+    Suspended,
+    Succeeded,
+    Failed { reason: String },
+    Cancelled,
+}
+
+impl ExecutionState {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            ExecutionState::Succeeded | ExecutionState::Failed { .. } | ExecutionState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionStatus {
+    pub state: ExecutionState,
+    pub started_at: SystemTime,
+    pub finished_at: Option<SystemTime>,
+}
+
+#[async_trait::async_trait]
+pub trait Executor: Send + Sync {
+    /// Starts the job. Fails without side effects if it cannot be launched.
+    async fn submit(&self, request: &ExecutionRequest) -> Result<()>;
+
+    /// `NotFound` for reservations this executor never ran or has since
+    /// forgotten.
+    async fn status(&self, reservation_id: &ReservationId) -> Result<ExecutionStatus>;
+
+    async fn suspend(&self, reservation_id: &ReservationId) -> Result<()>;
+
+    async fn resume(&self, reservation_id: &ReservationId) -> Result<()>;
+
+    /// Stops the job for good. Cancelling a finished job is a no-op.
+    async fn cancel(&self, reservation_id: &ReservationId) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<E: Executor + ?Sized> Executor for Arc<E> {
+    async fn submit(&self, request: &ExecutionRequest) -> Result<()> {
+        (**self).submit(request).await
+    }
+
+    async fn status(&self, reservation_id: &ReservationId) -> Result<ExecutionStatus> {
+        (**self).status(reservation_id).await
+    }
+
+    async fn suspend(&self, reservation_id: &ReservationId) -> Result<()> {
+        (**self).suspend(reservation_id).await
+    }
+
+    async fn resume(&self, reservation_id: &ReservationId) -> Result<()> {
+        (**self).resume(reservation_id).await
+    }
+
+    async fn cancel(&self, reservation_id: &ReservationId) -> Result<()> {
+        (**self).cancel(reservation_id).await
+    }
+}
+
+/// Lets an `Executor` receive pre-emption signals. Suspension stops the
+/// process where it is until it is resumed; processes cannot be moved to another tier while
+/// running, so downgrades are refused and the orchestrator suspends the job
+/// instead.
+pub struct ExecutorPreemption<E: Executor>(pub E);
+
+#[async_trait::async_trait]
+impl<E: Executor> PreemptionExecutor for ExecutorPreemption<E> {
+    async fn signal(
+        &self,
+        record: &JobRecord,
+        signal: &PreemptionSignal,
+        _rationale: &str,
+    ) -> Result<()> {
+        match signal {
+            PreemptionSignal::Suspend => self.0.suspend(record.reservation_id()).await,
+            PreemptionSignal::Resume => self.0.resume(record.reservation_id()).await,
+            PreemptionSignal::Downgrade { .. } => Err(OrchestratorError::InvalidRequest(
+                "running processes cannot change tier".into(),
+            )
+            .into()),
+        }
+    }
+}
+
+/// What a tier may consume on the local host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierLimits {
+    pub cpu_secs: u64,
+    pub wall_time: Duration,
+}
+
+struct LocalRun {
+    pid: Option<u32>,
+    status: ExecutionStatus,
+    // the outcome was reported by `status` or decided by `cancel`
+    observed: bool,
+}
+
+/// Runs allow-listed programs as child processes. CPU time is capped with
+/// `RLIMIT_CPU` (via `ulimit -t`) and wall-clock time by killing the process;
+/// the tighter of the tier's wall time and the job's `max_duration` applies.
+/// Suspend and resume send `SIGSTOP`/`SIGCONT`. A finished run is forgotten
+/// at the next submission once its outcome has been observed, i.e. `status`
+/// reported it or it was cancelled. Unix only.
+#[derive(Clone)]
+pub struct LocalProcessExecutor {
+    limits: HashMap<CapabilityTier, TierLimits>,
+    allowed_programs: Vec<String>,
+    runs: Arc<Mutex<HashMap<Uuid, LocalRun>>>,
+}
+
+impl Default for LocalProcessExecutor {
+    fn default() -> Self {
+        let hours = |h: u64| Duration::from_secs(h * 3600);
+        let limits = |cpu_hours: u64, wall_hours: u64| TierLimits {
+            cpu_secs: cpu_hours * 3600,
+            wall_time: hours(wall_hours),
+        };
+        Self::new()
+            .with_limits(CapabilityTier::Tier1, limits(1, 1))
+            .with_limits(CapabilityTier::Tier2, limits(8, 4))
+            .with_limits(CapabilityTier::Tier3, limits(64, 12))
+    }
+}
+
+impl LocalProcessExecutor {
+    /// An executor with no tier limits and no allowed programs; use
+    /// `default()` for the stock limits.
+    pub fn new() -> Self {
+        Self {
+            limits: HashMap::new(),
+            allowed_programs: Vec::new(),
+            runs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_limits(mut self, tier: CapabilityTier, limits: TierLimits) -> Self {
+        self.limits.insert(tier, limits);
+        self
+    }
+
+    pub fn with_allowed_program(mut self, program: &str) -> Self {
+        self.allowed_programs.push(program.to_string());
+        self
+    }
+
+    fn run_pid(&self, reservation_id: &ReservationId) -> Result<Option<u32>> {
+        let runs = self.runs.lock().unwrap();
+        let run = runs.get(&reservation_id.0).ok_or_else(|| not_running(reservation_id))?;
+        Ok(match run.status.state {
+            ExecutionState::Running | ExecutionState::Suspended => run.pid,
+            _ => None,
+        })
+    }
+
+    fn set_state(&self, reservation_id: &ReservationId, state: ExecutionState) {
+        if let Some(run) = self.runs.lock().unwrap().get_mut(&reservation_id.0) {
+            run.status.state = state;
+        }
+    }
+}
+
+fn not_running(reservation_id: &ReservationId) -> OrchestratorError {
+    OrchestratorError::NotFound(format!("no execution for reservation {}", reservation_id.0))
+}
+
+async fn send_signal(pid: u32, signal: &str) -> Result<()> {
+    let status = tokio::process::Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .status()
+        .await?;
+    if !status.success() {
+        anyhow::bail!("kill -{} {} failed with {}", signal, pid, status);
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl Executor for LocalProcessExecutor {
+    async fn submit(&self, request: &ExecutionRequest) -> Result<()> {
+        let id = request.reservation_id.clone();
+        if !self.allowed_programs.contains(&request.command.program) {
+            return Err(OrchestratorError::Forbidden(format!(
+                "program {:?} is not allowed on this executor",
+                request.command.program
+            ))
+            .into());
+        }
+        let limits = self.limits.get(&request.tier).ok_or_else(|| {
+            OrchestratorError::InvalidRequest(format!("no limits for {:?}", request.tier))
+        })?;
+        {
+            let mut runs = self.runs.lock().unwrap();
+            runs.retain(|_, run| !(run.observed && run.status.finished_at.is_some()));
+            if runs.contains_key(&id.0) {
+                return Err(OrchestratorError::Conflict(format!(
+                    "reservation {} was already submitted",
+                    id.0
+                ))
+                .into());
+            }
+        }
+
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(r#"ulimit -t "$0" && exec "$@""#)
+            .arg(limits.cpu_secs.to_string())
+            .arg(&request.command.program)
+            .args(&request.command.args)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let started_at = SystemTime::now();
+        self.runs.lock().unwrap().insert(
+            id.0,
+            LocalRun {
+                pid: child.id(),
+                status: ExecutionStatus {
+                    state: ExecutionState::Running,
+                    started_at,
+                    finished_at: None,
+                },
+                observed: false,
+            },
+        );
+
+        let wall_time = limits.wall_time.min(request.max_duration);
+        let runs = self.runs.clone();
+        tokio::spawn(async move {
+            let state = match tokio::time::timeout(wall_time, child.wait()).await {
+                Ok(Ok(exit)) if exit.success() => ExecutionState::Succeeded,
+                Ok(Ok(exit)) => {
+                    use std::os::unix::process::ExitStatusExt;
+                    // SIGXCPU is 24 on Linux and the BSDs
+                    let reason = match exit.signal() {
+                        Some(24) => "CPU time limit exceeded".to_string(),
+                        _ => format!("process exited with {}", exit),
+                    };
+                    ExecutionState::Failed { reason }
+                }
+                Ok(Err(e)) => ExecutionState::Failed {
+                    reason: format!("could not wait for the process: {}", e),
+                },
+                Err(_) => {
+                    let _ = child.kill().await;
+                    ExecutionState::Failed {
+                        reason: format!("wall-clock limit of {}s exceeded", wall_time.as_secs()),
+                    }
+                }
+            };
+            if let Some(run) = runs.lock().unwrap().get_mut(&id.0) {
+                // A cancelled run stays cancelled however the process ended.
+                if run.status.state != ExecutionState::Cancelled {
+                    run.status.state = state;
+                }
+                run.status.finished_at = Some(SystemTime::now());
+            }
+        });
+        Ok(())
+    }
+
+    async fn status(&self, reservation_id: &ReservationId) -> Result<ExecutionStatus> {
+        let mut runs = self.runs.lock().unwrap();
+        let run = runs.get_mut(&reservation_id.0).ok_or_else(|| not_running(reservation_id))?;
+        run.observed |= run.status.state.is_finished();
+        Ok(run.status.clone())
+    }
+
+    async fn suspend(&self, reservation_id: &ReservationId) -> Result<()> {
+        let pid = self.run_pid(reservation_id)?.ok_or_else(|| {
+            OrchestratorError::Conflict(format!("job {} is not running", reservation_id.0))
+        })?;
+        send_signal(pid, "STOP").await?;
+        self.set_state(reservation_id, ExecutionState::Suspended);
+        Ok(())
+    }
+
+    async fn resume(&self, reservation_id: &ReservationId) -> Result<()> {
+        let pid = self.run_pid(reservation_id)?.ok_or_else(|| {
+            OrchestratorError::Conflict(format!("job {} is not running", reservation_id.0))
+        })?;
+        send_signal(pid, "CONT").await?;
+        self.set_state(reservation_id, ExecutionState::Running);
+        Ok(())
+    }
+
+    async fn cancel(&self, reservation_id: &ReservationId) -> Result<()> {
+        let Some(pid) = self.run_pid(reservation_id)? else {
+            return Ok(());
+        };
+        self.set_state(reservation_id, ExecutionState::Cancelled);
+        if let Some(run) = self.runs.lock().unwrap().get_mut(&reservation_id.0) {
+            run.observed = true;
+        }
+        // SIGKILL also ends a stopped process; the watcher records the end.
+        send_signal(pid, "KILL").await
+    }
+}
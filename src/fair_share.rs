@@ -0,0 +1,186 @@
+//! Priority-weighted fair share of contended segments.
+//!
+//! Every actor gets a weight from its `ecological_priority_score` and the
+//! FLOPs it ran recently, with older usage decaying by half every
+//! `half_life`. The weight only matters while the chosen segment is
+//! contended: it scales the largest job the actor may start there straight
+//! away (its burst allowance), and larger jobs wait in the deferred queue,
+//! which releases higher weights first.
+//!
+//! Decayed usage lives in memory; the orchestrator rebuilds it from the
+//! receipts of settled jobs before it first weighs anyone.
+
+use crate::identity::ActorProfile;
+use crate::types::{ActorId, EcologicalJobSpec, FairShareDecision};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct FairShareConfig {
+    /// How much a priority score of 1.0 raises the weight over 0.0.
+    pub priority_weight: f64,
+    /// How much recent usage of `usage_scale_flops` lowers the weight.
+    pub usage_weight: f64,
+    pub usage_scale_flops: f64,
+    pub half_life: Duration,
+    /// Placement headroom (0..=1) below which a segment counts as contended.
+    pub contention_headroom: f64,
+    /// Burst allowance at weight 1.0; it scales with the weight up to
+    /// `max_burst_multiplier` times this.
+    pub base_burst_flops: f64,
+    pub max_burst_multiplier: f64,
+}
+
+impl Default for FairShareConfig {
+    fn default() -> Self {
+        Self {
+            priority_weight: 1.0,
+            usage_weight: 0.5,
+            usage_scale_flops: 1e16,
+            half_life: Duration::from_secs(6 * 3600),
+            contention_headroom: 0.2,
+            base_burst_flops: 1e15,
+            max_burst_multiplier: 4.0,
+        }
+    }
+}
+
+// Decayed FLOPs and when they were last brought up to date.
+#[derive(Debug, Clone, Copy)]
+struct RecentUsage {
+    flops: f64,
+    as_of: SystemTime,
+}
+
+#[derive(Debug, Default)]
+pub struct FairShareAllocator {
+    config: FairShareConfig,
+    recent: Mutex<HashMap<String, RecentUsage>>,
+}
+
+impl FairShareAllocator {
+    pub fn new(config: FairShareConfig) -> Self {
+        Self {
+            config,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &FairShareConfig {
+        &self.config
+    }
+
+    /// Adds committed FLOPs to the actor's decayed usage. Usage may be
+    /// recorded out of order, e.g. while it is rebuilt from old receipts.
+    pub fn record_usage(&self, actor: &ActorId, flops: f64, at: SystemTime) {
+        let mut recent = self.recent.lock().unwrap();
+        let entry = recent.entry(actor.0.clone()).or_insert(RecentUsage {
+            flops: 0.0,
+            as_of: at,
+        });
+        let added = RecentUsage {
+            flops: flops.max(0.0),
+            as_of: at,
+        };
+        let as_of = at.max(entry.as_of);
+        *entry = RecentUsage {
+            flops: self.decay(*entry, as_of) + self.decay(added, as_of),
+            as_of,
+        };
+    }
+
+    /// The actor's usage as of `now`, decayed.
+    pub fn recent_usage(&self, actor: &ActorId, now: SystemTime) -> f64 {
+        let recent = self.recent.lock().unwrap();
+        recent.get(&actor.0).map_or(0.0, |usage| self.decay(*usage, now))
+    }
+
+    fn decay(&self, usage: RecentUsage, now: SystemTime) -> f64 {
+        let elapsed = now.duration_since(usage.as_of).unwrap_or_default();
+        let half_life = self.config.half_life.as_secs_f64();
+        if half_life <= 0.0 {
+            return 0.0;
+        }
+        usage.flops * 0.5_f64.powf(elapsed.as_secs_f64() / half_life)
+    }
+
+    /// Weighs `actor` for a job placed on a segment with `headroom` left.
+    pub fn assess(
+        &self,
+        actor: &ActorProfile,
+        job: &EcologicalJobSpec,
+        headroom: f64,
+        now: SystemTime,
+    ) -> FairShareDecision {
+        self.assess_flops(
+            &actor.actor_id,
+            actor.ecological_priority_score,
+            job.expected_flops,
+            headroom,
+            now,
+        )
+    }
+
+    /// `assess` for an actor known by id and priority score and a bare FLOP
+    /// count, e.g. to weigh a deferred job again before it is released.
+    pub fn assess_flops(
+        &self,
+        actor_id: &ActorId,
+        priority_score: f32,
+        expected_flops: f64,
+        headroom: f64,
+        now: SystemTime,
+    ) -> FairShareDecision {
+        let c = &self.config;
+        let priority = f64::from(priority_score).clamp(0.0, 1.0);
+        let recent_usage_flops = self.recent_usage(actor_id, now);
+        let usage = recent_usage_flops / c.usage_scale_flops.max(f64::MIN_POSITIVE);
+        let weight = (1.0 + c.priority_weight.max(0.0) * priority)
+            / (1.0 + c.usage_weight.max(0.0) * usage);
+        let burst_flops = c.base_burst_flops * weight.min(c.max_burst_multiplier.max(0.0));
+        let contended = headroom < c.contention_headroom;
+        let within_burst = !contended || expected_flops <= burst_flops;
+
+        let basis = format!(
+            "Priority {:.2} and {:.3e} recently used FLOPs (half-life {}s) give a fair-share \
+             weight of {:.2}.",
+            priority,
+            recent_usage_flops,
+            c.half_life.as_secs(),
+            weight
+        );
+        let effect = if !contended {
+            format!(
+                "The segment is not contended ({:.0}% headroom), so the weight does not apply.",
+                headroom * 100.0
+            )
+        } else if within_burst {
+            format!(
+                "The segment is contended ({:.0}% headroom); the {:.3e} FLOP job fits the \
+                 {:.3e} FLOP burst allowance and starts now.",
+                headroom * 100.0,
+                expected_flops,
+                burst_flops
+            )
+        } else {
+            format!(
+                "The segment is contended ({:.0}% headroom); the {:.3e} FLOP job exceeds the \
+                 {:.3e} FLOP burst allowance and queues behind higher-weight work.",
+                headroom * 100.0,
+                expected_flops,
+                burst_flops
+            )
+        };
+
+        FairShareDecision {
+            priority_score: priority,
+            recent_usage_flops,
+            weight,
+            contended,
+            burst_flops,
+            within_burst,
+            explanation: format!("{} {}", basis, effect),
+        }
+    }
+}
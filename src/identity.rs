@@ -1,5 +1,9 @@
-use crate::types::{ActorId, SegmentId};
+use crate::error::OrchestratorError;
+use crate::types::{ActorId, EcologicalJobSpec, SegmentId};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActorProfile {
@@ -9,16 +13,150 @@ pub struct ActorProfile {
     pub ecological_priority_score: f32, // higher = more public-benefit
 }
 
+/// What an identity provider asserts about an actor. Every field but `roles`
+/// is required; an actor without roles is one with no role-based access.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActorClaims {
+    pub actor_id: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    pub clearance: u8,
+    pub eco_priority: f32,
+}
+
+impl ActorClaims {
+    /// Checks the claims and turns them into a profile. Claims that do not
+    /// identify an actor, or that carry blank roles or a priority outside
+    /// 0..=1, fail authentication.
+    pub fn into_profile(self) -> anyhow::Result<ActorProfile> {
+        let rejected = OrchestratorError::AuthenticationFailed;
+        let actor_id = self.actor_id.trim();
+        if actor_id.is_empty() {
+            return Err(rejected("identity claims carry no actor_id".into()).into());
+        }
+        if self.roles.iter().any(|r| r.trim().is_empty()) {
+            return Err(rejected(format!("blank role in the claims for {}", actor_id)).into());
+        }
+        if !(0.0..=1.0).contains(&self.eco_priority) {
+            return Err(rejected(format!(
+                "eco_priority {} for {} is outside 0..=1",
+                self.eco_priority, actor_id
+            ))
+            .into());
+        }
+        Ok(ActorProfile {
+            actor_id: ActorId(actor_id.to_string()),
+            roles: self.roles.iter().map(|r| r.trim().to_string()).collect(),
+            clearance_level: self.clearance,
+            ecological_priority_score: self.eco_priority,
+        })
+    }
+}
+
+/// Remembers resolved profiles by session token for `ttl`, so repeated calls
+/// from one session do not go back to the identity provider. Failures are
+/// never cached, and a zero TTL turns caching off.
+#[derive(Debug)]
+pub struct ResolutionCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (ActorProfile, Instant)>>,
+}
+
+impl ResolutionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, session_token: &str) -> Option<ActorProfile> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(session_token) {
+            Some((profile, expires)) if *expires > Instant::now() => Some(profile.clone()),
+            Some(_) => {
+                entries.remove(session_token);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, session_token: &str, profile: &ActorProfile) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, expires)| *expires > now);
+        entries.insert(session_token.to_string(), (profile.clone(), now + self.ttl));
+    }
+
+    /// Forgets a session, e.g. after logout.
+    pub fn invalidate(&self, session_token: &str) {
+        self.entries.lock().unwrap().remove(session_token);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZoneResolution {
     pub segment_id: SegmentId,
     pub trust_level: u8,
 }
 
+/// A segment left out of a job's placement, and why.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneExclusion {
+    pub segment_id: SegmentId,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ZoneEligibility {
+    pub eligible: Vec<ZoneResolution>,
+    pub excluded: Vec<ZoneExclusion>,
+}
+
+impl ZoneEligibility {
+    /// One line naming every excluded segment with its reasons.
+    pub fn explain_exclusions(&self) -> String {
+        if self.excluded.is_empty() {
+            return "no segments are registered".into();
+        }
+        self.excluded
+            .iter()
+            .map(|e| format!("{}: {}", e.segment_id.0, e.reasons.join("; ")))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+#[async_trait::async_trait]
 pub trait IdentityResolver: Send + Sync {
-    fn resolve_actor(&self, session_token: &str) -> anyhow::Result<ActorProfile>;
+    async fn resolve_actor(&self, session_token: &str) -> anyhow::Result<ActorProfile>;
 }
 
+#[async_trait::async_trait]
 pub trait ZoneResolver: Send + Sync {
-    fn resolve_zone(&self, actor: &ActorProfile) -> anyhow::Result<ZoneResolution>;
+    async fn resolve_zone(&self, actor: &ActorProfile) -> anyhow::Result<ZoneResolution>;
+
+    /// Every segment the actor is trusted to run on, preferred first.
+    /// Resolvers that only know one segment per actor can rely on the default.
+    async fn resolve_zones(&self, actor: &ActorProfile) -> anyhow::Result<Vec<ZoneResolution>> {
+        Ok(vec![self.resolve_zone(actor).await?])
+    }
+
+    /// The segments `job` may be placed on, preferred first, and the ones it
+    /// may not with the reasons. Resolvers that do not look at the job can
+    /// rely on the default, which excludes nothing.
+    async fn resolve_for_job(
+        &self,
+        actor: &ActorProfile,
+        _job: &EcologicalJobSpec,
+    ) -> anyhow::Result<ZoneEligibility> {
+        Ok(ZoneEligibility {
+            eligible: self.resolve_zones(actor).await?,
+            excluded: Vec::new(),
+        })
+    }
 }
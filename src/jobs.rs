@@ -0,0 +1,119 @@
+use crate::approval::ApprovalRequest;
+use crate::error::OrchestratorError;
+use crate::quota::ReservationState;
+use crate::receipts::SignedReceipt;
+use crate::types::{
+    ActorId, DeferralStatus, FairUseReceipt, JobExecutionPlan, ReservationId, UsageWindowId,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Execution state of a planned job, alongside (not instead of) the state of
+/// its quota reservation. `Planned` jobs start once their reservation is
+/// active; `Suspended` jobs were stopped under grid stress, keep their hold
+/// and resume once it eases. `Completed` and `Cancelled` are final.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Planned,
+    Running,
+    Completed,
+    Cancelled,
+    Suspended,
+}
+
+impl JobStatus {
+    pub fn can_transition_to(self, next: JobStatus) -> bool {
+        use JobStatus::*;
+        matches!(
+            (self, next),
+            (Planned, Running)
+                | (Planned, Cancelled)
+                | (Running, Completed)
+                | (Running, Cancelled)
+                | (Running, Suspended)
+                | (Suspended, Running)
+                | (Suspended, Completed)
+                | (Suspended, Cancelled)
+        )
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Planned => "planned",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Suspended => "suspended",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobReceipt {
+    pub receipt: FairUseReceipt,
+    pub signed_receipt: Option<SignedReceipt>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub plan: JobExecutionPlan,
+    pub actor_id: ActorId,
+    pub window_id: UsageWindowId,
+    // the requester's ecological_priority_score when the job was planned
+    #[serde(default)]
+    pub priority_score: f32,
+    pub status: JobStatus,
+    pub planned_at: SystemTime,
+    pub started_at: Option<SystemTime>,
+    pub finished_at: Option<SystemTime>,
+    // handed to the executor by `launch_job` rather than started by hand
+    #[serde(default)]
+    pub launched: bool,
+    pub receipt: Option<JobReceipt>,
+}
+
+impl JobRecord {
+    pub fn reservation_id(&self) -> &ReservationId {
+        &self.plan.reservation_id
+    }
+
+    /// Moves to `next`, failing if the lifecycle does not allow it.
+    pub fn advance(&mut self, next: JobStatus) -> Result<JobStatus> {
+        if !self.status.can_transition_to(next) {
+            return Err(OrchestratorError::Conflict(format!(
+                "job {} is {} and cannot become {}",
+                self.plan.reservation_id.0,
+                self.status.as_str(),
+                next.as_str()
+            ))
+            .into());
+        }
+        Ok(std::mem::replace(&mut self.status, next))
+    }
+}
+
+/// A job as the API reports it: the stored record plus the live state of its
+/// reservation, deferral and approval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanView {
+    pub job: JobRecord,
+    pub reservation_state: ReservationState,
+    pub deferral: Option<DeferralStatus>,
+    pub approval: Option<ApprovalRequest>,
+}
+
+#[async_trait::async_trait]
+pub trait JobStore: Send + Sync {
+    async fn insert(&self, record: &JobRecord) -> Result<()>;
+
+    async fn get(&self, reservation_id: &ReservationId) -> Result<JobRecord>;
+
+    /// Every job currently in `status`, oldest plan first.
+    async fn list_by_status(&self, status: JobStatus) -> Result<Vec<JobRecord>>;
+
+    /// Overwrites the stored record only if its status is still `expected`,
+    /// so concurrent start/complete/cancel calls cannot both win.
+    async fn replace(&self, record: &JobRecord, expected: JobStatus) -> Result<()>;
+}
@@ -0,0 +1,294 @@
+//! Identity from signed bearer tokens, without a call to an identity service.
+//!
+//! Tokens are JWTs signed with RS256 or EdDSA by a key in a local JWKS file
+//! and must name that key in their `kid` header. Besides `exp`, `aud` and
+//! `iss`, they carry the actor's DID as `sub` and the `ActorClaims` fields
+//! `roles`, `clearance` and `eco_priority`. Keys are rotated by editing the
+//! JWKS file: it is re-read when it changes (see `reload`) and when a token
+//! names a key that has not been loaded yet, at most once every 10 seconds
+//! by default.
+
+use crate::error::OrchestratorError;
+use crate::identity::{ActorClaims, ActorProfile, IdentityResolver};
+use anyhow::{Context, Result};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Deserialize)]
+struct TokenClaims {
+    sub: String,
+    #[serde(default)]
+    roles: Vec<String>,
+    clearance: u8,
+    eco_priority: f32,
+}
+
+#[derive(Clone)]
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// The signature keys of a JWKS document, by `kid`. Keys without a `kid`,
+/// keys meant for encryption and keys that are neither RSA nor Ed25519 are
+/// left out.
+#[derive(Clone, Default)]
+pub struct KeySet {
+    keys: HashMap<String, VerificationKey>,
+}
+
+fn algorithm_for(jwk: &Jwk) -> Option<Algorithm> {
+    let algorithm = match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => Algorithm::RS256,
+        AlgorithmParameters::OctetKeyPair(p) if p.curve == EllipticCurve::Ed25519 => {
+            Algorithm::EdDSA
+        }
+        _ => return None,
+    };
+    // A key that declares an algorithm is only used for that algorithm.
+    match (&jwk.common.key_algorithm, algorithm) {
+        (None, algorithm)
+        | (Some(KeyAlgorithm::RS256), algorithm @ Algorithm::RS256)
+        | (Some(KeyAlgorithm::EdDSA), algorithm @ Algorithm::EdDSA) => Some(algorithm),
+        _ => None,
+    }
+}
+
+impl KeySet {
+    pub fn parse(text: &str) -> Result<Self> {
+        let set: JwkSet = serde_json::from_str(text)?;
+        let mut keys = HashMap::new();
+        for jwk in &set.keys {
+            if matches!(jwk.common.public_key_use, Some(PublicKeyUse::Encryption)) {
+                continue;
+            }
+            let (Some(kid), Some(algorithm)) = (&jwk.common.key_id, algorithm_for(jwk)) else {
+                continue;
+            };
+            let key = DecodingKey::from_jwk(jwk).with_context(|| format!("key {}", kid))?;
+            keys.insert(kid.clone(), VerificationKey { algorithm, key });
+        }
+        if keys.is_empty() {
+            anyhow::bail!("no RS256 or EdDSA signature keys with a kid");
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading JWKS {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("parsing JWKS {}", path.display()))
+    }
+
+    pub fn key_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.keys.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+/// `IdentityResolver` that treats the session token as a signed bearer token
+/// (an optional `Bearer ` prefix is ignored). Every failure to verify it is
+/// an authentication failure.
+pub struct JwtIdentityResolver {
+    keys: RwLock<Arc<KeySet>>,
+    source: Option<PathBuf>,
+    loaded_modified: Mutex<Option<SystemTime>>,
+    unknown_key_reload_interval: Duration,
+    last_unknown_key_reload: Mutex<Option<Instant>>,
+    issuer: String,
+    audience: String,
+    leeway: Duration,
+}
+
+// The JWKS file's keys and modification time, or None if it has not changed
+// since `loaded`.
+fn read_if_changed(
+    path: &Path,
+    loaded: Option<SystemTime>,
+) -> Result<Option<(KeySet, Option<SystemTime>)>> {
+    let modified = std::fs::metadata(path)?.modified().ok();
+    if modified.is_some() && modified == loaded {
+        return Ok(None);
+    }
+    Ok(Some((KeySet::load(path)?, modified)))
+}
+
+impl JwtIdentityResolver {
+    pub fn new(keys: KeySet, issuer: &str, audience: &str) -> Self {
+        Self {
+            keys: RwLock::new(Arc::new(keys)),
+            source: None,
+            loaded_modified: Mutex::new(None),
+            unknown_key_reload_interval: Duration::from_secs(10),
+            last_unknown_key_reload: Mutex::new(None),
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            leeway: Duration::from_secs(30),
+        }
+    }
+
+    pub fn from_jwks_file(path: impl Into<PathBuf>, issuer: &str, audience: &str) -> Result<Self> {
+        let path = path.into();
+        let modified = std::fs::metadata(&path)?.modified().ok();
+        let mut resolver = Self::new(KeySet::load(&path)?, issuer, audience);
+        resolver.source = Some(path);
+        *resolver.loaded_modified.get_mut().unwrap() = modified;
+        Ok(resolver)
+    }
+
+    /// Clock skew tolerated on `exp` and `nbf`; 30 seconds by default.
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// How often a token naming an unknown key may trigger a reload; tokens
+    /// with made-up key ids otherwise force a re-read of the JWKS file each.
+    pub fn with_unknown_key_reload_interval(mut self, interval: Duration) -> Self {
+        self.unknown_key_reload_interval = interval;
+        self
+    }
+
+    pub fn keys(&self) -> Arc<KeySet> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// Re-reads the JWKS file if it changed since the last load. Returns
+    /// whether a new key set was installed; on error the old one stays.
+    pub fn reload(&self) -> Result<bool> {
+        let Some(path) = &self.source else {
+            return Ok(false);
+        };
+        let loaded = *self.loaded_modified.lock().unwrap();
+        Ok(self.install(read_if_changed(path, loaded)?))
+    }
+
+    /// `reload` with the file read on the blocking thread pool.
+    async fn reload_off_runtime(&self) -> Result<bool> {
+        let Some(path) = self.source.clone() else {
+            return Ok(false);
+        };
+        let loaded = *self.loaded_modified.lock().unwrap();
+        let read = tokio::task::spawn_blocking(move || read_if_changed(&path, loaded)).await??;
+        Ok(self.install(read))
+    }
+
+    fn install(&self, read: Option<(KeySet, Option<SystemTime>)>) -> bool {
+        let Some((keys, modified)) = read else {
+            return false;
+        };
+        *self.keys.write().unwrap() = Arc::new(keys);
+        *self.loaded_modified.lock().unwrap() = modified;
+        true
+    }
+
+    /// Polls the JWKS file every `interval` and reloads it when it changes.
+    pub fn spawn_hot_reload(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.reload_off_runtime().await {
+                    tracing::warn!(error = ?e, "JWKS reload failed, keeping previous keys");
+                }
+            }
+        })
+    }
+
+    /// Whether `token` names a key that may have been rotated in since the
+    /// last load and a reload is due; claims the reload if so.
+    fn claim_unknown_key_reload(&self, token: &str) -> bool {
+        if self.source.is_none() {
+            return false;
+        }
+        let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+        let Some(kid) = jsonwebtoken::decode_header(token).ok().and_then(|h| h.kid) else {
+            return false;
+        };
+        if self.keys().keys.contains_key(&kid) {
+            return false;
+        }
+        let mut last = self.last_unknown_key_reload.lock().unwrap();
+        let now = Instant::now();
+        if last.is_some_and(|t| now.duration_since(t) < self.unknown_key_reload_interval) {
+            return false;
+        }
+        *last = Some(now);
+        true
+    }
+
+    /// Verifies `token` and builds the profile its claims describe.
+    pub fn verify(&self, token: &str) -> Result<ActorProfile> {
+        if self.claim_unknown_key_reload(token) {
+            self.reload().map_err(reload_failed)?;
+        }
+        self.verify_loaded(token)
+    }
+
+    fn verify_loaded(&self, token: &str) -> Result<ActorProfile> {
+        let rejected = |why: String| -> anyhow::Error {
+            OrchestratorError::AuthenticationFailed(why).into()
+        };
+        let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| rejected(format!("malformed token: {}", e)))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| rejected("token does not name its signing key".into()))?;
+        let key = self
+            .keys()
+            .keys
+            .get(&kid)
+            .cloned()
+            .ok_or_else(|| rejected(format!("unknown signing key {}", kid)))?;
+        if header.alg != key.algorithm {
+            return Err(rejected(format!(
+                "token is signed with {:?} but key {} is for {:?}",
+                header.alg, kid, key.algorithm
+            )));
+        }
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
+        validation.validate_nbf = true;
+        validation.leeway = self.leeway.as_secs();
+        let claims = jsonwebtoken::decode::<TokenClaims>(token, &key.key, &validation)
+            .map_err(|e| rejected(format!("token rejected: {}", e)))?
+            .claims;
+
+        if !claims.sub.starts_with("did:") {
+            return Err(rejected(format!("token subject {} is not a DID", claims.sub)));
+        }
+        ActorClaims {
+            actor_id: claims.sub,
+            roles: claims.roles,
+            clearance: claims.clearance,
+            eco_priority: claims.eco_priority,
+        }
+        .into_profile()
+    }
+}
+
+#[async_trait::async_trait]
+impl IdentityResolver for JwtIdentityResolver {
+    async fn resolve_actor(&self, session_token: &str) -> Result<ActorProfile> {
+        if self.claim_unknown_key_reload(session_token) {
+            self.reload_off_runtime().await.map_err(reload_failed)?;
+        }
+        self.verify_loaded(session_token)
+    }
+}
+
+fn reload_failed(e: anyhow::Error) -> OrchestratorError {
+    OrchestratorError::IdentityUnavailable(format!("could not reload the JWKS: {:#}", e))
+}
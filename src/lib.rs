@@ -0,0 +1,60 @@
+//! Ecological orchestrator: plans compute jobs against per-actor energy and
+//! carbon budgets, places them on trusted segments and keeps a tamper-evident
+//! record of every decision.
+//!
+//! The modules live at the crate root; `eol` re-exports them under the paths
+//! the storage adapters, the HTTP API and downstream code use, with the
+//! planner exposed as `eol::orchestrator`. Concrete adapters for external
+//! services sit in `identity_adapters`, `metrics_client`, `storage` and `api`.
+
+pub mod approval;
+pub mod clearance;
+pub mod did;
+pub mod energy;
+pub mod error;
+pub mod executor;
+pub mod fair_share;
+pub mod identity;
+pub mod jobs;
+pub mod jwt;
+pub mod logging;
+pub mod metrics_client;
+pub mod policy;
+pub mod policy_rules;
+pub mod preemption;
+pub mod quota;
+pub mod receipts;
+pub mod reconcile;
+pub mod routing;
+pub mod scheduler;
+pub mod segments;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod types;
+pub mod windows;
+
+#[path = "eol/identity.rs"]
+pub mod identity_adapters;
+
+pub mod storage {
+    pub mod approval_pg;
+    pub mod jobs_pg;
+    pub mod logging_pg;
+    pub mod quota_pg;
+    pub mod segments_pg;
+}
+
+pub mod api {
+    pub mod http;
+}
+
+pub mod eol {
+    pub use crate::{
+        approval, clearance, did, energy, error, executor, fair_share, identity, jobs, jwt,
+        logging, policy, policy_rules, preemption, quota, receipts, reconcile, scheduler,
+        segments, types, windows,
+    };
+    pub use crate::routing as orchestrator;
+    #[cfg(any(test, feature = "testing"))]
+    pub use crate::testing;
+}
@@ -3,6 +3,8 @@ use crate::types::{
 };
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LogEventType {
@@ -10,8 +12,11 @@ pub enum LogEventType {
     QuotaReserved,
     JobStarted,
     JobCompleted,
+    JobCancelled,
     PolicyEvaluated,
     StabilityChecked,
+    ApprovalRequested,
+    ApprovalReviewed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,11 +29,193 @@ pub struct EcologicalLogEvent {
     pub metadata: serde_json::Value,
 }
 
+/// `prev_hash` of the first entry in a chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An event as stored by an `ImmutableLogger`: numbered, timestamped and
+/// linked to its predecessor by hash, so rewriting, dropping or reordering
+/// any stored entry breaks the chain from that point on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainedLogEvent {
+    pub sequence: u64,
+    pub recorded_at: SystemTime, // microsecond precision
+    pub prev_hash: String,
+    pub hash: String,
+    // the event exactly as hashed; stores must keep these bytes verbatim
+    pub event_json: String,
+}
+
+fn unix_micros(t: SystemTime) -> u128 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_micros()
+}
+
+/// sha256 over the previous hash, sequence, timestamp and event bytes, hex
+/// encoded.
+pub fn chain_hash(
+    prev_hash: &str,
+    sequence: u64,
+    recorded_at: SystemTime,
+    event_json: &str,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(format!("\n{}\n{}\n", sequence, unix_micros(recorded_at)).as_bytes());
+    hasher.update(event_json.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+impl ChainedLogEvent {
+    /// Builds the entry that follows `head` (sequence and hash of the current
+    /// last entry, `None` for an empty log).
+    pub fn link(
+        head: Option<(u64, &str)>,
+        event: &EcologicalLogEvent,
+        recorded_at: SystemTime,
+    ) -> Result<Self> {
+        let (sequence, prev_hash) = match head {
+            Some((sequence, hash)) => (sequence + 1, hash.to_string()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        // Truncate to what Postgres timestamps can hold so stored entries
+        // re-hash identically.
+        let recorded_at = UNIX_EPOCH + Duration::from_micros(unix_micros(recorded_at) as u64);
+        let event_json = serde_json::to_string(event)?;
+        Ok(Self {
+            sequence,
+            recorded_at,
+            hash: chain_hash(&prev_hash, sequence, recorded_at, &event_json),
+            prev_hash,
+            event_json,
+        })
+    }
+
+    pub fn event(&self) -> Result<EcologicalLogEvent> {
+        Ok(serde_json::from_str(&self.event_json)?)
+    }
+}
+
+#[async_trait::async_trait]
 pub trait ImmutableLogger: Send + Sync {
-    fn append(&self, event: &EcologicalLogEvent) -> Result<()>;
+    /// Assigns the next sequence number and links the event to the current
+    /// head; concurrent appends must not fork the chain.
+    async fn append(&self, event: &EcologicalLogEvent) -> Result<()>;
+
+    /// Up to `limit` entries starting at `from_sequence`, in sequence order.
+    async fn entries(&self, from_sequence: u64, limit: usize) -> Result<Vec<ChainedLogEvent>>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub sequence: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainVerification {
+    pub entries_checked: u64,
+    // hash of the last intact entry; compare against a previously published
+    // head to detect truncation of the tail
+    pub head_hash: String,
+    pub first_break: Option<BrokenLink>,
+}
+
+impl ChainVerification {
+    pub fn is_intact(&self) -> bool {
+        self.first_break.is_none()
+    }
+}
+
+/// Checks entries one at a time, in order, stopping at the first broken link.
+#[derive(Debug, Clone)]
+pub struct ChainVerifier {
+    next_sequence: u64,
+    prev_hash: String,
+    first_break: Option<BrokenLink>,
+}
+
+impl Default for ChainVerifier {
+    fn default() -> Self {
+        Self {
+            next_sequence: 0,
+            prev_hash: GENESIS_HASH.to_string(),
+            first_break: None,
+        }
+    }
+}
+
+impl ChainVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false once the chain is broken; later entries are ignored.
+    pub fn push(&mut self, entry: &ChainedLogEvent) -> bool {
+        if self.first_break.is_some() {
+            return false;
+        }
+        let reason = if entry.sequence != self.next_sequence {
+            Some(format!(
+                "expected sequence {}, found {} (entries missing or reordered)",
+                self.next_sequence, entry.sequence
+            ))
+        } else if entry.prev_hash != self.prev_hash {
+            Some("prev_hash does not match the preceding entry".to_string())
+        } else if chain_hash(&entry.prev_hash, entry.sequence, entry.recorded_at, &entry.event_json)
+            != entry.hash
+        {
+            Some("entry content does not match its hash".to_string())
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => {
+                self.first_break = Some(BrokenLink {
+                    sequence: self.next_sequence,
+                    reason,
+                });
+                false
+            }
+            None => {
+                self.prev_hash = entry.hash.clone();
+                self.next_sequence += 1;
+                true
+            }
+        }
+    }
+
+    pub fn finish(self) -> ChainVerification {
+        ChainVerification {
+            entries_checked: self.next_sequence,
+            head_hash: self.prev_hash,
+            first_break: self.first_break,
+        }
+    }
+}
+
+/// Walks the whole log `page_size` entries at a time.
+pub async fn verify_chain<L: ImmutableLogger + ?Sized>(
+    logger: &L,
+    page_size: usize,
+) -> Result<ChainVerification> {
+    let page_size = page_size.max(1);
+    let mut verifier = ChainVerifier::new();
+    let mut from = 0;
+    loop {
+        let page = logger.entries(from, page_size).await?;
+        for entry in &page {
+            if !verifier.push(entry) {
+                return Ok(verifier.finish());
+            }
+        }
+        if page.len() < page_size {
+            return Ok(verifier.finish());
+        }
+        from += page.len() as u64;
+    }
 }
 
-pub fn log_execution_plan<L: ImmutableLogger>(
+pub async fn log_execution_plan<L: ImmutableLogger>(
     logger: &L,
     plan: &JobExecutionPlan,
     actor: &ActorId,
@@ -44,10 +231,12 @@ pub fn log_execution_plan<L: ImmutableLogger>(
             "stability_decision": format!("{:?}", plan.stability_decision),
         }),
     };
-    logger.append(&event)
+    logger.append(&event).await
 }
 
-// Receipt generation would typically run after telemetry is reconciled.
+// Receipt generation runs after telemetry is reconciled (see
+// `EcologicalOrchestrator::finish_job`), which replaces the explanation.
+#[allow(clippy::too_many_arguments)]
 pub fn build_receipt(
     reservation_id: ReservationId,
     actor_id: ActorId,
@@ -73,5 +262,6 @@ pub fn build_receipt(
         allowance_remaining_carbon_kg,
         explanation: "Ecological job executed within configured FLOPs, energy, and carbon budgets; aligned with ALN ethical and stability constraints."
             .into(),
+        fair_share: None,
     }
 }
@@ -20,7 +20,7 @@ impl PrometheusTelemetry {
 
     async fn scrape(&self, url: &str) -> Result<Scrape> {
         let text = self.http.get(url).send().await?.text().await?;
-        let scrape = prometheus_parse::Scrape::parse(text.lines())?;
+        let scrape = prometheus_parse::Scrape::parse(text.lines().map(|l| Ok(l.to_owned())))?;
         Ok(scrape)
     }
 
@@ -42,7 +42,7 @@ impl PrometheusTelemetry {
 
 #[async_trait::async_trait]
 impl SegmentTelemetry for PrometheusTelemetry {
-    async fn get_segment_load_async(&self, segment_id: &SegmentId) -> Result<SegmentLoad> {
+    async fn get_segment_load(&self, segment_id: &SegmentId) -> Result<SegmentLoad> {
         let url = self
             .endpoints
             .get(&segment_id.0)
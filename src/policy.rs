@@ -1,36 +1,70 @@
+use crate::identity::ActorProfile;
 use crate::types::{CapabilityTier, EcologicalJobSpec};
 use anyhow::Result;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PolicyDecision {
     pub risk_score: f32,
     pub allowed_tiers: Vec<CapabilityTier>,
     pub requires_human_approval: bool,
+    // distinct reviewers needed, and the clearance each must hold, when
+    // requires_human_approval is set
+    pub required_approvals: u8,
+    pub min_reviewer_clearance: u8,
     pub notes: Vec<String>,
 }
 
+#[async_trait::async_trait]
 pub trait PolicyEngine: Send + Sync {
-    fn evaluate(&self, job: &EcologicalJobSpec) -> Result<PolicyDecision>;
+    async fn evaluate(
+        &self,
+        job: &EcologicalJobSpec,
+        actor: &ActorProfile,
+    ) -> Result<PolicyDecision>;
+}
+
+// Lets a shared engine (e.g. one with a hot-reload task) back the orchestrator.
+#[async_trait::async_trait]
+impl<P: PolicyEngine + ?Sized> PolicyEngine for Arc<P> {
+    async fn evaluate(
+        &self,
+        job: &EcologicalJobSpec,
+        actor: &ActorProfile,
+    ) -> Result<PolicyDecision> {
+        (**self).evaluate(job, actor).await
+    }
 }
 
 // Example stub aligned with NIST AI RMF + HITL for critical decisions.[file:1][file:2][file:5]
 pub struct SimplePolicyEngine;
 
+#[async_trait::async_trait]
 impl PolicyEngine for SimplePolicyEngine {
-    fn evaluate(&self, job: &EcologicalJobSpec) -> Result<PolicyDecision> {
-        let mut risk = 0.1;
+    async fn evaluate(
+        &self,
+        job: &EcologicalJobSpec,
+        _actor: &ActorProfile,
+    ) -> Result<PolicyDecision> {
+        let mut risk: f32 = 0.1;
         let mut requires_human = false;
+        let mut required_approvals: u8 = 0;
+        let mut min_reviewer_clearance: u8 = 0;
         let mut notes = Vec::new();
 
         if job.domain_tags.contains(&"geoengineering".to_string()) {
             risk = 0.9;
             requires_human = true;
+            required_approvals = 2;
+            min_reviewer_clearance = 4;
             notes.push("geoengineering scenario: enforce HITL & multi-party approval".into());
         }
 
         if job.domain_tags.contains(&"critical_infrastructure".to_string()) {
             risk = risk.max(0.8);
             requires_human = true;
+            required_approvals = required_approvals.max(1);
+            min_reviewer_clearance = min_reviewer_clearance.max(3);
             notes.push("critical infrastructure modeling: enforce HITL".into());
         }
 
@@ -38,6 +72,8 @@ impl PolicyEngine for SimplePolicyEngine {
             risk_score: risk,
             allowed_tiers: vec![CapabilityTier::Tier1, CapabilityTier::Tier2, CapabilityTier::Tier3],
             requires_human_approval: requires_human,
+            required_approvals,
+            min_reviewer_clearance,
             notes,
         })
     }
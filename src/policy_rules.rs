@@ -0,0 +1,493 @@
+//! Declarative policy: a document of rules, each a condition over the job and
+//! the requesting actor plus the effect it has on the decision. Every matching
+//! rule applies: risk is the highest of the matched scores, allowed tiers are
+//! intersected, human approval is required if any rule asks for it, and notes
+//! are collected in rule order.
+//!
+//! Documents are TOML, JSON or YAML, chosen by file extension:
+//!
+//! ```toml
+//! [defaults]
+//! risk_score = 0.1
+//! allowed_tiers = ["Tier1", "Tier2", "Tier3"]
+//!
+//! [[rules]]
+//! name = "geoengineering"
+//! when = { domain_tags_any = ["geoengineering"] }
+//! then = { risk_score = 0.9, requires_human_approval = true, required_approvals = 2,
+//!          min_reviewer_clearance = 4, note = "enforce HITL & multi-party approval" }
+//! ```
+
+use crate::identity::ActorProfile;
+use crate::policy::{PolicyDecision, PolicyEngine};
+use crate::types::{CapabilityTier, EcologicalJobSpec};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+fn all_tiers() -> Vec<CapabilityTier> {
+    vec![CapabilityTier::Tier1, CapabilityTier::Tier2, CapabilityTier::Tier3]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyDefaults {
+    pub risk_score: f32,
+    pub allowed_tiers: Vec<CapabilityTier>,
+}
+
+impl Default for PolicyDefaults {
+    fn default() -> Self {
+        Self {
+            risk_score: 0.1,
+            allowed_tiers: all_tiers(),
+        }
+    }
+}
+
+/// Every populated field must hold for the rule to match; an empty condition
+/// matches every job. Tag, role and keyword comparisons are exact except
+/// `purpose_keywords`, which is a case-insensitive substring match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleCondition {
+    pub domain_tags_any: Vec<String>,
+    pub domain_tags_all: Vec<String>,
+    pub domain_tags_none: Vec<String>,
+    pub tiers: Vec<CapabilityTier>,
+    pub min_flops: Option<f64>,
+    pub max_flops: Option<f64>,
+    pub min_duration_secs: Option<f64>,
+    pub max_duration_secs: Option<f64>,
+    pub purpose_keywords: Vec<String>,
+    pub roles_any: Vec<String>,
+    pub roles_none: Vec<String>,
+    pub min_clearance: Option<u8>,
+    pub max_clearance: Option<u8>,
+    pub min_priority: Option<f32>,
+    pub max_priority: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleEffect {
+    pub risk_score: Option<f32>,
+    pub allowed_tiers: Option<Vec<CapabilityTier>>,
+    pub requires_human_approval: bool,
+    pub required_approvals: Option<u8>,
+    pub min_reviewer_clearance: Option<u8>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub name: String,
+    #[serde(default)]
+    pub when: RuleCondition,
+    #[serde(default)]
+    pub then: RuleEffect,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDocument {
+    #[serde(default)]
+    pub defaults: PolicyDefaults,
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    /// The document is malformed and will not be loaded.
+    Error,
+    /// The document loads, but rules contradict each other or cannot match.
+    Conflict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyIssue {
+    pub severity: IssueSeverity,
+    pub rules: Vec<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for PolicyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            IssueSeverity::Error => "error",
+            IssueSeverity::Conflict => "conflict",
+        };
+        write!(f, "{} [{}]: {}", severity, self.rules.join(", "), self.message)
+    }
+}
+
+fn in_range<T: PartialOrd>(value: T, min: Option<T>, max: Option<T>) -> bool {
+    min.is_none_or(|m| value >= m) && max.is_none_or(|m| value <= m)
+}
+
+/// True when no value can satisfy both ranges.
+fn ranges_disjoint<T: PartialOrd + Copy>(
+    a: (Option<T>, Option<T>),
+    b: (Option<T>, Option<T>),
+) -> bool {
+    inverted(a.0, b.1) || inverted(b.0, a.1)
+}
+
+fn inverted<T: PartialOrd>(min: Option<T>, max: Option<T>) -> bool {
+    matches!((min, max), (Some(lo), Some(hi)) if lo > hi)
+}
+
+fn contains(list: &[String], value: &str) -> bool {
+    list.iter().any(|v| v == value)
+}
+
+impl RuleCondition {
+    pub fn matches(&self, job: &EcologicalJobSpec, actor: &ActorProfile) -> bool {
+        let has_tag = |t: &String| contains(&job.domain_tags, t);
+        let has_role = |r: &String| contains(&actor.roles, r);
+        let purpose = job.purpose.to_lowercase();
+
+        (self.domain_tags_any.is_empty() || self.domain_tags_any.iter().any(has_tag))
+            && self.domain_tags_all.iter().all(has_tag)
+            && !self.domain_tags_none.iter().any(has_tag)
+            && (self.tiers.is_empty() || self.tiers.contains(&job.requested_tier))
+            && in_range(job.expected_flops, self.min_flops, self.max_flops)
+            && in_range(
+                job.max_duration.as_secs_f64(),
+                self.min_duration_secs,
+                self.max_duration_secs,
+            )
+            && (self.purpose_keywords.is_empty()
+                || self
+                    .purpose_keywords
+                    .iter()
+                    .any(|k| purpose.contains(&k.to_lowercase())))
+            && (self.roles_any.is_empty() || self.roles_any.iter().any(has_role))
+            && !self.roles_none.iter().any(has_role)
+            && in_range(actor.clearance_level, self.min_clearance, self.max_clearance)
+            && in_range(
+                actor.ecological_priority_score,
+                self.min_priority,
+                self.max_priority,
+            )
+    }
+
+    /// Why the condition can never match, if it cannot.
+    fn unsatisfiable(&self) -> Option<String> {
+        if let Some(tag) = self
+            .domain_tags_all
+            .iter()
+            .find(|t| contains(&self.domain_tags_none, t))
+        {
+            return Some(format!("tag {:?} is both required and excluded", tag));
+        }
+        if !self.domain_tags_any.is_empty()
+            && self
+                .domain_tags_any
+                .iter()
+                .all(|t| contains(&self.domain_tags_none, t))
+        {
+            return Some("every tag in domain_tags_any is excluded".into());
+        }
+        if !self.roles_any.is_empty() && self.roles_any.iter().all(|r| contains(&self.roles_none, r))
+        {
+            return Some("every role in roles_any is excluded".into());
+        }
+        if inverted(self.min_flops, self.max_flops)
+            || inverted(self.min_duration_secs, self.max_duration_secs)
+            || inverted(self.min_clearance, self.max_clearance)
+            || inverted(self.min_priority, self.max_priority)
+        {
+            return Some("a min bound is above its max bound".into());
+        }
+        None
+    }
+
+    /// Conservative overlap test: false only when some field provably
+    /// separates the two conditions.
+    fn may_overlap(&self, other: &RuleCondition) -> bool {
+        let excludes = |required: &[String], excluded: &[String]| {
+            required.iter().any(|t| contains(excluded, t))
+        };
+        let excludes_any = |any: &[String], excluded: &[String]| {
+            !any.is_empty() && any.iter().all(|t| contains(excluded, t))
+        };
+        let tiers_disjoint = !self.tiers.is_empty()
+            && !other.tiers.is_empty()
+            && !self.tiers.iter().any(|t| other.tiers.contains(t));
+
+        !(tiers_disjoint
+            || excludes(&self.domain_tags_all, &other.domain_tags_none)
+            || excludes(&other.domain_tags_all, &self.domain_tags_none)
+            || excludes_any(&self.domain_tags_any, &other.domain_tags_none)
+            || excludes_any(&other.domain_tags_any, &self.domain_tags_none)
+            || excludes_any(&self.roles_any, &other.roles_none)
+            || excludes_any(&other.roles_any, &self.roles_none)
+            || ranges_disjoint(
+                (self.min_flops, self.max_flops),
+                (other.min_flops, other.max_flops),
+            )
+            || ranges_disjoint(
+                (self.min_duration_secs, self.max_duration_secs),
+                (other.min_duration_secs, other.max_duration_secs),
+            )
+            || ranges_disjoint(
+                (self.min_clearance, self.max_clearance),
+                (other.min_clearance, other.max_clearance),
+            )
+            || ranges_disjoint(
+                (self.min_priority, self.max_priority),
+                (other.min_priority, other.max_priority),
+            ))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PolicyFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl PolicyFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Ok(PolicyFormat::Toml),
+            Some("json") => Ok(PolicyFormat::Json),
+            Some("yaml") | Some("yml") => Ok(PolicyFormat::Yaml),
+            _ => anyhow::bail!(
+                "cannot tell policy format of {}; use .toml, .json, .yaml or .yml",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl PolicyDocument {
+    pub fn parse(text: &str, format: PolicyFormat) -> Result<Self> {
+        Ok(match format {
+            PolicyFormat::Toml => toml::from_str(text)?,
+            PolicyFormat::Json => serde_json::from_str(text)?,
+            PolicyFormat::Yaml => serde_yaml::from_str(text)?,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading policy {}", path.display()))?;
+        Self::parse(&text, PolicyFormat::from_path(path)?)
+            .with_context(|| format!("parsing policy {}", path.display()))
+    }
+
+    pub fn evaluate(&self, job: &EcologicalJobSpec, actor: &ActorProfile) -> PolicyDecision {
+        let mut decision = PolicyDecision {
+            risk_score: self.defaults.risk_score,
+            allowed_tiers: self.defaults.allowed_tiers.clone(),
+            requires_human_approval: false,
+            required_approvals: 0,
+            min_reviewer_clearance: 0,
+            notes: Vec::new(),
+        };
+
+        for rule in self.rules.iter().filter(|r| r.when.matches(job, actor)) {
+            let effect = &rule.then;
+            if let Some(risk) = effect.risk_score {
+                decision.risk_score = decision.risk_score.max(risk);
+            }
+            if let Some(tiers) = &effect.allowed_tiers {
+                decision.allowed_tiers.retain(|t| tiers.contains(t));
+            }
+            if effect.requires_human_approval {
+                decision.requires_human_approval = true;
+                decision.required_approvals = decision
+                    .required_approvals
+                    .max(effect.required_approvals.unwrap_or(1));
+                decision.min_reviewer_clearance = decision
+                    .min_reviewer_clearance
+                    .max(effect.min_reviewer_clearance.unwrap_or(0));
+            }
+            decision.notes.push(match &effect.note {
+                Some(note) => format!("{}: {}", rule.name, note),
+                None => format!("{}: matched", rule.name),
+            });
+        }
+
+        decision
+    }
+
+    /// Structural problems (`Error`) and rule pairs that can match the same job
+    /// yet leave it no tier to run at (`Conflict`).
+    pub fn validate(&self) -> Vec<PolicyIssue> {
+        let mut issues = Vec::new();
+        let error = |rules: Vec<String>, message: String| PolicyIssue {
+            severity: IssueSeverity::Error,
+            rules,
+            message,
+        };
+        let conflict = |rules: Vec<String>, message: String| PolicyIssue {
+            severity: IssueSeverity::Conflict,
+            rules,
+            message,
+        };
+
+        if !(0.0..=1.0).contains(&self.defaults.risk_score) {
+            issues.push(error(vec!["defaults".into()], "risk_score must be within 0..=1".into()));
+        }
+
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            let name = vec![rule.name.clone()];
+            if rule.name.trim().is_empty() {
+                issues.push(error(name.clone(), "rule name is empty".into()));
+            } else if !names.insert(rule.name.as_str()) {
+                issues.push(error(name.clone(), "duplicate rule name".into()));
+            }
+            if rule.then.risk_score.is_some_and(|r| !(0.0..=1.0).contains(&r)) {
+                issues.push(error(name.clone(), "risk_score must be within 0..=1".into()));
+            }
+            if !rule.then.requires_human_approval
+                && (rule.then.required_approvals.is_some()
+                    || rule.then.min_reviewer_clearance.is_some())
+            {
+                issues.push(error(
+                    name.clone(),
+                    "reviewer settings given without requires_human_approval".into(),
+                ));
+            }
+            if let Some(reason) = rule.when.unsatisfiable() {
+                issues.push(conflict(name.clone(), format!("can never match: {}", reason)));
+            }
+            if let Some(tiers) = &rule.then.allowed_tiers {
+                if !tiers.is_empty()
+                    && !tiers.iter().any(|t| self.defaults.allowed_tiers.contains(t))
+                {
+                    issues.push(conflict(
+                        name.clone(),
+                        "allows only tiers the defaults already exclude".into(),
+                    ));
+                }
+            }
+        }
+
+        for (i, a) in self.rules.iter().enumerate() {
+            for b in &self.rules[i + 1..] {
+                let (Some(ta), Some(tb)) = (&a.then.allowed_tiers, &b.then.allowed_tiers) else {
+                    continue;
+                };
+                // An explicitly empty list is a deliberate deny, not a conflict.
+                if ta.is_empty() || tb.is_empty() || ta.iter().any(|t| tb.contains(t)) {
+                    continue;
+                }
+                if a.when.may_overlap(&b.when) {
+                    issues.push(conflict(
+                        vec![a.name.clone(), b.name.clone()],
+                        format!(
+                            "both can match the same job but allow disjoint tiers {:?} and {:?}",
+                            ta, tb
+                        ),
+                    ));
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// `PolicyEngine` backed by a `PolicyDocument`. When loaded from a file it can
+/// be reloaded in place; a reload that fails to parse or validate keeps the
+/// previous document in force.
+pub struct RuleBasedPolicyEngine {
+    document: RwLock<Arc<PolicyDocument>>,
+    source: Option<PathBuf>,
+    loaded_modified: Mutex<Option<SystemTime>>,
+}
+
+fn check_errors(document: &PolicyDocument) -> Result<()> {
+    let errors: Vec<String> = document
+        .validate()
+        .into_iter()
+        .filter(|i| i.severity == IssueSeverity::Error)
+        .map(|i| i.to_string())
+        .collect();
+    if !errors.is_empty() {
+        anyhow::bail!("invalid policy: {}", errors.join("; "));
+    }
+    Ok(())
+}
+
+impl RuleBasedPolicyEngine {
+    pub fn new(document: PolicyDocument) -> Result<Self> {
+        check_errors(&document)?;
+        Ok(Self {
+            document: RwLock::new(Arc::new(document)),
+            source: None,
+            loaded_modified: Mutex::new(None),
+        })
+    }
+
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let modified = std::fs::metadata(&path)?.modified().ok();
+        let mut engine = Self::new(PolicyDocument::load(&path)?)?;
+        engine.source = Some(path);
+        *engine.loaded_modified.get_mut().unwrap() = modified;
+        Ok(engine)
+    }
+
+    pub fn document(&self) -> Arc<PolicyDocument> {
+        self.document.read().unwrap().clone()
+    }
+
+    /// Re-reads the source file if it changed since the last load. Returns
+    /// whether a new document was installed. A file that fails to parse or
+    /// validate is reported once and not re-read until it changes again.
+    pub fn reload(&self) -> Result<bool> {
+        let Some(path) = &self.source else {
+            return Ok(false);
+        };
+        let modified = std::fs::metadata(path)?.modified().ok();
+        if modified.is_some() && modified == *self.loaded_modified.lock().unwrap() {
+            return Ok(false);
+        }
+
+        *self.loaded_modified.lock().unwrap() = modified;
+        let document = PolicyDocument::load(path)?;
+        check_errors(&document)?;
+        *self.document.write().unwrap() = Arc::new(document);
+        Ok(true)
+    }
+
+    /// Polls the source file every `interval` and reloads it when it changes.
+    /// The file is read on the blocking thread pool.
+    pub fn spawn_hot_reload(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let engine = self.clone();
+                let reloaded = tokio::task::spawn_blocking(move || engine.reload()).await;
+                if let Err(e) = reloaded.map_err(anyhow::Error::from).and_then(|r| r) {
+                    tracing::warn!(error = ?e, "policy reload failed, keeping previous rules");
+                }
+            }
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PolicyEngine for RuleBasedPolicyEngine {
+    async fn evaluate(
+        &self,
+        job: &EcologicalJobSpec,
+        actor: &ActorProfile,
+    ) -> Result<PolicyDecision> {
+        Ok(self.document().evaluate(job, actor))
+    }
+}
@@ -0,0 +1,191 @@
+//! Pre-emption of running work when a segment comes under grid stress.
+//!
+//! The controller compares each telemetry reading for a segment with the one
+//! before it. A sharp fall in thermal margin or renewable share, or either
+//! dropping below its floor, marks the segment as stressed; running jobs on
+//! it are then picked as victims, lowest ecological priority first and, among
+//! equals, the heaviest tier first. Thermal stress suspends victims outright;
+//! a renewable shortfall first tries to move them down a tier. Suspended jobs
+//! are resumed once their segment reads unstressed again.
+
+use crate::jobs::JobRecord;
+use crate::types::{CapabilityTier, SegmentLoad};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "action")]
+pub enum PreemptionSignal {
+    Suspend,
+    Downgrade { tier: CapabilityTier },
+    Resume,
+}
+
+/// Whatever runs jobs, as far as pre-emption needs it. The orchestrator
+/// suspends, resumes or re-tiers the job only after the signal was delivered. An
+/// executor that cannot honour a downgrade refuses it with `InvalidRequest`,
+/// and the job is suspended instead.
+#[async_trait::async_trait]
+pub trait PreemptionExecutor: Send + Sync {
+    async fn signal(
+        &self,
+        record: &JobRecord,
+        signal: &PreemptionSignal,
+        rationale: &str,
+    ) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<E: PreemptionExecutor + ?Sized> PreemptionExecutor for Arc<E> {
+    async fn signal(
+        &self,
+        record: &JobRecord,
+        signal: &PreemptionSignal,
+        rationale: &str,
+    ) -> Result<()> {
+        (**self).signal(record, signal, rationale).await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreemptionPolicy {
+    pub min_thermal_margin_pct: f64,
+    pub min_renewable_share_pct: f64,
+    /// Fall, in percentage points since the previous reading, that counts as
+    /// sharp even while the segment is still above its floors.
+    pub sharp_drop_pct: f64,
+    pub max_victims_per_pass: usize,
+    /// Jobs from actors at or above this priority are never pre-empted.
+    pub protected_priority: f32,
+}
+
+impl Default for PreemptionPolicy {
+    fn default() -> Self {
+        Self {
+            min_thermal_margin_pct: 10.0,
+            min_renewable_share_pct: 20.0,
+            sharp_drop_pct: 15.0,
+            max_victims_per_pass: 1,
+            protected_priority: 0.9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StressKind {
+    Thermal,
+    Renewable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridStress {
+    pub kind: StressKind,
+    pub rationale: String,
+}
+
+pub struct PreemptionController {
+    policy: PreemptionPolicy,
+    executor: Arc<dyn PreemptionExecutor>,
+    last_seen: Mutex<HashMap<String, SegmentLoad>>,
+}
+
+impl PreemptionController {
+    pub fn new(policy: PreemptionPolicy, executor: Arc<dyn PreemptionExecutor>) -> Self {
+        Self {
+            policy,
+            executor,
+            last_seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn executor(&self) -> &dyn PreemptionExecutor {
+        self.executor.as_ref()
+    }
+
+    /// Records `load` as the segment's latest reading and reports stress
+    /// relative to the previous one. Thermal stress wins when both apply.
+    pub fn observe(&self, load: &SegmentLoad) -> Option<GridStress> {
+        let previous = self
+            .last_seen
+            .lock()
+            .unwrap()
+            .insert(load.segment_id.0.clone(), load.clone());
+        let p = &self.policy;
+        let before = previous.as_ref();
+        let fall = |before: Option<f64>, now: f64| before.map_or(0.0, |b| b - now);
+
+        let thermal_fall = fall(before.map(|l| l.thermal_margin_pct), load.thermal_margin_pct);
+        if load.thermal_margin_pct < p.min_thermal_margin_pct || thermal_fall >= p.sharp_drop_pct {
+            return Some(GridStress {
+                kind: StressKind::Thermal,
+                rationale: format!(
+                    "thermal margin on {} is {:.1}% (fell {:.1} points; floor {:.1}%)",
+                    load.segment_id.0,
+                    load.thermal_margin_pct,
+                    thermal_fall,
+                    p.min_thermal_margin_pct
+                ),
+            });
+        }
+        let renewable_fall = fall(before.map(|l| l.renewable_share_pct), load.renewable_share_pct);
+        if load.renewable_share_pct < p.min_renewable_share_pct
+            || renewable_fall >= p.sharp_drop_pct
+        {
+            return Some(GridStress {
+                kind: StressKind::Renewable,
+                rationale: format!(
+                    "renewable share on {} is {:.1}% (fell {:.1} points; floor {:.1}%)",
+                    load.segment_id.0,
+                    load.renewable_share_pct,
+                    renewable_fall,
+                    p.min_renewable_share_pct
+                ),
+            });
+        }
+        None
+    }
+
+    /// Picks up to `max_victims_per_pass` of `running` and what to signal
+    /// each of them.
+    pub fn select_victims(
+        &self,
+        mut running: Vec<JobRecord>,
+        stress: &GridStress,
+    ) -> Vec<(JobRecord, PreemptionSignal)> {
+        running.retain(|r| r.priority_score < self.policy.protected_priority);
+        running.sort_by(|a, b| {
+            a.priority_score
+                .total_cmp(&b.priority_score)
+                .then(tier_rank(&b.plan.approved_tier).cmp(&tier_rank(&a.plan.approved_tier)))
+                // the most recently started job loses the least work
+                .then(b.started_at.cmp(&a.started_at))
+        });
+        running
+            .into_iter()
+            .take(self.policy.max_victims_per_pass)
+            .map(|record| {
+                let lower = match record.plan.approved_tier {
+                    CapabilityTier::Tier3 => Some(CapabilityTier::Tier2),
+                    CapabilityTier::Tier2 => Some(CapabilityTier::Tier1),
+                    CapabilityTier::Tier1 => None,
+                };
+                let signal = match (stress.kind, lower) {
+                    (StressKind::Renewable, Some(tier)) => PreemptionSignal::Downgrade { tier },
+                    _ => PreemptionSignal::Suspend,
+                };
+                (record, signal)
+            })
+            .collect()
+    }
+}
+
+fn tier_rank(tier: &CapabilityTier) -> u8 {
+    match tier {
+        CapabilityTier::Tier1 => 1,
+        CapabilityTier::Tier2 => 2,
+        CapabilityTier::Tier3 => 3,
+    }
+}
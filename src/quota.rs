@@ -1,25 +1,376 @@
+use crate::error::{OrchestratorError, QuotaDimension, RemainingAllowance};
 use crate::types::{
-    ActorId, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec, ReservationId,
+    ActorId, BudgetLevel, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec, ReservationId,
     UsageSnapshot, UsageWindowId,
 };
 use anyhow::Result;
-use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+/// Lifecycle of a quota hold. Reservations start `Pending`, become `Active`
+/// once the plan is cleared to run, and end as `Committed` (actual usage
+/// recorded), `Released` (cancelled) or `Expired` (swept after overrunning
+/// the job's `max_duration`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReservationState {
+    Pending,
+    Active,
+    Committed,
+    Released,
+    Expired,
+}
+
+impl ReservationState {
+    /// Outstanding holds count against the window's allowance.
+    pub fn is_outstanding(self) -> bool {
+        matches!(self, ReservationState::Pending | ReservationState::Active)
+    }
+
+    /// An expired hold can still be committed, so a job that outran its hold
+    /// is charged for what it used.
+    pub fn can_transition_to(self, next: ReservationState) -> bool {
+        use ReservationState::*;
+        matches!(
+            (self, next),
+            (Pending, Active)
+                | (Pending, Released)
+                | (Pending, Expired)
+                | (Active, Committed)
+                | (Active, Released)
+                | (Active, Expired)
+                | (Expired, Committed)
+        )
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReservationState::Pending => "pending",
+            ReservationState::Active => "active",
+            ReservationState::Committed => "committed",
+            ReservationState::Released => "released",
+            ReservationState::Expired => "expired",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "pending" => ReservationState::Pending,
+            "active" => ReservationState::Active,
+            "committed" => ReservationState::Committed,
+            "released" => ReservationState::Released,
+            "expired" => ReservationState::Expired,
+            other => anyhow::bail!("unknown reservation state {:?}", other),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    pub id: ReservationId,
+    pub actor_id: ActorId,
+    pub window_id: UsageWindowId,
+    pub expected_flops: f64,
+    pub expected_energy_kwh: f64,
+    pub expected_carbon_kg: f64,
+    pub tier: CapabilityTier,
+    pub max_duration: Duration,
+    pub state: ReservationState,
+    pub created_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+/// Where an actor stands in a window: the allowance, what is committed and
+/// held against it, the holds themselves and the headroom left for new jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowUsageReport {
+    pub actor_id: ActorId,
+    pub window_id: UsageWindowId,
+    pub allowance: ComputeEnergyAllowance,
+    pub usage: UsageSnapshot,
+    pub outstanding: Vec<Reservation>,
+    pub remaining: RemainingAllowance,
+}
+
+/// Where an actor sits in the budget hierarchy. Either link may be missing;
+/// an actor outside any project is only bound by its own allowance.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetPath {
+    pub organisation: Option<String>,
+    pub project: Option<String>,
+}
+
+/// Consumption at one level of the hierarchy for a window. Group usage is
+/// the sum over every member actor; `allowance` and `remaining` are `None`
+/// where the level has no cap of its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetUsageNode {
+    pub level: BudgetLevel,
+    pub id: String,
+    pub window_id: UsageWindowId,
+    pub allowance: Option<ComputeEnergyAllowance>,
+    pub usage: UsageSnapshot,
+    pub remaining: Option<RemainingAllowance>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BudgetUsageNode>,
+}
+
+/// Measured usage reported when a reservation is committed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservationActuals {
+    pub flops_used: f64,
+    pub energy_kwh_used: f64,
+    pub carbon_kg_emitted: f64,
+}
+
+#[async_trait::async_trait]
 pub trait QuotaStore: Send + Sync {
-    fn get_allowance(&self, actor: &ActorId, window: &UsageWindowId)
+    async fn get_allowance(&self, actor: &ActorId, window: &UsageWindowId)
         -> Result<ComputeEnergyAllowance>;
 
-    fn get_usage(&self, actor: &ActorId, window: &UsageWindowId) -> Result<UsageSnapshot>;
+    /// Like `get_allowance`, but `None` when the window has no allowance.
+    async fn find_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>>;
+
+    /// Stores `allowance` for the window unless one already exists. Returns
+    /// whether it was inserted.
+    async fn provision_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool>;
+
+    /// Committed usage for the window plus the amounts still held by
+    /// outstanding (pending or active) reservations.
+    async fn get_usage(&self, actor: &ActorId, window: &UsageWindowId) -> Result<UsageSnapshot>;
+
+    /// The project and organisation the actor's usage also counts against.
+    async fn budget_path(&self, actor: &ActorId) -> Result<BudgetPath>;
+
+    /// The organisation or project allowance for the window, `None` when the
+    /// group is uncapped.
+    async fn find_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>>;
+
+    /// Stores the organisation or project allowance for the window unless one
+    /// already exists. Returns whether it was inserted.
+    async fn provision_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool>;
 
-    fn reserve_quota(
+    /// `get_usage` summed over every actor in the organisation or project.
+    async fn get_group_usage(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<UsageSnapshot>;
+
+    /// Direct members of a group, sorted: the projects of an organisation or
+    /// the actors of a project. Actors have none.
+    async fn budget_children(&self, level: BudgetLevel, group_id: &str) -> Result<Vec<String>>;
+
+    /// Reserves the expected amounts against the actor's allowance for the
+    /// window and against every capped group on its `budget_path`.
+    /// Implementations must check headroom (committed usage plus outstanding
+    /// reservations) at every level and insert the reservation atomically, so
+    /// concurrent callers cannot jointly overspend any of them. The new
+    /// reservation is `Pending` and expires `job.max_duration` after the later
+    /// of now and `job.deadline`, so deferred jobs keep their hold while they
+    /// wait.
+    async fn reserve_quota(
         &self,
         actor: &ActorId,
         window: &UsageWindowId,
-        expected_flops: f64,
+        job: &EcologicalJobSpec,
         expected_energy_kwh: f64,
         expected_carbon_kg: f64,
-        tier: &CapabilityTier,
     ) -> Result<ReservationId>;
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation>;
+
+    /// Pending and active reservations for the window, oldest first.
+    async fn outstanding_reservations(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Vec<Reservation>>;
+
+    /// Moves a pending reservation to `Active` and restarts its expiry clock
+    /// so the job gets its full `max_duration` from activation.
+    async fn activate(&self, reservation_id: &ReservationId) -> Result<()>;
+
+    /// Pushes a pending reservation's expiry out to `until` unless it already
+    /// expires later, e.g. to keep a held plan's hold through human review.
+    async fn extend_hold(&self, reservation_id: &ReservationId, until: SystemTime) -> Result<()>;
+
+    /// Moves an outstanding reservation to `tier` and lowers its expected
+    /// energy and carbon to the given figures, handing the difference back to
+    /// the window. Amounts are never raised.
+    async fn shrink_hold(
+        &self,
+        reservation_id: &ReservationId,
+        tier: &CapabilityTier,
+        expected_energy_kwh: f64,
+        expected_carbon_kg: f64,
+    ) -> Result<()>;
+
+    /// Converts an active reservation into recorded usage for its window.
+    async fn commit_usage(
+        &self,
+        reservation_id: &ReservationId,
+        actuals: &ReservationActuals,
+    ) -> Result<()>;
+
+    /// Cancels an outstanding reservation, returning its hold to the window.
+    async fn release(&self, reservation_id: &ReservationId) -> Result<()>;
+
+    /// Expires every outstanding reservation whose deadline is before
+    /// `cutoff`, returning the ids that were expired.
+    async fn expire_reservations(&self, cutoff: SystemTime) -> Result<Vec<ReservationId>>;
+}
+
+#[async_trait::async_trait]
+impl<Q: QuotaStore + ?Sized> QuotaStore for Arc<Q> {
+    async fn get_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<ComputeEnergyAllowance> {
+        (**self).get_allowance(actor, window).await
+    }
+
+    async fn find_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>> {
+        (**self).find_allowance(actor, window).await
+    }
+
+    async fn provision_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool> {
+        (**self).provision_allowance(actor, window, allowance).await
+    }
+
+    async fn get_usage(&self, actor: &ActorId, window: &UsageWindowId) -> Result<UsageSnapshot> {
+        (**self).get_usage(actor, window).await
+    }
+
+    async fn budget_path(&self, actor: &ActorId) -> Result<BudgetPath> {
+        (**self).budget_path(actor).await
+    }
+
+    async fn find_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>> {
+        (**self).find_group_allowance(level, group_id, window).await
+    }
+
+    async fn provision_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool> {
+        (**self)
+            .provision_group_allowance(level, group_id, window, allowance)
+            .await
+    }
+
+    async fn get_group_usage(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<UsageSnapshot> {
+        (**self).get_group_usage(level, group_id, window).await
+    }
+
+    async fn budget_children(&self, level: BudgetLevel, group_id: &str) -> Result<Vec<String>> {
+        (**self).budget_children(level, group_id).await
+    }
+
+    async fn reserve_quota(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        job: &EcologicalJobSpec,
+        expected_energy_kwh: f64,
+        expected_carbon_kg: f64,
+    ) -> Result<ReservationId> {
+        (**self)
+            .reserve_quota(actor, window, job, expected_energy_kwh, expected_carbon_kg)
+            .await
+    }
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        (**self).get_reservation(reservation_id).await
+    }
+
+    async fn outstanding_reservations(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Vec<Reservation>> {
+        (**self).outstanding_reservations(actor, window).await
+    }
+
+    async fn activate(&self, reservation_id: &ReservationId) -> Result<()> {
+        (**self).activate(reservation_id).await
+    }
+
+    async fn extend_hold(&self, reservation_id: &ReservationId, until: SystemTime) -> Result<()> {
+        (**self).extend_hold(reservation_id, until).await
+    }
+
+    async fn shrink_hold(
+        &self,
+        reservation_id: &ReservationId,
+        tier: &CapabilityTier,
+        expected_energy_kwh: f64,
+        expected_carbon_kg: f64,
+    ) -> Result<()> {
+        (**self)
+            .shrink_hold(reservation_id, tier, expected_energy_kwh, expected_carbon_kg)
+            .await
+    }
+
+    async fn commit_usage(
+        &self,
+        reservation_id: &ReservationId,
+        actuals: &ReservationActuals,
+    ) -> Result<()> {
+        (**self).commit_usage(reservation_id, actuals).await
+    }
+
+    async fn release(&self, reservation_id: &ReservationId) -> Result<()> {
+        (**self).release(reservation_id).await
+    }
+
+    async fn expire_reservations(&self, cutoff: SystemTime) -> Result<Vec<ReservationId>> {
+        (**self).expire_reservations(cutoff).await
+    }
 }
 
 pub struct QuotaService<Q: QuotaStore> {
@@ -31,7 +382,7 @@ impl<Q: QuotaStore> QuotaService<Q> {
         Self { store }
     }
 
-    pub fn check_and_reserve(
+    pub async fn check_and_reserve(
         &self,
         actor: &ActorId,
         window: &UsageWindowId,
@@ -39,38 +390,257 @@ impl<Q: QuotaStore> QuotaService<Q> {
         expected_energy_kwh: f64,
         expected_carbon_kg: f64,
     ) -> Result<ReservationId> {
-        let allowance = self.store.get_allowance(actor, window)?;
-        let usage = self.store.get_usage(actor, window)?;
-
-        // Check tier
-        if matches!((&job.requested_tier, &allowance.max_tier),
-            (CapabilityTier::Tier3, CapabilityTier::Tier2)
-            | (CapabilityTier::Tier3, CapabilityTier::Tier1)
-            | (CapabilityTier::Tier2, CapabilityTier::Tier1)
-        ) {
-            anyhow::bail!("requested tier exceeds maximum allowed tier");
+        let chain = self.allowance_chain(actor, window).await?;
+        let now = SystemTime::now();
+        if let Some((level, id, _)) = chain.iter().find(|(_, _, a)| a.valid_until <= now) {
+            return Err(OrchestratorError::AllowanceExpired(format!(
+                "{} allowance for {} in {} is no longer valid",
+                level.as_str(),
+                id,
+                window.0
+            ))
+            .into());
         }
 
-        // Check FLOPs and energy/carbon
-        if usage.flops_used + job.expected_flops > allowance.max_flops {
-            anyhow::bail!("FLOPs allowance exceeded");
+        // Check tier. A level may narrow the tier its parent allows, never
+        // widen it, so the effective maximum is the lowest along the chain.
+        let (level, _, max_tier) = chain
+            .iter()
+            .map(|(level, id, a)| (level, id, &a.max_tier))
+            .min_by_key(|(_, _, tier)| tier_rank(tier))
+            .expect("the actor allowance is always in the chain");
+        if tier_rank(&job.requested_tier) > tier_rank(max_tier) {
+            let allowed = [CapabilityTier::Tier1, CapabilityTier::Tier2, CapabilityTier::Tier3]
+                .into_iter()
+                .take(tier_rank(max_tier))
+                .collect();
+            return Err(OrchestratorError::TierNotAllowed {
+                requested: job.requested_tier.clone(),
+                allowed,
+                reason: format!("requested tier exceeds the {} maximum", level.as_str()),
+            }
+            .into());
+        }
+
+        // FLOPs and energy/carbon limits are enforced by the store, at every
+        // level, inside the same transaction that records the reservation.
+        let reservation_id = self
+            .store
+            .reserve_quota(actor, window, job, expected_energy_kwh, expected_carbon_kg)
+            .await?;
+
+        Ok(reservation_id)
+    }
+
+    pub async fn activate(&self, reservation_id: &ReservationId) -> Result<()> {
+        self.store.activate(reservation_id).await
+    }
+
+    pub async fn commit_usage(
+        &self,
+        reservation_id: &ReservationId,
+        actuals: &ReservationActuals,
+    ) -> Result<()> {
+        self.store.commit_usage(reservation_id, actuals).await
+    }
+
+    pub async fn release(&self, reservation_id: &ReservationId) -> Result<()> {
+        self.store.release(reservation_id).await
+    }
+
+    pub async fn window_usage(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<WindowUsageReport> {
+        let allowance = self.store.get_allowance(actor, window).await?;
+        let usage = self.store.get_usage(actor, window).await?;
+        let outstanding = self.store.outstanding_reservations(actor, window).await?;
+        Ok(WindowUsageReport {
+            actor_id: actor.clone(),
+            window_id: window.clone(),
+            remaining: remaining_allowance(&allowance, &usage),
+            allowance,
+            usage,
+            outstanding,
+        })
+    }
+
+    /// Consumption for `id` at `level` in the window, with its children
+    /// (projects, then their actors) rolled up beneath it.
+    pub async fn budget_report(
+        &self,
+        level: BudgetLevel,
+        id: &str,
+        window: &UsageWindowId,
+    ) -> Result<BudgetUsageNode> {
+        let mut root = self.budget_node(level, id, window).await?;
+        for child_id in self.store.budget_children(level, id).await? {
+            let child_level = match level {
+                BudgetLevel::Organisation => BudgetLevel::Project,
+                _ => BudgetLevel::Actor,
+            };
+            let mut child = self.budget_node(child_level, &child_id, window).await?;
+            for actor in self.store.budget_children(child_level, &child_id).await? {
+                child.children.push(self.budget_node(BudgetLevel::Actor, &actor, window).await?);
+            }
+            root.children.push(child);
         }
-        if usage.energy_kwh_used + expected_energy_kwh > allowance.max_energy_kwh {
-            anyhow::bail!("energy allowance exceeded");
+        Ok(root)
+    }
+
+    async fn budget_node(
+        &self,
+        level: BudgetLevel,
+        id: &str,
+        window: &UsageWindowId,
+    ) -> Result<BudgetUsageNode> {
+        let (allowance, usage) = match level {
+            BudgetLevel::Actor => {
+                let actor = ActorId(id.to_string());
+                (
+                    self.store.find_allowance(&actor, window).await?,
+                    self.store.get_usage(&actor, window).await?,
+                )
+            }
+            _ => (
+                self.store.find_group_allowance(level, id, window).await?,
+                self.store.get_group_usage(level, id, window).await?,
+            ),
+        };
+        Ok(BudgetUsageNode {
+            level,
+            id: id.to_string(),
+            window_id: window.clone(),
+            remaining: allowance.as_ref().map(|a| remaining_allowance(a, &usage)),
+            allowance,
+            usage,
+            children: Vec::new(),
+        })
+    }
+
+    /// The allowances a reservation must fit, organisation first. The actor's
+    /// own allowance is required; uncapped groups are skipped.
+    async fn allowance_chain(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Vec<(BudgetLevel, String, ComputeEnergyAllowance)>> {
+        let path = self.store.budget_path(actor).await?;
+        let mut chain = Vec::new();
+        for (level, group) in [
+            (BudgetLevel::Organisation, path.organisation),
+            (BudgetLevel::Project, path.project),
+        ] {
+            let Some(group) = group else { continue };
+            if let Some(allowance) = self.store.find_group_allowance(level, &group, window).await? {
+                chain.push((level, group, allowance));
+            }
         }
-        if usage.carbon_kg_emitted + expected_carbon_kg > allowance.max_carbon_kg {
-            anyhow::bail!("carbon allowance exceeded");
+        let allowance = self.store.get_allowance(actor, window).await?;
+        chain.push((BudgetLevel::Actor, actor.0.clone(), allowance));
+        Ok(chain)
+    }
+
+    pub fn store(&self) -> &Q {
+        &self.store
+    }
+}
+
+/// Periodically expires reservations that have outlived their job's
+/// `max_duration` plus a grace period, so abandoned holds stop counting
+/// against the window.
+pub struct ReservationSweeper<Q: QuotaStore> {
+    store: Q,
+    interval: Duration,
+    grace: Duration,
+}
+
+impl<Q: QuotaStore + 'static> ReservationSweeper<Q> {
+    pub fn new(store: Q, interval: Duration, grace: Duration) -> Self {
+        Self {
+            store,
+            interval,
+            grace,
         }
+    }
+
+    pub async fn sweep_once(&self) -> Result<Vec<ReservationId>> {
+        let cutoff = SystemTime::now()
+            .checked_sub(self.grace)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        self.store.expire_reservations(cutoff).await
+    }
 
-        let reservation_id = self.store.reserve_quota(
-            actor,
-            window,
-            job.expected_flops,
-            expected_energy_kwh,
-            expected_carbon_kg,
-            &job.requested_tier,
-        )?;
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.sweep_once().await {
+                    tracing::warn!(error = ?e, "reservation sweep failed");
+                }
+            }
+        })
+    }
+}
 
-        Ok(reservation_id)
+fn tier_rank(tier: &CapabilityTier) -> usize {
+    match tier {
+        CapabilityTier::Tier1 => 1,
+        CapabilityTier::Tier2 => 2,
+        CapabilityTier::Tier3 => 3,
+    }
+}
+
+/// Allowance left after committed usage and outstanding holds, floored at 0.
+pub fn remaining_allowance(
+    allowance: &ComputeEnergyAllowance,
+    usage: &UsageSnapshot,
+) -> RemainingAllowance {
+    RemainingAllowance {
+        flops: (allowance.max_flops - usage.flops_used - usage.flops_reserved).max(0.0),
+        energy_kwh: (allowance.max_energy_kwh - usage.energy_kwh_used - usage.energy_kwh_reserved)
+            .max(0.0),
+        carbon_kg: (allowance.max_carbon_kg - usage.carbon_kg_emitted - usage.carbon_kg_reserved)
+            .max(0.0),
+    }
+}
+
+/// Fails with `QuotaExceeded` if adding the expected amounts to `usage`
+/// (committed plus held) would exceed any dimension of `allowance`. Stores
+/// call this for each level while holding the lock that makes their
+/// check-and-reserve atomic.
+pub fn ensure_headroom(
+    level: BudgetLevel,
+    allowance: &ComputeEnergyAllowance,
+    usage: &UsageSnapshot,
+    expected_flops: f64,
+    expected_energy_kwh: f64,
+    expected_carbon_kg: f64,
+) -> Result<()> {
+    let exceeded = if usage.flops_used + usage.flops_reserved + expected_flops > allowance.max_flops
+    {
+        Some((QuotaDimension::Flops, expected_flops))
+    } else if usage.energy_kwh_used + usage.energy_kwh_reserved + expected_energy_kwh
+        > allowance.max_energy_kwh
+    {
+        Some((QuotaDimension::EnergyKwh, expected_energy_kwh))
+    } else if usage.carbon_kg_emitted + usage.carbon_kg_reserved + expected_carbon_kg
+        > allowance.max_carbon_kg
+    {
+        Some((QuotaDimension::CarbonKg, expected_carbon_kg))
+    } else {
+        None
+    };
+    match exceeded {
+        Some((dimension, requested)) => Err(OrchestratorError::QuotaExceeded {
+            level,
+            dimension,
+            requested,
+            remaining: remaining_allowance(allowance, usage),
+        }
+        .into()),
+        None => Ok(()),
     }
 }
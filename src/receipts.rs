@@ -0,0 +1,193 @@
+//! Ed25519-signed `FairUseReceipt`s. The signature covers the canonical JSON
+//! of the receipt together with the signing key id and issue time, so any
+//! edit to the usage numbers, the explanation or the metadata is detected.
+//!
+//! Canonical JSON: object keys sorted bytewise, no insignificant whitespace,
+//! numbers and strings as serde_json writes them.
+
+use crate::types::FairUseReceipt;
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::time::SystemTime;
+
+pub const RECEIPT_ALGORITHM: &str = "ed25519";
+
+/// A published verification key, as served by the public-key endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptPublicKey {
+    pub key_id: String,
+    pub algorithm: String,
+    pub public_key_hex: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReceipt {
+    pub receipt: FairUseReceipt,
+    pub key_id: String,
+    pub algorithm: String,
+    pub issued_at: SystemTime,
+    pub signature_hex: String,
+}
+
+// Everything in a SignedReceipt except the signature itself.
+#[derive(Serialize)]
+struct SigningPayload<'a> {
+    receipt: &'a FairUseReceipt,
+    key_id: &'a str,
+    algorithm: &'a str,
+    issued_at: SystemTime,
+}
+
+impl SignedReceipt {
+    /// The exact bytes that were signed.
+    pub fn signing_bytes(&self) -> Result<Vec<u8>> {
+        Ok(canonical_json(&SigningPayload {
+            receipt: &self.receipt,
+            key_id: &self.key_id,
+            algorithm: &self.algorithm,
+            issued_at: self.issued_at,
+        })?
+        .into_bytes())
+    }
+}
+
+pub fn canonical_json<T: Serialize>(value: &T) -> Result<String> {
+    fn write(value: &serde_json::Value, out: &mut String) -> Result<()> {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                out.push('{');
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&serde_json::to_string(key)?);
+                    out.push(':');
+                    write(&map[key], out)?;
+                }
+                out.push('}');
+            }
+            serde_json::Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write(item, out)?;
+                }
+                out.push(']');
+            }
+            scalar => out.push_str(&serde_json::to_string(scalar)?),
+        }
+        Ok(())
+    }
+
+    let mut out = String::new();
+    write(&serde_json::to_value(value)?, &mut out)?;
+    Ok(out)
+}
+
+/// Key id: first 16 hex chars of sha256 over the raw public key.
+pub fn key_id(key: &VerifyingKey) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))[..16].to_string()
+}
+
+pub struct ReceiptSigner {
+    signing_key: SigningKey,
+    key_id: String,
+    // earlier keys still published so receipts they signed stay verifiable
+    retired: Vec<ReceiptPublicKey>,
+}
+
+impl ReceiptSigner {
+    pub fn new(signing_key: SigningKey) -> Self {
+        Self {
+            key_id: key_id(&signing_key.verifying_key()),
+            signing_key,
+            retired: Vec::new(),
+        }
+    }
+
+    /// Loads a 32-byte Ed25519 seed written as 64 hex characters, e.g. from
+    /// `openssl rand -hex 32`.
+    pub fn from_seed_hex(seed_hex: &str) -> Result<Self> {
+        let seed: [u8; 32] = hex::decode(seed_hex.trim())
+            .context("receipt signing seed is not hex")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("receipt signing seed must be 32 bytes"))?;
+        Ok(Self::new(SigningKey::from_bytes(&seed)))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let seed = std::fs::read_to_string(path)
+            .with_context(|| format!("reading receipt signing key {}", path.display()))?;
+        Self::from_seed_hex(&seed)
+    }
+
+    /// Keeps publishing a previous key after rotation.
+    pub fn with_retired_key(mut self, key: ReceiptPublicKey) -> Self {
+        self.retired.push(key);
+        self
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The current key first, then retired keys.
+    pub fn public_keys(&self) -> Vec<ReceiptPublicKey> {
+        let current = ReceiptPublicKey {
+            key_id: self.key_id.clone(),
+            algorithm: RECEIPT_ALGORITHM.into(),
+            public_key_hex: hex::encode(self.signing_key.verifying_key().as_bytes()),
+        };
+        std::iter::once(current)
+            .chain(self.retired.iter().cloned())
+            .collect()
+    }
+
+    pub fn sign(&self, receipt: FairUseReceipt) -> Result<SignedReceipt> {
+        let mut signed = SignedReceipt {
+            receipt,
+            key_id: self.key_id.clone(),
+            algorithm: RECEIPT_ALGORITHM.into(),
+            issued_at: SystemTime::now(),
+            signature_hex: String::new(),
+        };
+        let signature = self.signing_key.sign(&signed.signing_bytes()?);
+        signed.signature_hex = hex::encode(signature.to_bytes());
+        Ok(signed)
+    }
+}
+
+/// Checks `signed` against whichever of `keys` its `key_id` names.
+pub fn verify_receipt(signed: &SignedReceipt, keys: &[ReceiptPublicKey]) -> Result<()> {
+    if signed.algorithm != RECEIPT_ALGORITHM {
+        anyhow::bail!("unsupported receipt algorithm {:?}", signed.algorithm);
+    }
+    let published = keys
+        .iter()
+        .find(|k| k.key_id == signed.key_id)
+        .ok_or_else(|| anyhow::anyhow!("receipt signed by unknown key {}", signed.key_id))?;
+
+    let key_bytes: [u8; 32] = hex::decode(&published.public_key_hex)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("public key {} is not 32 bytes", published.key_id))?;
+    let verifying_key = VerifyingKey::from_bytes(&key_bytes)?;
+    // A key id that does not belong to the key it is published with would let
+    // one key vouch under another's name.
+    if key_id(&verifying_key) != published.key_id {
+        anyhow::bail!("key id {} does not match its public key", published.key_id);
+    }
+
+    let signature_bytes: [u8; 64] = hex::decode(&signed.signature_hex)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("receipt signature is not 64 bytes"))?;
+    verifying_key
+        .verify(&signed.signing_bytes()?, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| anyhow::anyhow!("receipt signature does not match its contents"))
+}
@@ -0,0 +1,172 @@
+use crate::energy::EnergyEstimator;
+use crate::quota::ReservationActuals;
+use crate::receipts::SignedReceipt;
+use crate::types::{FairUseReceipt, ReservationId, SegmentId, SegmentLoad};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerSample {
+    pub at: SystemTime,
+    pub power_kw: f64, // xr_power_kw
+    pub renewable_share_pct: f64,
+    pub current_flops: f64,
+}
+
+/// Recent telemetry per segment, kept so finished jobs can be charged for
+/// what the segment actually drew while they ran. Samples older than
+/// `retention` are dropped as new ones arrive.
+pub struct PowerSampleLog {
+    samples: Mutex<HashMap<String, VecDeque<PowerSample>>>,
+    retention: Duration,
+}
+
+impl Default for PowerSampleLog {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(7 * 86_400))
+    }
+}
+
+impl PowerSampleLog {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            samples: Mutex::new(HashMap::new()),
+            retention,
+        }
+    }
+
+    pub fn record(&self, load: &SegmentLoad, at: SystemTime) {
+        let mut samples = self.samples.lock().unwrap();
+        let series = samples.entry(load.segment_id.0.clone()).or_default();
+        // Samplers may race; keep the series ordered by time.
+        let idx = series.partition_point(|s| s.at <= at);
+        series.insert(
+            idx,
+            PowerSample {
+                at,
+                power_kw: load.energy_rate_kw,
+                renewable_share_pct: load.renewable_share_pct,
+                current_flops: load.current_flops,
+            },
+        );
+        let cutoff = at.checked_sub(self.retention).unwrap_or(UNIX_EPOCH);
+        while series.front().is_some_and(|s| s.at < cutoff) {
+            series.pop_front();
+        }
+    }
+
+    /// Samples within `[from, to]` plus the nearest one on either side, which
+    /// `integrate` needs to interpolate the edges of the window.
+    pub fn samples_around(
+        &self,
+        segment_id: &SegmentId,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Vec<PowerSample> {
+        let samples = self.samples.lock().unwrap();
+        let Some(series) = samples.get(&segment_id.0) else {
+            return Vec::new();
+        };
+        let start = series.partition_point(|s| s.at < from).saturating_sub(1);
+        let end = (series.partition_point(|s| s.at <= to) + 1).min(series.len());
+        series.range(start..end).cloned().collect()
+    }
+}
+
+/// Totals for the whole segment over a window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentIntegral {
+    pub energy_kwh: f64,
+    pub carbon_kg: f64,
+    pub flops: f64,
+    pub samples_used: usize,
+}
+
+/// Integrates power, carbon rate and FLOP rate over `[from, to]` with the
+/// trapezoidal rule, treating the signal as linear between samples and flat
+/// before the first and after the last. `samples` must be in time order.
+pub fn integrate(
+    samples: &[PowerSample],
+    from: SystemTime,
+    to: SystemTime,
+    estimator: &EnergyEstimator,
+) -> Option<SegmentIntegral> {
+    if samples.is_empty() || to <= from {
+        return None;
+    }
+    let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    // (power kW, carbon kg/h, FLOP/s) per sample
+    let rates = |s: &PowerSample| {
+        [
+            s.power_kw,
+            s.power_kw * estimator.carbon_intensity(s.renewable_share_pct),
+            s.current_flops,
+        ]
+    };
+    let rate_at = |t: f64| {
+        let next = samples.partition_point(|s| secs(s.at) < t);
+        if next == 0 {
+            return rates(&samples[0]);
+        }
+        if next == samples.len() {
+            return rates(&samples[next - 1]);
+        }
+        let (a, b) = (&samples[next - 1], &samples[next]);
+        let (ta, tb) = (secs(a.at), secs(b.at));
+        let w = if tb > ta { (t - ta) / (tb - ta) } else { 1.0 };
+        let (ra, rb) = (rates(a), rates(b));
+        [0, 1, 2].map(|i| ra[i] + (rb[i] - ra[i]) * w)
+    };
+
+    let (from_s, to_s) = (secs(from), secs(to));
+    let mut points = vec![from_s];
+    points.extend(
+        samples
+            .iter()
+            .map(|s| secs(s.at))
+            .filter(|t| *t > from_s && *t < to_s),
+    );
+    points.push(to_s);
+
+    let mut totals = [0.0; 3];
+    for pair in points.windows(2) {
+        let (r0, r1) = (rate_at(pair[0]), rate_at(pair[1]));
+        let dt = pair[1] - pair[0];
+        for i in 0..3 {
+            totals[i] += (r0[i] + r1[i]) / 2.0 * dt;
+        }
+    }
+
+    Some(SegmentIntegral {
+        energy_kwh: totals[0] / 3600.0,
+        carbon_kg: totals[1] / 3600.0,
+        flops: totals[2],
+        samples_used: samples.len(),
+    })
+}
+
+/// What a finished job is charged for. The segment and timing come from the
+/// job record and the executor, never from the caller; `flops_used` defaults
+/// to the reservation's expected FLOPs when the executor cannot count them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCompletion {
+    pub reservation_id: ReservationId,
+    pub segment_id: SegmentId,
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    #[serde(default)]
+    pub flops_used: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciledJob {
+    pub actuals: ReservationActuals,
+    // fraction of the segment's energy charged to this job; None when no
+    // telemetry covered the run and the reserved estimate was charged instead
+    pub attributed_share: Option<f64>,
+    pub segment: Option<SegmentIntegral>,
+    pub receipt: FairUseReceipt,
+    pub signed_receipt: Option<SignedReceipt>,
+}
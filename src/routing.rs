@@ -1,12 +1,173 @@
-use crate::identity::{ActorProfile, IdentityResolver, ZoneResolver};
-use crate::quota::QuotaService;
-use crate::energy::StabilityGuard;
+use crate::approval::{ApprovalRequest, ApprovalStore, ReviewRecord, ReviewVerdict};
+use crate::error::{OrchestratorError, RemainingAllowance};
+use crate::identity::{
+    ActorProfile, IdentityResolver, ZoneExclusion, ZoneResolution, ZoneResolver,
+};
+use crate::quota::{QuotaService, QuotaStore};
+use crate::energy::{EnergyEstimator, SegmentTelemetry, StabilityGuard};
+use crate::executor::{ExecutionRequest, ExecutionState, ExecutionStatus, Executor, LaunchCommand};
+use crate::clearance::ClearancePolicy;
+use crate::fair_share::FairShareAllocator;
 use crate::policy::PolicyEngine;
-use crate::logging::{ImmutableLogger, EcologicalLogEvent, LogEventType};
+use crate::preemption::{GridStress, PreemptionController, PreemptionSignal};
+use crate::quota::{
+    remaining_allowance, BudgetUsageNode, Reservation, ReservationActuals, ReservationState,
+    WindowUsageReport,
+};
+use crate::reconcile::{
+    integrate, JobCompletion, PowerSampleLog, ReconciledJob, SegmentIntegral,
+};
+use crate::receipts::{ReceiptPublicKey, ReceiptSigner, SignedReceipt};
+use crate::jobs::{JobReceipt, JobRecord, JobStatus, JobStore, PlanView};
+use crate::logging::{
+    build_receipt, log_execution_plan, ImmutableLogger, EcologicalLogEvent, LogEventType,
+};
+use crate::scheduler::{DeferredJob, DeferredScheduler, SchedulerOutcome};
 use crate::types::*;
+use crate::windows::WindowManager;
 use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-pub struct EcologicalOrchestrator<I, Z, Q, T, P, L>
+/// Relative weight of each placement signal. Every signal is normalised to
+/// 0..=1 before weighting.
+#[derive(Debug, Clone)]
+pub struct PlacementWeights {
+    pub renewable: f64,
+    pub thermal: f64,
+    pub headroom: f64,
+    pub hint: f64,
+}
+
+impl Default for PlacementWeights {
+    fn default() -> Self {
+        Self {
+            renewable: 0.5,
+            thermal: 0.2,
+            headroom: 0.2,
+            hint: 0.1,
+        }
+    }
+}
+
+pub struct RankedSegment {
+    pub candidate: SegmentCandidate,
+    pub zone: ZoneResolution,
+    pub load: SegmentLoad,
+}
+
+/// Chooses among the segments an actor is trusted for. Candidates the
+/// stability guard would accept outright rank ahead of ones it would
+/// downgrade, which rank ahead of ones it would throttle or deny; within each
+/// group the weighted score decides, with ties kept in resolver order.
+///
+/// Headroom is measured against a segment's configured FLOP capacity, or
+/// against the busiest candidate when no capacity is configured.
+#[derive(Debug, Clone, Default)]
+pub struct PlacementEngine {
+    weights: PlacementWeights,
+    capacity_flops: HashMap<String, f64>,
+}
+
+impl PlacementEngine {
+    pub fn new(weights: PlacementWeights) -> Self {
+        Self {
+            weights,
+            capacity_flops: HashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(mut self, segment_id: &SegmentId, capacity_flops: f64) -> Self {
+        self.capacity_flops.insert(segment_id.0.clone(), capacity_flops);
+        self
+    }
+
+    /// Headroom on a segment with a configured capacity; `None` without one.
+    pub fn capacity_headroom(&self, segment_id: &SegmentId, load: &SegmentLoad) -> Option<f64> {
+        self.capacity_flops
+            .get(&segment_id.0)
+            .filter(|capacity| **capacity > 0.0)
+            .map(|capacity| (1.0 - load.current_flops / capacity).clamp(0.0, 1.0))
+    }
+
+    pub fn rank<T: SegmentTelemetry>(
+        &self,
+        job: &EcologicalJobSpec,
+        candidates: Vec<(ZoneResolution, SegmentLoad)>,
+        guard: &StabilityGuard<T>,
+    ) -> Vec<RankedSegment> {
+        let busiest = candidates
+            .iter()
+            .map(|(_, load)| load.current_flops)
+            .fold(0.0_f64, f64::max);
+
+        let mut ranked: Vec<RankedSegment> = candidates
+            .into_iter()
+            .map(|(zone, load)| {
+                let headroom = match self.capacity_headroom(&zone.segment_id, &load) {
+                    Some(headroom) => headroom,
+                    None if busiest > 0.0 => (1.0 - load.current_flops / busiest).clamp(0.0, 1.0),
+                    None => 1.0,
+                };
+                let hinted = job
+                    .segment_hint
+                    .as_ref()
+                    .is_some_and(|hint| hint.0 == zone.segment_id.0);
+
+                let renewable = (load.renewable_share_pct / 100.0).clamp(0.0, 1.0);
+                let thermal = (load.thermal_margin_pct / 100.0).clamp(0.0, 1.0);
+                let score = self.weights.renewable * renewable
+                    + self.weights.thermal * thermal
+                    + self.weights.headroom * headroom
+                    + if hinted { self.weights.hint } else { 0.0 };
+
+                RankedSegment {
+                    candidate: SegmentCandidate {
+                        segment_id: zone.segment_id.clone(),
+                        score,
+                        renewable_share_pct: load.renewable_share_pct,
+                        thermal_margin_pct: load.thermal_margin_pct,
+                        headroom,
+                        trust_level: zone.trust_level,
+                        hinted,
+                        stability_decision: guard.check_load(&load, &job.requested_tier),
+                    },
+                    zone,
+                    load,
+                }
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| {
+            stability_rank(&a.candidate.stability_decision)
+                .cmp(&stability_rank(&b.candidate.stability_decision))
+                .then(b.candidate.score.total_cmp(&a.candidate.score))
+        });
+        ranked
+    }
+}
+
+fn stability_rank(decision: &StabilityDecision) -> u8 {
+    match decision {
+        StabilityDecision::Ok => 0,
+        StabilityDecision::Downgrade { .. } => 1,
+        StabilityDecision::Throttle { .. } => 2,
+        StabilityDecision::Deny { .. } => 3,
+    }
+}
+
+// Usage committed for a finished job, before its receipt is issued.
+struct CommittedUsage {
+    reservation: Reservation,
+    actuals: ReservationActuals,
+    attributed_share: Option<f64>,
+    segment: Option<SegmentIntegral>,
+    runtime_secs: u64,
+    explanation: String,
+}
+
+pub struct EcologicalOrchestrator<I, Z, Q, T, P, L, A, J>
 where
     I: IdentityResolver,
     Z: ZoneResolver,
@@ -14,16 +175,32 @@ where
     T: SegmentTelemetry,
     P: PolicyEngine,
     L: ImmutableLogger,
+    A: ApprovalStore,
+    J: JobStore,
 {
     identity_resolver: I,
     zone_resolver: Z,
     quota_service: QuotaService<Q>,
     stability_guard: StabilityGuard<T>,
+    energy_estimator: EnergyEstimator,
+    scheduler: DeferredScheduler,
+    placement_engine: PlacementEngine,
     policy_engine: P,
     logger: L,
+    approval_store: A,
+    job_store: J,
+    receipt_signer: Option<ReceiptSigner>,
+    power_log: PowerSampleLog,
+    window_manager: Option<WindowManager>,
+    fair_share: Option<FairShareAllocator>,
+    fair_share_restored: tokio::sync::OnceCell<()>,
+    preemption: Option<PreemptionController>,
+    executor: Option<Arc<dyn Executor>>,
+    clearance_policy: Option<ClearancePolicy>,
+    review_ttl: Duration,
 }
 
-impl<I, Z, Q, T, P, L> EcologicalOrchestrator<I, Z, Q, T, P, L>
+impl<I, Z, Q, T, P, L, A, J> EcologicalOrchestrator<I, Z, Q, T, P, L, A, J>
 where
     I: IdentityResolver,
     Z: ZoneResolver,
@@ -31,7 +208,10 @@ where
     T: SegmentTelemetry,
     P: PolicyEngine,
     L: ImmutableLogger,
+    A: ApprovalStore,
+    J: JobStore,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         identity_resolver: I,
         zone_resolver: Z,
@@ -39,92 +219,1790 @@ where
         stability_guard: StabilityGuard<T>,
         policy_engine: P,
         logger: L,
+        approval_store: A,
+        job_store: J,
     ) -> Self {
         Self {
             identity_resolver,
             zone_resolver,
             quota_service: QuotaService::new(quota_store),
             stability_guard,
+            energy_estimator: EnergyEstimator::default(),
+            scheduler: DeferredScheduler::default(),
+            placement_engine: PlacementEngine::default(),
             policy_engine,
             logger,
+            approval_store,
+            job_store,
+            receipt_signer: None,
+            power_log: PowerSampleLog::default(),
+            window_manager: None,
+            fair_share: None,
+            fair_share_restored: tokio::sync::OnceCell::new(),
+            preemption: None,
+            executor: None,
+            clearance_policy: None,
+            review_ttl: Duration::from_secs(24 * 3600),
+        }
+    }
+
+    pub fn with_energy_estimator(mut self, energy_estimator: EnergyEstimator) -> Self {
+        self.energy_estimator = energy_estimator;
+        self
+    }
+
+    pub fn with_scheduler(mut self, scheduler: DeferredScheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    pub fn with_placement_engine(mut self, placement_engine: PlacementEngine) -> Self {
+        self.placement_engine = placement_engine;
+        self
+    }
+
+    pub fn with_receipt_signer(mut self, receipt_signer: ReceiptSigner) -> Self {
+        self.receipt_signer = Some(receipt_signer);
+        self
+    }
+
+    pub fn with_power_log(mut self, power_log: PowerSampleLog) -> Self {
+        self.power_log = power_log;
+        self
+    }
+
+    /// Derive usage windows from server time instead of trusting the caller.
+    pub fn with_window_manager(mut self, window_manager: WindowManager) -> Self {
+        self.window_manager = Some(window_manager);
+        self
+    }
+
+    /// Weigh actors by ecological priority and recent usage when their
+    /// segment is contended.
+    pub fn with_fair_share(mut self, allocator: FairShareAllocator) -> Self {
+        self.fair_share = Some(allocator);
+        self
+    }
+
+    /// Suspend or downgrade running jobs when their segment comes under grid
+    /// stress, and resume them once it eases (see `run_preemption_once`).
+    pub fn with_preemption(mut self, controller: PreemptionController) -> Self {
+        self.preemption = Some(controller);
+        self
+    }
+
+    /// Run jobs through `executor` (see `launch_job` and `run_executor_once`).
+    pub fn with_executor(mut self, executor: Arc<dyn Executor>) -> Self {
+        self.executor = Some(executor);
+        self
+    }
+
+    /// How long a plan held for human review keeps its hold waiting for
+    /// reviewers; 24 hours by default. A later deadline plus `max_duration`
+    /// still wins.
+    pub fn with_review_ttl(mut self, review_ttl: Duration) -> Self {
+        self.review_ttl = review_ttl;
+        self
+    }
+
+    /// Require clearance per tier and high-risk domain tag, and trusted
+    /// segments for the work that needs them.
+    pub fn with_clearance_policy(mut self, policy: ClearancePolicy) -> Self {
+        self.clearance_policy = Some(policy);
+        self
+    }
+
+    /// Keys receipts can be verified against; empty when no signer is set.
+    pub fn receipt_public_keys(&self) -> Vec<ReceiptPublicKey> {
+        self.receipt_signer
+            .as_ref()
+            .map(|s| s.public_keys())
+            .unwrap_or_default()
+    }
+
+    pub fn sign_receipt(&self, receipt: FairUseReceipt) -> Result<SignedReceipt> {
+        self.receipt_signer
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("no receipt signing key configured"))?
+            .sign(receipt)
+    }
+
+    /// Resolves the session. Resolver failures that are not already typed are
+    /// reported as failed authentication.
+    async fn authenticate(&self, session_token: &str) -> Result<ActorProfile> {
+        self.identity_resolver
+            .resolve_actor(session_token)
+            .await
+            .map_err(|e| match OrchestratorError::find(&e) {
+                Some(_) => e,
+                None => OrchestratorError::AuthenticationFailed(e.to_string()).into(),
+            })
+    }
+
+    /// The window a request is charged to. With a window manager this is the
+    /// actor's current window, provisioned on first use, and a window named by
+    /// the caller must match it. Without one the caller has to name it.
+    async fn resolve_window(
+        &self,
+        actor_id: &ActorId,
+        requested: Option<UsageWindowId>,
+    ) -> Result<UsageWindowId> {
+        let Some(manager) = &self.window_manager else {
+            return requested.ok_or_else(|| {
+                OrchestratorError::InvalidRequest("window_id is required".into()).into()
+            });
+        };
+        let current = manager
+            .ensure_current(self.quota_service.store(), actor_id, SystemTime::now())
+            .await?;
+        match requested {
+            Some(window_id) if window_id.0 != current.id.0 => {
+                Err(OrchestratorError::InvalidRequest(format!(
+                    "window {} is not the current window {}",
+                    window_id.0, current.id.0
+                ))
+                .into())
+            }
+            _ => Ok(current.id),
         }
     }
 
-    pub fn plan_job(
+    pub async fn plan_job(
         &self,
         session_token: &str,
-        window_id: UsageWindowId,
+        window_id: Option<UsageWindowId>,
         mut job: EcologicalJobSpec,
-        expected_energy_kwh: f64,
-        expected_carbon_kg: f64,
+        expected_energy_kwh: Option<f64>,
+        expected_carbon_kg: Option<f64>,
     ) -> Result<JobExecutionPlan> {
-        // 1. Resolve actor
-        let actor = self.identity_resolver.resolve_actor(session_token)?;
-        let zone = self.zone_resolver.resolve_zone(&actor)?;
-
-        // 2. Policy evaluation
-        let policy_decision = self.policy_engine.evaluate(&job)?;
-        self.logger.append(&EcologicalLogEvent {
-            event_type: LogEventType::PolicyEvaluated,
-            reservation_id: None,
-            actor_id: Some(actor.actor_id.clone()),
-            segment_id: Some(zone.segment_id.clone()),
-            window_id: Some(window_id.clone()),
-            metadata: serde_json::json!({
-                "risk_score": policy_decision.risk_score,
-                "requires_human_approval": policy_decision.requires_human_approval,
-                "notes": policy_decision.notes,
-            }),
-        })?;
+        // 1. Resolve actor, the window it is charged to and the segments it is
+        // trusted to run on
+        let actor = self.authenticate(session_token).await?;
+        if job.deadline.is_some_and(|deadline| deadline <= SystemTime::now()) {
+            return Err(OrchestratorError::InvalidRequest(
+                "the job's deadline has already passed".into(),
+            )
+            .into());
+        }
+        let window_id = self.resolve_window(&actor.actor_id, window_id).await?;
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::JobRequested,
+                reservation_id: None,
+                actor_id: Some(actor.actor_id.clone()),
+                segment_id: job.segment_hint.clone(),
+                window_id: Some(window_id.clone()),
+                metadata: serde_json::json!({
+                    "purpose": job.purpose,
+                    "requested_tier": job.requested_tier,
+                    "expected_flops": job.expected_flops,
+                    "domain_tags": job.domain_tags,
+                    "deadline": job.deadline,
+                }),
+            })
+            .await?;
+        let mut eligibility = self.zone_resolver.resolve_for_job(&actor, &job).await?;
+        let clearance = self
+            .clearance_policy
+            .as_ref()
+            .map(|policy| policy.assess(&actor, &job))
+            .unwrap_or_default();
+        if !clearance.is_cleared() {
+            self.logger
+                .append(&EcologicalLogEvent {
+                    event_type: LogEventType::PolicyEvaluated,
+                    reservation_id: None,
+                    actor_id: Some(actor.actor_id.clone()),
+                    segment_id: None,
+                    window_id: Some(window_id.clone()),
+                    metadata: serde_json::json!({
+                        "denied": true,
+                        "clearance_level": actor.clearance_level,
+                        "required_clearance": clearance.required_clearance,
+                        "clearance_rules": clearance.rules,
+                        "notes": clearance.denials,
+                    }),
+                })
+                .await?;
+            return Err(OrchestratorError::PolicyDenied(format!(
+                "insufficient clearance ({})",
+                clearance.denials.join("; ")
+            ))
+            .into());
+        }
+        let (trusted, untrusted): (Vec<_>, Vec<_>) = eligibility
+            .eligible
+            .into_iter()
+            .partition(|zone| zone.trust_level >= clearance.min_segment_trust);
+        eligibility.eligible = trusted;
+        eligibility.excluded.extend(untrusted.into_iter().map(|zone| ZoneExclusion {
+            reasons: vec![format!(
+                "trust level {} is below the {} this job requires",
+                zone.trust_level, clearance.min_segment_trust
+            )],
+            segment_id: zone.segment_id,
+        }));
+        if eligibility.eligible.is_empty() {
+            let reason = format!(
+                "no segment is eligible for this job ({})",
+                eligibility.explain_exclusions()
+            );
+            self.logger
+                .append(&EcologicalLogEvent {
+                    event_type: LogEventType::PolicyEvaluated,
+                    reservation_id: None,
+                    actor_id: Some(actor.actor_id.clone()),
+                    segment_id: None,
+                    window_id: Some(window_id.clone()),
+                    metadata: serde_json::json!({
+                        "denied": true,
+                        "min_segment_trust": clearance.min_segment_trust,
+                        "clearance_rules": clearance.rules,
+                        "excluded_segments": eligibility.excluded,
+                        "notes": [reason],
+                    }),
+                })
+                .await?;
+            return Err(OrchestratorError::Forbidden(reason).into());
+        }
 
-        // 3. Align requested tier with allowed tiers
-        if !policy_decision
-            .allowed_tiers
-            .iter()
-            .any(|t| *t == job.requested_tier)
-        {
-            anyhow::bail!("requested tier not allowed by policy");
-        }
-
-        // 4. Reserve quota
-        let reservation_id = self.quota_service.check_and_reserve(
-            &actor.actor_id,
-            &window_id,
-            &job,
-            expected_energy_kwh,
-            expected_carbon_kg,
-        )?;
-
-        // 5. Stability guard
-        let stability = self.stability_guard.check(
-            &zone.segment_id,
-            job.expected_flops,
-            expected_energy_kwh,
-            &job.requested_tier,
-        )?;
-
-        // Apply downgrade if needed
+        // 2. Place the job on the best trusted segment; segments without
+        // telemetry cannot be assessed and are skipped
+        let telemetry = self.stability_guard.telemetry();
+        let mut candidates = Vec::with_capacity(eligibility.eligible.len());
+        for zone in eligibility.eligible {
+            if let Ok(load) = telemetry.get_segment_load(&zone.segment_id).await {
+                self.power_log.record(&load, SystemTime::now());
+                candidates.push((zone, load));
+            }
+        }
+        let mut ranked = self
+            .placement_engine
+            .rank(&job, candidates, &self.stability_guard)
+            .into_iter();
+        let RankedSegment {
+            candidate: chosen,
+            zone,
+            load,
+        } = ranked
+            .next()
+            .ok_or_else(|| {
+                OrchestratorError::SegmentUnavailable(
+                    "no trusted segment is reporting telemetry".into(),
+                )
+            })?;
+        let alternatives: Vec<SegmentCandidate> = ranked.map(|r| r.candidate).collect();
+        let fair_share = self
+            .fair_share_allocator()
+            .await?
+            .map(|allocator| allocator.assess(&actor, &job, chosen.headroom, SystemTime::now()));
+
+        // 3. Policy evaluation
+        let policy_decision = self.policy_engine.evaluate(&job, &actor).await?;
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::PolicyEvaluated,
+                reservation_id: None,
+                actor_id: Some(actor.actor_id.clone()),
+                segment_id: Some(zone.segment_id.clone()),
+                window_id: Some(window_id.clone()),
+                metadata: serde_json::json!({
+                    "risk_score": policy_decision.risk_score,
+                    "requires_human_approval": policy_decision.requires_human_approval,
+                    "notes": policy_decision.notes,
+                    "excluded_segments": eligibility.excluded,
+                    "clearance_rules": clearance.rules,
+                }),
+            })
+            .await?;
+
+        // 4. Align requested tier with allowed tiers
+        if policy_decision.allowed_tiers.is_empty() {
+            return Err(OrchestratorError::PolicyDenied(format!(
+                "no tier is allowed for this job ({})",
+                policy_decision.notes.join("; ")
+            ))
+            .into());
+        }
+        if !policy_decision.allowed_tiers.contains(&job.requested_tier) {
+            return Err(OrchestratorError::TierNotAllowed {
+                requested: job.requested_tier.clone(),
+                allowed: policy_decision.allowed_tiers.clone(),
+                reason: "requested tier not allowed by policy".into(),
+            }
+            .into());
+        }
+
+        // 5. Stability guard (already evaluated against the chosen segment
+        // while ranking); a downgrade applies before anything is estimated or
+        // held, so both are sized for the tier that will actually run
+        let stability = chosen.stability_decision;
         if let StabilityDecision::Downgrade { ref downgraded_tier, .. } = stability {
             job.requested_tier = downgraded_tier.clone();
         }
 
-        // 6. Log plan
-        self.logger.append(&EcologicalLogEvent {
-            event_type: LogEventType::StabilityChecked,
-            reservation_id: Some(reservation_id.clone()),
-            actor_id: Some(actor.actor_id.clone()),
-            segment_id: Some(zone.segment_id.clone()),
-            window_id: Some(window_id.clone()),
-            metadata: serde_json::json!({
-                "stability_decision": format!("{:?}", stability),
-            }),
-        })?;
+        // 6. Estimate energy and carbon from the segment's measured efficiency;
+        // caller-declared figures are only cross-checked against it
+        let estimate = self
+            .energy_estimator
+            .estimate(&job, &job.requested_tier, &load);
+        self.energy_estimator
+            .cross_check(&estimate, expected_energy_kwh, expected_carbon_kg)?;
+
+        // 7. Reserve quota
+        let reservation_id = self
+            .quota_service
+            .check_and_reserve(
+                &actor.actor_id,
+                &window_id,
+                &job,
+                estimate.energy_kwh,
+                estimate.carbon_kg,
+            )
+            .await?;
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::QuotaReserved,
+                reservation_id: Some(reservation_id.clone()),
+                actor_id: Some(actor.actor_id.clone()),
+                segment_id: Some(zone.segment_id.clone()),
+                window_id: Some(window_id.clone()),
+                metadata: serde_json::json!({
+                    "expected_flops": job.expected_flops,
+                    "energy_estimate": estimate,
+                    "declared_energy_kwh": expected_energy_kwh,
+                    "declared_carbon_kg": expected_carbon_kg,
+                }),
+            })
+            .await?;
+
+        // 8. Plans that need human sign-off keep a pending hold until reviewers
+        // reach quorum; everything else is queued, started or released now
+        let approval = if policy_decision.requires_human_approval {
+            let request = ApprovalRequest {
+                reservation_id: reservation_id.clone(),
+                actor_id: actor.actor_id.clone(),
+                window_id: window_id.clone(),
+                segment_id: zone.segment_id.clone(),
+                approved_tier: job.requested_tier.clone(),
+                job: job.clone(),
+                risk_score: policy_decision.risk_score,
+                notes: policy_decision.notes.clone(),
+                required_approvals: policy_decision.required_approvals.max(1),
+                min_reviewer_clearance: policy_decision.min_reviewer_clearance,
+                reviews: Vec::new(),
+                status: ApprovalStatus::Pending,
+                created_at: SystemTime::now(),
+            };
+            self.quota_service
+                .store()
+                .extend_hold(&reservation_id, SystemTime::now() + self.review_ttl)
+                .await?;
+            self.approval_store.insert(&request).await?;
+            self.logger
+                .append(&EcologicalLogEvent {
+                    event_type: LogEventType::ApprovalRequested,
+                    reservation_id: Some(reservation_id.clone()),
+                    actor_id: Some(actor.actor_id.clone()),
+                    segment_id: Some(zone.segment_id.clone()),
+                    window_id: Some(window_id.clone()),
+                    metadata: serde_json::json!({
+                        "required_approvals": request.required_approvals,
+                        "min_reviewer_clearance": request.min_reviewer_clearance,
+                        "notes": request.notes,
+                    }),
+                })
+                .await?;
+            Some(ApprovalStatus::Pending)
+        } else {
+            None
+        };
+        let deferral = match approval {
+            Some(_) => None,
+            None => {
+                self.dispatch(
+                    &reservation_id,
+                    &actor.actor_id,
+                    &zone.segment_id,
+                    &window_id,
+                    &job,
+                    &stability,
+                    fair_share.as_ref(),
+                )
+                .await?
+            }
+        };
+
+        // 9. Log plan
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::StabilityChecked,
+                reservation_id: Some(reservation_id.clone()),
+                actor_id: Some(actor.actor_id.clone()),
+                segment_id: Some(zone.segment_id.clone()),
+                window_id: Some(window_id.clone()),
+                metadata: serde_json::json!({
+                    "stability_decision": format!("{:?}", stability),
+                    "deferral": deferral,
+                    "approval": approval,
+                    "placement_score": chosen.score,
+                    "fair_share": fair_share,
+                    "alternatives": alternatives
+                        .iter()
+                        .map(|c| &c.segment_id.0)
+                        .collect::<Vec<_>>(),
+                }),
+            })
+            .await?;
 
-        Ok(JobExecutionPlan {
+        let plan = JobExecutionPlan {
             reservation_id,
             approved_segment: zone.segment_id,
             approved_tier: job.requested_tier,
             stability_decision: stability,
+            estimated_energy_kwh: estimate.energy_kwh,
+            estimated_carbon_kg: estimate.carbon_kg,
+            deferral,
+            alternatives,
+            approval,
+            fair_share,
+        };
+        self.job_store
+            .insert(&JobRecord {
+                plan: plan.clone(),
+                actor_id: actor.actor_id,
+                window_id,
+                priority_score: actor.ecological_priority_score,
+                status: JobStatus::Planned,
+                planned_at: SystemTime::now(),
+                started_at: None,
+                finished_at: None,
+                launched: false,
+                receipt: None,
+            })
+            .await?;
+
+        Ok(plan)
+    }
+
+    /// An actor's allowance, usage, holds and headroom for a window. Actors
+    /// may only query their own windows.
+    pub async fn window_usage(
+        &self,
+        session_token: &str,
+        actor_id: &ActorId,
+        window_id: &UsageWindowId,
+    ) -> Result<WindowUsageReport> {
+        let caller = self.authenticate(session_token).await?;
+        if caller.actor_id.0 != actor_id.0 {
+            return Err(OrchestratorError::Forbidden(format!(
+                "usage for {} is only visible to that actor",
+                actor_id.0
+            ))
+            .into());
+        }
+        if let Some(manager) = &self.window_manager {
+            // Querying the current window before planning in it provisions it.
+            let now = SystemTime::now();
+            if manager.current_window(actor_id, now).id.0 == window_id.0 {
+                manager.ensure_current(self.quota_service.store(), actor_id, now).await?;
+            }
+        }
+        self.quota_service.window_usage(actor_id, window_id).await
+    }
+
+    /// Consumption rolled up under an organisation, project or actor. Callers
+    /// may only look at their own budget path: their organisation, their
+    /// project or themselves.
+    pub async fn budget_usage(
+        &self,
+        session_token: &str,
+        level: BudgetLevel,
+        group_id: &str,
+        window_id: &UsageWindowId,
+    ) -> Result<BudgetUsageNode> {
+        let caller = self.authenticate(session_token).await?;
+        let path = self.quota_service.store().budget_path(&caller.actor_id).await?;
+        let visible = match level {
+            BudgetLevel::Organisation => path.organisation.as_deref() == Some(group_id),
+            BudgetLevel::Project => path.project.as_deref() == Some(group_id),
+            BudgetLevel::Actor => caller.actor_id.0 == group_id,
+        };
+        if !visible {
+            return Err(OrchestratorError::Forbidden(format!(
+                "{} {} is not on the caller's budget path",
+                level.as_str(),
+                group_id
+            ))
+            .into());
+        }
+        self.quota_service.budget_report(level, group_id, window_id).await
+    }
+
+    /// Loads a job on behalf of its owner.
+    async fn owned_job(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+    ) -> Result<(ActorId, JobRecord)> {
+        let actor = self.authenticate(session_token).await?;
+        let record = self.job_store.get(reservation_id).await?;
+        if record.actor_id.0 != actor.actor_id.0 {
+            return Err(OrchestratorError::Forbidden(format!(
+                "job {} belongs to another actor",
+                reservation_id.0
+            ))
+            .into());
+        }
+        Ok((actor.actor_id, record))
+    }
+
+    pub async fn plan(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+    ) -> Result<PlanView> {
+        let (_, job) = self.owned_job(session_token, reservation_id).await?;
+        let reservation = self.quota_service.store().get_reservation(reservation_id).await?;
+        let approval = match job.plan.approval {
+            Some(_) => Some(self.approval_store.get(reservation_id).await?),
+            None => None,
+        };
+        Ok(PlanView {
+            job,
+            reservation_state: reservation.state,
+            deferral: self.scheduler.status(reservation_id),
+            approval,
         })
     }
+
+    /// Marks a job as running. Its reservation must be active, i.e. not still
+    /// deferred, awaiting approval, released or expired.
+    pub async fn start_job(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+    ) -> Result<JobRecord> {
+        self.begin_job(session_token, reservation_id, None).await
+    }
+
+    /// Starts the job by handing `command` to the configured executor, under
+    /// the approved tier's limits. `JobStarted` is only logged once the
+    /// executor has accepted the job; `run_executor_once` settles it when the
+    /// execution ends.
+    pub async fn launch_job(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+        command: LaunchCommand,
+    ) -> Result<JobRecord> {
+        self.begin_job(session_token, reservation_id, Some(command)).await
+    }
+
+    async fn begin_job(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+        command: Option<LaunchCommand>,
+    ) -> Result<JobRecord> {
+        let (actor_id, mut record) = self.owned_job(session_token, reservation_id).await?;
+        let reservation = self.quota_service.store().get_reservation(reservation_id).await?;
+        if reservation.state != ReservationState::Active {
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} is {}; the job cannot start",
+                reservation_id.0,
+                reservation.state.as_str()
+            ))
+            .into());
+        }
+
+        let previous = record.advance(JobStatus::Running)?;
+        record.started_at = Some(SystemTime::now());
+        let Some(command) = command else {
+            self.job_store.replace(&record, previous).await?;
+            log_execution_plan(&self.logger, &record.plan, &actor_id).await?;
+            return Ok(record);
+        };
+
+        let executor = self.executor.as_ref().ok_or_else(|| {
+            OrchestratorError::InvalidRequest("no executor is configured".into())
+        })?;
+        record.launched = true;
+        executor
+            .submit(&ExecutionRequest {
+                reservation_id: reservation_id.clone(),
+                tier: record.plan.approved_tier.clone(),
+                command,
+                max_duration: reservation.max_duration,
+            })
+            .await?;
+        if let Err(e) = self.job_store.replace(&record, previous).await {
+            // Someone else moved the job on; do not leave the process behind.
+            let _ = executor.cancel(reservation_id).await;
+            return Err(e);
+        }
+        log_execution_plan(&self.logger, &record.plan, &actor_id).await?;
+        Ok(record)
+    }
+
+    /// Reconciles a running job's usage (see `settle`) and stores its
+    /// receipt. `flops_used` is the executor's count, if it has one; it is
+    /// never charged below the hold's rate for the time the job ran.
+    pub async fn finish_job(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+        flops_used: Option<f64>,
+    ) -> Result<JobRecord> {
+        let (_, record) = self.owned_job(session_token, reservation_id).await?;
+        self.settle(record, JobStatus::Completed, flops_used, None).await
+    }
+
+    /// Cancels a job. A job that has not started hands back its hold; a
+    /// running or suspended one is charged for what it used so far (the full expected FLOPs
+    /// unless `flops_used` says otherwise, and never less than the hold's rate
+    /// for the time it ran), so cancelling never dodges quota.
+    pub async fn cancel_job(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+        reason: String,
+        flops_used: Option<f64>,
+    ) -> Result<JobRecord> {
+        let (actor_id, mut record) = self.owned_job(session_token, reservation_id).await?;
+        check_flops_used(flops_used)?;
+        if matches!(record.status, JobStatus::Running | JobStatus::Suspended) {
+            if let Some(executor) = &self.executor {
+                match executor.cancel(reservation_id).await {
+                    Ok(()) => {}
+                    Err(e) => match OrchestratorError::find(&e) {
+                        // started without the executor
+                        Some(OrchestratorError::NotFound(_)) => {}
+                        _ => return Err(e),
+                    },
+                }
+            }
+            let record = self.settle(record, JobStatus::Cancelled, flops_used, None).await?;
+            self.log_cancellation(&record, &actor_id, &reason).await?;
+            return Ok(record);
+        }
+
+        let previous = record.advance(JobStatus::Cancelled)?;
+        record.finished_at = Some(SystemTime::now());
+        self.job_store.replace(&record, previous).await?;
+        self.scheduler.remove(reservation_id);
+        let reservation = self.quota_service.store().get_reservation(reservation_id).await?;
+        if reservation.state.is_outstanding() {
+            self.quota_service.release(reservation_id).await?;
+        }
+        self.log_cancellation(&record, &actor_id, &reason).await?;
+        Ok(record)
+    }
+
+    /// Charges a finished job for what its approved segment measurably drew
+    /// while it ran: the segment's `xr_power_kw` is integrated from the job's
+    /// start until `finished_at` (when the executor saw it end; now otherwise)
+    /// and the job is attributed the share matching its share of the
+    /// segment's FLOPs. Carbon follows the renewable share at each sample. If
+    /// no telemetry covers the run, the reservation's estimate is charged
+    /// instead. A reported `flops_used` must be finite and non-negative, and
+    /// is raised to the reservation's expected FLOPs pro rata to the runtime
+    /// if it is lower.
+    ///
+    /// Commits the usage, logs `JobCompleted` and stores a receipt (signed
+    /// when a receipt signer is configured) whose remaining allowance is net
+    /// of everything committed or still held in the window.
+    async fn settle(
+        &self,
+        mut record: JobRecord,
+        next: JobStatus,
+        flops_used: Option<f64>,
+        finished_at: Option<SystemTime>,
+    ) -> Result<JobRecord> {
+        let started_at = record.started_at.ok_or_else(|| {
+            OrchestratorError::Conflict(format!("job {} never started", record.reservation_id().0))
+        })?;
+        let previous = record.advance(next)?;
+        let now = SystemTime::now();
+        let finished_at = finished_at.unwrap_or(now).clamp(started_at, now.max(started_at));
+        record.finished_at = Some(finished_at);
+        let completion = JobCompletion {
+            reservation_id: record.reservation_id().clone(),
+            segment_id: record.plan.approved_segment.clone(),
+            started_at,
+            finished_at,
+            flops_used,
+        };
+
+        // Committing the hold is the claim: a concurrent finish/cancel fails
+        // here, and the job only reaches its final status once it is charged.
+        let committed = self.commit_completion(&completion).await?;
+        let reconciled = self.issue_receipt(&completion, committed).await;
+        if let Ok(reconciled) = &reconciled {
+            record.receipt = Some(JobReceipt {
+                receipt: reconciled.receipt.clone(),
+                signed_receipt: reconciled.signed_receipt.clone(),
+            });
+        }
+        self.job_store.replace(&record, previous).await?;
+        reconciled?;
+        Ok(record)
+    }
+
+    async fn log_cancellation(
+        &self,
+        record: &JobRecord,
+        actor_id: &ActorId,
+        reason: &str,
+    ) -> Result<()> {
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::JobCancelled,
+                reservation_id: Some(record.reservation_id().clone()),
+                actor_id: Some(actor_id.clone()),
+                segment_id: Some(record.plan.approved_segment.clone()),
+                window_id: Some(record.window_id.clone()),
+                metadata: serde_json::json!({
+                    "reason": reason,
+                    "charged": record.receipt.is_some(),
+                }),
+            })
+            .await
+    }
+
+    pub async fn receipt(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+    ) -> Result<JobReceipt> {
+        let (_, record) = self.owned_job(session_token, reservation_id).await?;
+        Ok(record.receipt.ok_or_else(|| {
+            OrchestratorError::NotFound(format!("job {} has no receipt yet", reservation_id.0))
+        })?)
+    }
+
+    /// Parks the job for a later window if the segment is too hot, or too
+    /// carbon-intensive and the caller gave a deadline it is willing to wait
+    /// for; otherwise activates the hold, or hands it back on a denial.
+    #[allow(clippy::too_many_arguments)]
+    async fn dispatch(
+        &self,
+        reservation_id: &ReservationId,
+        actor_id: &ActorId,
+        segment_id: &SegmentId,
+        window_id: &UsageWindowId,
+        job: &EcologicalJobSpec,
+        stability: &StabilityDecision,
+        fair_share: Option<&FairShareDecision>,
+    ) -> Result<Option<DeferralStatus>> {
+        // Jobs over their burst allowance on a contended segment wait at
+        // least one scheduler tick, queued by fair-share weight.
+        let over_burst = fair_share.filter(|d| !d.within_burst);
+        let deferral = match stability {
+            StabilityDecision::Throttle {
+                reason,
+                recommended_delay,
+            } => Some((reason.clone(), *recommended_delay)),
+            StabilityDecision::Deny { reason } if job.deadline.is_some() => {
+                Some((reason.clone(), Duration::ZERO))
+            }
+            StabilityDecision::Ok | StabilityDecision::Downgrade { .. } => over_burst
+                .map(|d| (d.explanation.clone(), self.scheduler.poll_interval())),
+            _ => None,
+        }
+        .map(|(reason, delay)| {
+            let now = SystemTime::now();
+            self.scheduler.enqueue(DeferredJob {
+                reservation_id: reservation_id.clone(),
+                actor_id: actor_id.clone(),
+                segment_id: segment_id.clone(),
+                window_id: window_id.clone(),
+                tier: job.requested_tier.clone(),
+                enqueued_at: now,
+                not_before: now + delay,
+                deadline: job.deadline.unwrap_or(now + job.max_duration),
+                reason,
+                priority: fair_share.map_or(0.0, |d| d.weight),
+            })
+        });
+
+        // Deferred jobs keep a pending hold until the scheduler releases them.
+        if deferral.is_none() {
+            if let StabilityDecision::Deny { .. } = stability {
+                self.quota_service.release(reservation_id).await?;
+            } else {
+                self.quota_service.activate(reservation_id).await?;
+            }
+        }
+        Ok(deferral)
+    }
+
+    /// Records a reviewer's verdict on a held plan. Once quorum is reached the
+    /// job is re-checked against current telemetry and started, queued or
+    /// released like a fresh plan; a rejection hands the hold back.
+    ///
+    /// Reviews must land before the pending hold expires (see
+    /// `with_review_ttl`); once it has, the request is rejected instead.
+    pub async fn review_plan(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+        verdict: ReviewVerdict,
+        comment: String,
+    ) -> Result<ApprovalRequest> {
+        let reviewer = self.authenticate(session_token).await?;
+        let pending = self.approval_store.get(reservation_id).await?;
+        if pending.status == ApprovalStatus::Pending && self.withdraw_if_stale(&pending).await? {
+            return Err(OrchestratorError::Conflict(format!(
+                "the hold for {} expired before review; the job has to be planned again",
+                reservation_id.0
+            ))
+            .into());
+        }
+        let request = self
+            .approval_store
+            .record_review(
+                reservation_id,
+                ReviewRecord {
+                    reviewer: reviewer.actor_id.clone(),
+                    reviewer_clearance: reviewer.clearance_level,
+                    verdict,
+                    comment,
+                    at: SystemTime::now(),
+                },
+            )
+            .await?;
+
+        let mut metadata = serde_json::json!({
+            "reviewer": reviewer.actor_id.0,
+            "verdict": verdict,
+            "status": request.status,
+            "approvals": request.approvals(),
+            "required_approvals": request.required_approvals,
+        });
+        let outcome = match request.status {
+            ApprovalStatus::Pending => Ok(()),
+            ApprovalStatus::Rejected => self.quota_service.release(reservation_id).await,
+            ApprovalStatus::Approved => self.place_approved(&request).await.map(
+                |(stability, deferral)| {
+                    metadata["stability_decision"] = serde_json::json!(format!("{:?}", stability));
+                    metadata["deferral"] = serde_json::json!(deferral);
+                },
+            ),
+        };
+        if let Err(e) = &outcome {
+            metadata["error"] = serde_json::json!(e.to_string());
+        }
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::ApprovalReviewed,
+                reservation_id: Some(reservation_id.clone()),
+                actor_id: Some(request.actor_id.clone()),
+                segment_id: Some(request.segment_id.clone()),
+                window_id: Some(request.window_id.clone()),
+                metadata,
+            })
+            .await?;
+        outcome?;
+
+        Ok(request)
+    }
+
+    async fn place_approved(
+        &self,
+        request: &ApprovalRequest,
+    ) -> Result<(StabilityDecision, Option<DeferralStatus>)> {
+        let load = self
+            .stability_guard
+            .telemetry()
+            .get_segment_load(&request.segment_id)
+            .await?;
+        let mut job = request.job.clone();
+        job.requested_tier = request.approved_tier.clone();
+        let stability = self.stability_guard.check_load(&load, &job.requested_tier);
+        if let StabilityDecision::Downgrade { ref downgraded_tier, .. } = stability {
+            job.requested_tier = downgraded_tier.clone();
+        }
+        self.record_stability(&request.reservation_id, &stability).await?;
+
+        // The fair-share standing is the one recorded when the job was planned.
+        let fair_share = self.job_store.get(&request.reservation_id).await?.plan.fair_share;
+        let deferral = self
+            .dispatch(
+                &request.reservation_id,
+                &request.actor_id,
+                &request.segment_id,
+                &request.window_id,
+                &job,
+                &stability,
+                fair_share.as_ref(),
+            )
+            .await?;
+        if let Some(deferral) = &deferral {
+            // kept on the record so `restore_deferred` can re-queue the job
+            let mut record = self.job_store.get(&request.reservation_id).await?;
+            record.plan.deferral = Some(deferral.clone());
+            self.job_store.replace(&record, record.status).await?;
+        }
+        Ok((stability, deferral))
+    }
+
+    /// A held plan, visible to its requester and to anyone cleared to review it.
+    pub async fn approval(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+    ) -> Result<ApprovalRequest> {
+        let viewer = self.authenticate(session_token).await?;
+        let request = self.approval_store.get(reservation_id).await?;
+        if request.actor_id.0 != viewer.actor_id.0
+            && viewer.clearance_level < request.min_reviewer_clearance
+        {
+            return Err(OrchestratorError::Forbidden(
+                "insufficient clearance to view this approval".into(),
+            )
+            .into());
+        }
+        Ok(request)
+    }
+
+    /// Plans still awaiting review that the caller is cleared to review.
+    pub async fn pending_approvals(&self, session_token: &str) -> Result<Vec<ApprovalRequest>> {
+        let reviewer = self.authenticate(session_token).await?;
+        self.expire_stale_approvals().await?;
+        Ok(self
+            .approval_store
+            .list_pending()
+            .await?
+            .into_iter()
+            .filter(|r| {
+                r.actor_id.0 != reviewer.actor_id.0
+                    && reviewer.clearance_level >= r.min_reviewer_clearance
+            })
+            .collect())
+    }
+
+    /// Rejects every pending approval whose hold has expired, so reviewers
+    /// are not asked to sign off plans that can no longer run. Returns the
+    /// rejected reservations.
+    pub async fn expire_stale_approvals(&self) -> Result<Vec<ReservationId>> {
+        let mut expired = Vec::new();
+        for request in self.approval_store.list_pending().await? {
+            if self.withdraw_if_stale(&request).await? {
+                expired.push(request.reservation_id);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Withdraws `request` if its hold is gone or past its expiry, handing
+    /// back a hold the sweeper has not reached yet. A request decided
+    /// concurrently is left alone.
+    async fn withdraw_if_stale(&self, request: &ApprovalRequest) -> Result<bool> {
+        let reservation = self
+            .quota_service
+            .store()
+            .get_reservation(&request.reservation_id)
+            .await?;
+        let pending = reservation.state == ReservationState::Pending;
+        if pending && reservation.expires_at > SystemTime::now() {
+            return Ok(false);
+        }
+        // Withdraw before touching the hold: a review that wins the race keeps
+        // the request, and with it the hold.
+        let reason = "the hold expired before reviewers reached quorum";
+        let request = match self.approval_store.withdraw(&request.reservation_id, reason).await {
+            Ok(request) => request,
+            Err(e) => match OrchestratorError::find(&e) {
+                Some(OrchestratorError::Conflict(_)) => return Ok(false),
+                _ => return Err(e),
+            },
+        };
+        if pending {
+            match self.quota_service.release(&reservation.id).await {
+                Ok(()) => {}
+                Err(e) => match OrchestratorError::find(&e) {
+                    // the sweeper expired it meanwhile
+                    Some(OrchestratorError::Conflict(_)) => {}
+                    _ => return Err(e),
+                },
+            }
+        }
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::ApprovalReviewed,
+                reservation_id: Some(request.reservation_id.clone()),
+                actor_id: Some(request.actor_id.clone()),
+                segment_id: Some(request.segment_id.clone()),
+                window_id: Some(request.window_id.clone()),
+                metadata: serde_json::json!({
+                    "status": request.status,
+                    "approvals": request.approvals(),
+                    "required_approvals": request.required_approvals,
+                    "withdrawn": reason,
+                }),
+            })
+            .await?;
+        Ok(true)
+    }
+
+    /// Reads current telemetry for each segment into the power log. Segments
+    /// that fail to report are skipped.
+    pub async fn sample_power_once(&self, segments: &[SegmentId]) {
+        for segment in segments {
+            if let Ok(load) = self.stability_guard.telemetry().get_segment_load(segment).await {
+                self.power_log.record(&load, SystemTime::now());
+            }
+        }
+    }
+
+    /// Runs `sample_power_once` every `interval`. Reconciliation is only as
+    /// fine-grained as this interval.
+    pub fn spawn_power_sampler(
+        self: Arc<Self>,
+        segments: Vec<SegmentId>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        I: 'static,
+        Z: 'static,
+        Q: 'static,
+        T: 'static,
+        P: 'static,
+        L: 'static,
+        A: 'static,
+        J: 'static,
+    {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.sample_power_once(&segments).await;
+            }
+        })
+    }
+
+    async fn commit_completion(&self, completion: &JobCompletion) -> Result<CommittedUsage> {
+        if completion.finished_at < completion.started_at {
+            return Err(
+                OrchestratorError::InvalidRequest("job finished before it started".into()).into(),
+            );
+        }
+        check_flops_used(completion.flops_used)?;
+        let store = self.quota_service.store();
+        let reservation = store.get_reservation(&completion.reservation_id).await?;
+        let ran = completion
+            .finished_at
+            .duration_since(completion.started_at)
+            .unwrap_or_default();
+        // A reported count never undercuts the hold's rate over the runtime.
+        let flops_used = match completion.flops_used {
+            Some(flops) => flops.max(flops_at_reserved_rate(&reservation, ran)),
+            None => reservation.expected_flops,
+        };
+
+        let samples = self.power_log.samples_around(
+            &completion.segment_id,
+            completion.started_at,
+            completion.finished_at,
+        );
+        let segment = integrate(
+            &samples,
+            completion.started_at,
+            completion.finished_at,
+            &self.energy_estimator,
+        );
+        let runtime_secs = ran.as_secs();
+
+        let (actuals, attributed_share, explanation) = match &segment {
+            Some(integral) => {
+                let share = if integral.flops > 0.0 {
+                    (flops_used / integral.flops).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                let actuals = ReservationActuals {
+                    flops_used,
+                    energy_kwh_used: integral.energy_kwh * share,
+                    carbon_kg_emitted: integral.carbon_kg * share,
+                };
+                let explanation = format!(
+                    "Measured from {} xr_power_kw samples over {}s on {}: {:.1}% of the segment's \
+                     {:.3} kWh attributed to this job by FLOP share.",
+                    integral.samples_used,
+                    runtime_secs,
+                    completion.segment_id.0,
+                    share * 100.0,
+                    integral.energy_kwh
+                );
+                (actuals, Some(share), explanation)
+            }
+            None => {
+                let actuals = ReservationActuals {
+                    flops_used,
+                    energy_kwh_used: reservation.expected_energy_kwh,
+                    carbon_kg_emitted: reservation.expected_carbon_kg,
+                };
+                let explanation = format!(
+                    "No power telemetry covered the {}s run on {}; charged the reserved estimate.",
+                    runtime_secs, completion.segment_id.0
+                );
+                (actuals, None, explanation)
+            }
+        };
+
+        let fair_share = self.fair_share_allocator().await?;
+        self.quota_service
+            .commit_usage(&completion.reservation_id, &actuals)
+            .await?;
+        if let Some(allocator) = fair_share {
+            let finished_at = completion.finished_at;
+            allocator.record_usage(&reservation.actor_id, actuals.flops_used, finished_at);
+        }
+        Ok(CommittedUsage {
+            reservation,
+            actuals,
+            attributed_share,
+            segment,
+            runtime_secs,
+            explanation,
+        })
+    }
+
+    async fn issue_receipt(
+        &self,
+        completion: &JobCompletion,
+        committed: CommittedUsage,
+    ) -> Result<ReconciledJob> {
+        let CommittedUsage {
+            reservation,
+            actuals,
+            attributed_share,
+            segment,
+            runtime_secs,
+            explanation,
+        } = committed;
+        let store = self.quota_service.store();
+        let allowance = store
+            .get_allowance(&reservation.actor_id, &reservation.window_id)
+            .await?;
+        let usage = store
+            .get_usage(&reservation.actor_id, &reservation.window_id)
+            .await?;
+        let remaining = remaining_allowance(&allowance, &usage);
+        let mut receipt = build_receipt(
+            completion.reservation_id.clone(),
+            reservation.actor_id.clone(),
+            completion.segment_id.clone(),
+            reservation.window_id.clone(),
+            actuals.flops_used,
+            actuals.energy_kwh_used,
+            actuals.carbon_kg_emitted,
+            remaining.flops,
+            remaining.energy_kwh,
+            remaining.carbon_kg,
+        );
+        receipt.explanation = explanation;
+        // Completions reported outside the job lifecycle have no plan on file.
+        receipt.fair_share = match self.job_store.get(&completion.reservation_id).await {
+            Ok(record) => record.plan.fair_share,
+            Err(e) => match OrchestratorError::find(&e) {
+                Some(OrchestratorError::NotFound(_)) => None,
+                _ => return Err(e),
+            },
+        };
+
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::JobCompleted,
+                reservation_id: Some(completion.reservation_id.clone()),
+                actor_id: Some(reservation.actor_id.clone()),
+                segment_id: Some(completion.segment_id.clone()),
+                window_id: Some(reservation.window_id.clone()),
+                metadata: serde_json::json!({
+                    "actuals": actuals,
+                    "runtime_secs": runtime_secs,
+                    "attributed_share": attributed_share,
+                    "segment": segment,
+                    "reserved_energy_kwh": reservation.expected_energy_kwh,
+                    "reserved_carbon_kg": reservation.expected_carbon_kg,
+                }),
+            })
+            .await?;
+
+        let signed_receipt = match &self.receipt_signer {
+            Some(signer) => Some(signer.sign(receipt.clone())?),
+            None => None,
+        };
+
+        Ok(ReconciledJob {
+            actuals,
+            attributed_share,
+            segment,
+            receipt,
+            signed_receipt,
+        })
+    }
+
+    pub fn deferral_status(&self, reservation_id: &ReservationId) -> Option<DeferralStatus> {
+        self.scheduler.status(reservation_id)
+    }
+
+    /// A deferred job's place in the queue, as seen by its owner. Other
+    /// actors are told it is not queued rather than that it exists.
+    pub async fn deferral(
+        &self,
+        session_token: &str,
+        reservation_id: &ReservationId,
+    ) -> Result<DeferralStatus> {
+        let actor = self.authenticate(session_token).await?;
+        let owned = match self.job_store.get(reservation_id).await {
+            Ok(record) => record.actor_id.0 == actor.actor_id.0,
+            Err(e) => match OrchestratorError::find(&e) {
+                Some(OrchestratorError::NotFound(_)) => false,
+                _ => return Err(e),
+            },
+        };
+        let status = if owned { self.scheduler.status(reservation_id) } else { None };
+        status.ok_or_else(|| {
+            OrchestratorError::NotFound(format!(
+                "reservation {} is not waiting in the deferral queue",
+                reservation_id.0
+            ))
+            .into()
+        })
+    }
+
+    /// Puts deferred jobs back in the queue after a restart: every planned job
+    /// that was deferred and whose hold is still pending is queued again, to
+    /// be re-checked on the next tick. Returns how many were queued.
+    pub async fn restore_deferred(&self) -> Result<usize> {
+        let now = SystemTime::now();
+        let mut restored = 0;
+        for record in self.job_store.list_by_status(JobStatus::Planned).await? {
+            let Some(deferral) = &record.plan.deferral else {
+                continue;
+            };
+            let reservation_id = record.reservation_id();
+            if self.scheduler.status(reservation_id).is_some() {
+                continue;
+            }
+            let reservation = self.quota_service.store().get_reservation(reservation_id).await?;
+            if reservation.state != ReservationState::Pending {
+                continue;
+            }
+            self.scheduler.enqueue(DeferredJob {
+                reservation_id: reservation_id.clone(),
+                actor_id: record.actor_id.clone(),
+                segment_id: record.plan.approved_segment.clone(),
+                window_id: record.window_id.clone(),
+                tier: record.plan.approved_tier.clone(),
+                enqueued_at: record.planned_at,
+                not_before: now,
+                deadline: deferral.deadline,
+                reason: deferral.reason.clone(),
+                priority: record.plan.fair_share.as_ref().map_or(0.0, |d| d.weight),
+            });
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// The fair-share allocator, if any, with recent usage rebuilt from the
+    /// receipts of settled jobs the first time it is needed.
+    async fn fair_share_allocator(&self) -> Result<Option<&FairShareAllocator>> {
+        let Some(allocator) = &self.fair_share else {
+            return Ok(None);
+        };
+        self.fair_share_restored
+            .get_or_try_init(|| async {
+                for status in [JobStatus::Completed, JobStatus::Cancelled] {
+                    for record in self.job_store.list_by_status(status).await? {
+                        if let (Some(receipt), Some(at)) = (&record.receipt, record.finished_at) {
+                            let flops = receipt.receipt.flops_used;
+                            allocator.record_usage(&record.actor_id, flops, at);
+                        }
+                    }
+                }
+                Ok::<_, anyhow::Error>(())
+            })
+            .await?;
+        Ok(Some(allocator))
+    }
+
+    /// Weighs a deferred job that was over its burst allowance again, and
+    /// returns the new decision if it still is. Contention is re-measured on
+    /// segments with a configured capacity and taken to persist elsewhere.
+    async fn still_over_burst(
+        &self,
+        job: &DeferredJob,
+        now: SystemTime,
+    ) -> Result<Option<FairShareDecision>> {
+        let Some(allocator) = self.fair_share_allocator().await? else {
+            return Ok(None);
+        };
+        let record = self.job_store.get(&job.reservation_id).await?;
+        let over_burst = record.plan.fair_share.as_ref().is_some_and(|d| !d.within_burst);
+        if !over_burst {
+            return Ok(None);
+        }
+        let reservation = self.quota_service.store().get_reservation(&job.reservation_id).await?;
+        let telemetry = self.stability_guard.telemetry();
+        let headroom = match telemetry.get_segment_load(&job.segment_id).await {
+            Ok(load) => self.placement_engine.capacity_headroom(&job.segment_id, &load),
+            Err(_) => None,
+        };
+        let share = allocator.assess_flops(
+            &record.actor_id,
+            record.priority_score,
+            reservation.expected_flops,
+            headroom.unwrap_or(0.0),
+            now,
+        );
+        Ok((!share.within_burst).then_some(share))
+    }
+
+    /// Puts a released job back in the queue if it is still over its burst
+    /// allowance. A job that cannot be weighed again is released as before.
+    async fn requeue_over_burst(&self, job: &DeferredJob, now: SystemTime) -> Result<bool> {
+        let Ok(Some(share)) = self.still_over_burst(job, now).await else {
+            return Ok(false);
+        };
+        let deferral = self.scheduler.enqueue(DeferredJob {
+            not_before: now + self.scheduler.poll_interval(),
+            reason: share.explanation.clone(),
+            priority: share.weight,
+            ..job.clone()
+        });
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::StabilityChecked,
+                reservation_id: Some(job.reservation_id.clone()),
+                actor_id: Some(job.actor_id.clone()),
+                segment_id: Some(job.segment_id.clone()),
+                window_id: Some(job.window_id.clone()),
+                metadata: serde_json::json!({
+                    "deferred": "requeued",
+                    "fair_share": share,
+                    "deferral": deferral,
+                }),
+            })
+            .await?;
+        Ok(true)
+    }
+
+    /// One scheduler pass: activates deferred jobs whose segment has recovered
+    /// and hands back the holds of jobs whose deadline passed while waiting.
+    /// Jobs deferred for exceeding their burst allowance are weighed again
+    /// first and keep waiting, up to their deadline, while they still do.
+    pub async fn run_scheduler_once(&self) -> Result<()> {
+        let now = SystemTime::now();
+        let outcomes = self.scheduler.tick(&self.stability_guard, now).await?;
+
+        for outcome in outcomes {
+            if let SchedulerOutcome::Released { job, .. } = &outcome {
+                if self.requeue_over_burst(job, now).await? {
+                    continue;
+                }
+            }
+            let (job, result, mut metadata) = match outcome {
+                SchedulerOutcome::Released { job, decision } => {
+                    let result = match self.record_stability(&job.reservation_id, &decision).await {
+                        Ok(()) => self.quota_service.activate(&job.reservation_id).await,
+                        Err(e) => Err(e),
+                    };
+                    let metadata = serde_json::json!({
+                        "stability_decision": format!("{:?}", decision),
+                        "deferred": "released",
+                        "waited_secs": now
+                            .duration_since(job.enqueued_at)
+                            .unwrap_or_default()
+                            .as_secs(),
+                    });
+                    (job, result, metadata)
+                }
+                SchedulerOutcome::DeadlineMissed(job) => {
+                    let result = self.quota_service.release(&job.reservation_id).await;
+                    let metadata = serde_json::json!({
+                        "deferred": "deadline_missed",
+                        "reason": job.reason,
+                    });
+                    (job, result, metadata)
+                }
+            };
+
+            if let Err(e) = &result {
+                // Typically the hold already expired or was cancelled.
+                metadata["error"] = serde_json::json!(e.to_string());
+            }
+            self.logger
+                .append(&EcologicalLogEvent {
+                    event_type: LogEventType::StabilityChecked,
+                    reservation_id: Some(job.reservation_id.clone()),
+                    actor_id: Some(job.actor_id.clone()),
+                    segment_id: Some(job.segment_id.clone()),
+                    window_id: Some(job.window_id.clone()),
+                    metadata,
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the decision a held or deferred job is finally placed under on
+    /// its plan, so a job placed with a downgrade runs at the lower tier.
+    async fn record_stability(
+        &self,
+        reservation_id: &ReservationId,
+        decision: &StabilityDecision,
+    ) -> Result<()> {
+        let mut record = self.job_store.get(reservation_id).await?;
+        if let StabilityDecision::Downgrade { downgraded_tier, .. } = decision {
+            record.plan.approved_tier = downgraded_tier.clone();
+        }
+        record.plan.stability_decision = decision.clone();
+        self.job_store.replace(&record, record.status).await
+    }
+
+    /// One pre-emption pass: reads telemetry for every segment with running
+    /// or suspended jobs and, where the controller sees grid stress, signals
+    /// its victims. A suspended job keeps its hold and is resumed by a later
+    /// pass that finds its segment unstressed; a downgraded one keeps running
+    /// at the lower tier, re-estimated there, and the part of its hold the
+    /// lower tier no longer needs goes back to the window. Every action is
+    /// logged as `StabilityChecked` with its rationale.
+    pub async fn run_preemption_once(&self) -> Result<()> {
+        let Some(controller) = &self.preemption else {
+            return Ok(());
+        };
+        let running = self.job_store.list_by_status(JobStatus::Running).await?;
+        let suspended = self.job_store.list_by_status(JobStatus::Suspended).await?;
+        let mut segments: Vec<SegmentId> = Vec::new();
+        for record in running.iter().chain(&suspended) {
+            if !segments.iter().any(|s| s.0 == record.plan.approved_segment.0) {
+                segments.push(record.plan.approved_segment.clone());
+            }
+        }
+
+        let telemetry = self.stability_guard.telemetry();
+        for segment in segments {
+            let Ok(load) = telemetry.get_segment_load(&segment).await else {
+                continue;
+            };
+            self.power_log.record(&load, SystemTime::now());
+            let Some(stress) = controller.observe(&load) else {
+                for record in suspended.iter().filter(|r| r.plan.approved_segment.0 == segment.0) {
+                    self.resume(controller, record.clone(), &load).await?;
+                }
+                continue;
+            };
+            let on_segment: Vec<JobRecord> = running
+                .iter()
+                .filter(|r| r.plan.approved_segment.0 == segment.0)
+                .cloned()
+                .collect();
+            for (record, signal) in controller.select_victims(on_segment, &stress) {
+                self.preempt(controller, record, signal, &stress, &load).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn preempt(
+        &self,
+        controller: &PreemptionController,
+        record: JobRecord,
+        signal: PreemptionSignal,
+        stress: &GridStress,
+        load: &SegmentLoad,
+    ) -> Result<()> {
+        let mut metadata = serde_json::json!({
+            "preemption": signal,
+            "rationale": stress.rationale,
+            "stress": stress.kind,
+            "thermal_margin_pct": load.thermal_margin_pct,
+            "renewable_share_pct": load.renewable_share_pct,
+            "priority_score": record.priority_score,
+            "tier": record.plan.approved_tier,
+        });
+        let reservation_id = record.reservation_id().clone();
+        let (actor_id, window_id) = (record.actor_id.clone(), record.window_id.clone());
+
+        let executor = controller.executor();
+        let mut signal = signal;
+        let mut delivered = executor.signal(&record, &signal, &stress.rationale).await;
+        // An executor that cannot re-tier a running job refuses the downgrade;
+        // suspend the job instead so the stress is still relieved.
+        if let (PreemptionSignal::Downgrade { .. }, Err(e)) = (&signal, &delivered) {
+            if let Some(OrchestratorError::InvalidRequest(_)) = OrchestratorError::find(e) {
+                metadata["downgrade_refused"] = serde_json::json!(e.to_string());
+                signal = PreemptionSignal::Suspend;
+                metadata["preemption"] = serde_json::json!(signal);
+                delivered = executor.signal(&record, &signal, &stress.rationale).await;
+            }
+        }
+        let outcome = match delivered {
+            Ok(()) => self.apply_preemption(record, &signal, load).await,
+            Err(e) => Err(e),
+        };
+        match &outcome {
+            Ok(Some(refunded)) => metadata["refunded"] = serde_json::json!(refunded),
+            Ok(None) => {}
+            // Typically the job finished or was cancelled meanwhile.
+            Err(e) => metadata["error"] = serde_json::json!(e.to_string()),
+        }
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::StabilityChecked,
+                reservation_id: Some(reservation_id),
+                actor_id: Some(actor_id),
+                segment_id: Some(load.segment_id.clone()),
+                window_id: Some(window_id),
+                metadata,
+            })
+            .await
+    }
+
+    /// Resumes a suspended job whose segment is no longer under stress.
+    async fn resume(
+        &self,
+        controller: &PreemptionController,
+        record: JobRecord,
+        load: &SegmentLoad,
+    ) -> Result<()> {
+        let signal = PreemptionSignal::Resume;
+        let rationale = format!("grid stress on {} has eased", load.segment_id.0);
+        let mut metadata = serde_json::json!({
+            "preemption": signal,
+            "rationale": rationale,
+            "thermal_margin_pct": load.thermal_margin_pct,
+            "renewable_share_pct": load.renewable_share_pct,
+            "priority_score": record.priority_score,
+            "tier": record.plan.approved_tier,
+        });
+        let reservation_id = record.reservation_id().clone();
+        let (actor_id, window_id) = (record.actor_id.clone(), record.window_id.clone());
+        let outcome = match controller.executor().signal(&record, &signal, &rationale).await {
+            Ok(()) => self.apply_preemption(record, &signal, load).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = outcome {
+            metadata["error"] = serde_json::json!(e.to_string());
+        }
+        self.logger
+            .append(&EcologicalLogEvent {
+                event_type: LogEventType::StabilityChecked,
+                reservation_id: Some(reservation_id),
+                actor_id: Some(actor_id),
+                segment_id: Some(load.segment_id.clone()),
+                window_id: Some(window_id),
+                metadata,
+            })
+            .await
+    }
+
+    /// Carries out a delivered signal. Returns what a downgrade handed back
+    /// to the window.
+    async fn apply_preemption(
+        &self,
+        mut record: JobRecord,
+        signal: &PreemptionSignal,
+        load: &SegmentLoad,
+    ) -> Result<Option<RemainingAllowance>> {
+        let next = match signal {
+            PreemptionSignal::Suspend => JobStatus::Suspended,
+            PreemptionSignal::Resume => JobStatus::Running,
+            PreemptionSignal::Downgrade { tier } => {
+                return self.downgrade(record, tier, load).await.map(Some);
+            }
+        };
+        let previous = record.advance(next)?;
+        self.job_store.replace(&record, previous).await?;
+        Ok(None)
+    }
+
+    /// Moves a running job to `tier` and shrinks its hold to the lower tier's
+    /// estimate on the segment's current load, returning the difference.
+    async fn downgrade(
+        &self,
+        mut record: JobRecord,
+        tier: &CapabilityTier,
+        load: &SegmentLoad,
+    ) -> Result<RemainingAllowance> {
+        let store = self.quota_service.store();
+        let before = store.get_reservation(record.reservation_id()).await?;
+        let estimate = self
+            .energy_estimator
+            .estimate_flops(before.expected_flops, tier, load);
+        store
+            .shrink_hold(record.reservation_id(), tier, estimate.energy_kwh, estimate.carbon_kg)
+            .await?;
+        let after = store.get_reservation(record.reservation_id()).await?;
+        record.plan.approved_tier = tier.clone();
+        record.plan.estimated_energy_kwh = after.expected_energy_kwh;
+        record.plan.estimated_carbon_kg = after.expected_carbon_kg;
+        self.job_store.replace(&record, JobStatus::Running).await?;
+        Ok(RemainingAllowance {
+            flops: 0.0,
+            energy_kwh: before.expected_energy_kwh - after.expected_energy_kwh,
+            carbon_kg: before.expected_carbon_kg - after.expected_carbon_kg,
+        })
+    }
+
+    /// One executor pass: settles every running or suspended job whose
+    /// execution has ended, which logs its `JobCompleted`. A successful run is charged its
+    /// expected FLOPs; a failed or externally cancelled one is cancelled and
+    /// charged for the share of its time limit it ran. A launched job the
+    /// executor no longer knows (e.g. after a restart) is cancelled the same
+    /// way, as of now; jobs started by hand are left alone.
+    pub async fn run_executor_once(&self) -> Result<()> {
+        let Some(executor) = &self.executor else {
+            return Ok(());
+        };
+        let mut records = self.job_store.list_by_status(JobStatus::Running).await?;
+        records.extend(self.job_store.list_by_status(JobStatus::Suspended).await?);
+        for record in records {
+            let status = match executor.status(record.reservation_id()).await {
+                Ok(status) => status,
+                Err(e) => match OrchestratorError::find(&e) {
+                    Some(OrchestratorError::NotFound(_)) if record.launched => ExecutionStatus {
+                        state: ExecutionState::Failed {
+                            reason: "the executor has no record of it".into(),
+                        },
+                        started_at: record.started_at.unwrap_or_else(SystemTime::now),
+                        finished_at: None,
+                    },
+                    Some(OrchestratorError::NotFound(_)) => continue,
+                    _ => return Err(e),
+                },
+            };
+            let reason = match status.state {
+                ExecutionState::Running | ExecutionState::Suspended => continue,
+                ExecutionState::Succeeded => {
+                    let finished_at = status.finished_at;
+                    self.settle(record, JobStatus::Completed, None, finished_at).await?;
+                    continue;
+                }
+                ExecutionState::Failed { reason } => format!("execution failed: {}", reason),
+                ExecutionState::Cancelled => "execution was cancelled".to_string(),
+            };
+            let reservation = self
+                .quota_service
+                .store()
+                .get_reservation(record.reservation_id())
+                .await?;
+            let finished_at = status.finished_at.unwrap_or_else(SystemTime::now);
+            let flops_used = flops_for_runtime(&record, &reservation, finished_at);
+            let actor_id = record.actor_id.clone();
+            let record = self
+                .settle(record, JobStatus::Cancelled, Some(flops_used), Some(finished_at))
+                .await?;
+            self.log_cancellation(&record, &actor_id, &reason).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs `run_executor_once` every `interval`.
+    pub fn spawn_executor(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()>
+    where
+        I: 'static,
+        Z: 'static,
+        Q: 'static,
+        T: 'static,
+        P: 'static,
+        L: 'static,
+        A: 'static,
+        J: 'static,
+    {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_executor_once().await {
+                    tracing::warn!(error = ?e, "executor pass failed");
+                }
+            }
+        })
+    }
+
+    /// Runs `run_preemption_once` every `interval`.
+    pub fn spawn_preemption(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()>
+    where
+        I: 'static,
+        Z: 'static,
+        Q: 'static,
+        T: 'static,
+        P: 'static,
+        L: 'static,
+        A: 'static,
+        J: 'static,
+    {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_preemption_once().await {
+                    tracing::warn!(error = ?e, "pre-emption pass failed");
+                }
+            }
+        })
+    }
+
+    /// Restores the deferral queue (see `restore_deferred`), then runs
+    /// `run_scheduler_once` every scheduler poll interval.
+    pub fn spawn_scheduler(self: Arc<Self>) -> tokio::task::JoinHandle<()>
+    where
+        I: 'static,
+        Z: 'static,
+        Q: 'static,
+        T: 'static,
+        P: 'static,
+        L: 'static,
+        A: 'static,
+        J: 'static,
+    {
+        tokio::spawn(async move {
+            if let Err(e) = self.restore_deferred().await {
+                tracing::warn!(error = ?e, "restoring the deferral queue failed");
+            }
+            let mut ticker = tokio::time::interval(self.scheduler.poll_interval());
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_scheduler_once().await {
+                    tracing::warn!(error = ?e, "deferred scheduler pass failed");
+                }
+            }
+        })
+    }
+}
+
+/// The share of the reservation's expected FLOPs that the job's runtime up to
+/// `until` covers of its time limit.
+fn flops_for_runtime(record: &JobRecord, reservation: &Reservation, until: SystemTime) -> f64 {
+    let ran = record
+        .started_at
+        .and_then(|t| until.duration_since(t).ok())
+        .unwrap_or_default();
+    flops_at_reserved_rate(reservation, ran)
+}
+
+fn check_flops_used(flops_used: Option<f64>) -> Result<()> {
+    match flops_used {
+        Some(flops) if !flops.is_finite() || flops < 0.0 => {
+            Err(OrchestratorError::InvalidRequest(format!(
+                "flops_used must be a finite, non-negative count, got {}",
+                flops
+            ))
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The reservation's expected FLOPs spread evenly over its time limit, for
+/// `ran` of it.
+fn flops_at_reserved_rate(reservation: &Reservation, ran: Duration) -> f64 {
+    let covered = ran.as_secs_f64() / reservation.max_duration.as_secs_f64().max(1e-9);
+    reservation.expected_flops * covered.clamp(0.0, 1.0)
 }
@@ -0,0 +1,199 @@
+use crate::energy::{SegmentTelemetry, StabilityGuard};
+use crate::types::{
+    ActorId, CapabilityTier, DeferralStatus, ReservationId, SegmentId, StabilityDecision,
+    UsageWindowId,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredJob {
+    pub reservation_id: ReservationId,
+    pub actor_id: ActorId,
+    pub segment_id: SegmentId,
+    pub window_id: UsageWindowId,
+    pub tier: CapabilityTier,
+    pub enqueued_at: SystemTime,
+    pub not_before: SystemTime,
+    pub deadline: SystemTime,
+    pub reason: String,
+    // fair-share weight; higher weights are released first
+    #[serde(default)]
+    pub priority: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum SchedulerOutcome {
+    /// The segment now satisfies the stability thresholds for this job, or
+    /// would at the lower tier a `Downgrade` decision names.
+    Released {
+        job: DeferredJob,
+        decision: StabilityDecision,
+    },
+    /// No suitable window opened before the job's deadline.
+    DeadlineMissed(DeferredJob),
+}
+
+/// Holds jobs that could not start immediately because their segment was too
+/// hot, too carbon-intensive or contended, and releases them, highest
+/// priority first and oldest first within a priority, on the first tick where
+/// the segment passes the stability guard again.
+///
+/// At most `releases_per_tick` jobs leave a segment's queue per tick so a
+/// recovering segment is not immediately pushed back over its thresholds.
+pub struct DeferredScheduler {
+    queue: Mutex<Vec<DeferredJob>>,
+    last_tick: Mutex<Option<SystemTime>>,
+    poll_interval: Duration,
+    releases_per_tick: usize,
+}
+
+impl Default for DeferredScheduler {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60), 4)
+    }
+}
+
+impl DeferredScheduler {
+    pub fn new(poll_interval: Duration, releases_per_tick: usize) -> Self {
+        Self {
+            queue: Mutex::new(Vec::new()),
+            last_tick: Mutex::new(None),
+            poll_interval,
+            releases_per_tick: releases_per_tick.max(1),
+        }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    pub fn enqueue(&self, job: DeferredJob) -> DeferralStatus {
+        let id = job.reservation_id.0;
+        let mut queue = self.queue.lock().unwrap();
+        let idx = queue.partition_point(|queued| queued.priority >= job.priority);
+        queue.insert(idx, job);
+        drop(queue);
+        self.status(&ReservationId(id)).expect("job was just enqueued")
+    }
+
+    /// Queue position (0 = next to be released on its segment) and estimated
+    /// start time, assuming the segment clears on the next tick after
+    /// `not_before`.
+    pub fn status(&self, reservation_id: &ReservationId) -> Option<DeferralStatus> {
+        let queue = self.queue.lock().unwrap();
+        let job = queue.iter().find(|j| j.reservation_id.0 == reservation_id.0)?;
+        let queue_position = queue
+            .iter()
+            .take_while(|j| j.reservation_id.0 != reservation_id.0)
+            .filter(|j| j.segment_id.0 == job.segment_id.0)
+            .count();
+
+        let now = SystemTime::now();
+        let next_tick = self
+            .last_tick
+            .lock()
+            .unwrap()
+            .map(|t| t + self.poll_interval)
+            .filter(|t| *t > now)
+            .unwrap_or(now);
+        let first_eligible_tick = if job.not_before > next_tick {
+            // round up to the tick on or after not_before
+            let wait = job.not_before.duration_since(next_tick).unwrap_or_default();
+            let ticks = wait.as_secs_f64() / self.poll_interval.as_secs_f64().max(1e-9);
+            next_tick + self.poll_interval.mul_f64(ticks.ceil())
+        } else {
+            next_tick
+        };
+        let ticks_behind = (queue_position / self.releases_per_tick) as u32;
+
+        Some(DeferralStatus {
+            queue_position,
+            estimated_start: first_eligible_tick + self.poll_interval * ticks_behind,
+            deadline: job.deadline,
+            reason: job.reason.clone(),
+        })
+    }
+
+    /// Drops a job from the queue, e.g. when its owner cancels it.
+    pub fn remove(&self, reservation_id: &ReservationId) -> Option<DeferredJob> {
+        let mut queue = self.queue.lock().unwrap();
+        let idx = queue
+            .iter()
+            .position(|j| j.reservation_id.0 == reservation_id.0)?;
+        Some(queue.remove(idx))
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Re-reads telemetry for every segment with queued work and decides which
+    /// jobs to release or drop. Segments whose telemetry cannot be read keep
+    /// their jobs queued (deadlines are still enforced).
+    pub async fn tick<T: SegmentTelemetry>(
+        &self,
+        guard: &StabilityGuard<T>,
+        now: SystemTime,
+    ) -> Result<Vec<SchedulerOutcome>> {
+        *self.last_tick.lock().unwrap() = Some(now);
+
+        let mut segments: Vec<SegmentId> = Vec::new();
+        for job in self.queue.lock().unwrap().iter() {
+            if !segments.iter().any(|s| s.0 == job.segment_id.0) {
+                segments.push(job.segment_id.clone());
+            }
+        }
+
+        let mut loads = HashMap::new();
+        for segment in &segments {
+            if let Ok(load) = guard.telemetry().get_segment_load(segment).await {
+                loads.insert(segment.0.clone(), load);
+            }
+        }
+
+        let mut outcomes = Vec::new();
+        let mut released_per_segment: HashMap<String, usize> = HashMap::new();
+        let mut queue = self.queue.lock().unwrap();
+        queue.retain(|job| {
+            if now > job.deadline {
+                outcomes.push(SchedulerOutcome::DeadlineMissed(job.clone()));
+                return false;
+            }
+            if now < job.not_before {
+                return true;
+            }
+            let Some(load) = loads.get(&job.segment_id.0) else {
+                return true;
+            };
+            let released = released_per_segment
+                .entry(job.segment_id.0.clone())
+                .or_default();
+            if *released >= self.releases_per_tick {
+                return true;
+            }
+
+            let decision = guard.check_load(load, &job.tier);
+            match decision {
+                StabilityDecision::Ok | StabilityDecision::Downgrade { .. } => {
+                    *released += 1;
+                    outcomes.push(SchedulerOutcome::Released {
+                        job: job.clone(),
+                        decision,
+                    });
+                    false
+                }
+                StabilityDecision::Throttle { .. } | StabilityDecision::Deny { .. } => true,
+            }
+        });
+
+        Ok(outcomes)
+    }
+}
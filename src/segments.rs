@@ -0,0 +1,249 @@
+//! The segments work can be placed on, and who may use them.
+//!
+//! A `SegmentRegistry` declares each segment's capabilities, the highest
+//! tier it runs, the clearance it requires, its trust level, the domain tags
+//! it accepts and the roles admitted to it. It is loaded from a TOML, JSON or
+//! YAML file, or from Postgres (see `storage::segments_pg`).
+//! `RegistryZoneResolver` resolves actors and their jobs against it and
+//! reports why every segment it leaves out was excluded.
+
+use crate::error::OrchestratorError;
+use crate::identity::{
+    ActorProfile, ZoneEligibility, ZoneExclusion, ZoneResolution, ZoneResolver,
+};
+use crate::policy_rules::PolicyFormat;
+use crate::types::{CapabilityTier, EcologicalJobSpec, SegmentId};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// Admits every actor when listed in `SegmentSpec::roles`.
+pub const ANY_ROLE: &str = "*";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentSpec {
+    pub segment_id: SegmentId,
+    // e.g. "gpu", "hpc", "low_latency"; matched against a job's
+    // `required_capabilities`
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    pub max_tier: CapabilityTier,
+    #[serde(default)]
+    pub required_clearance: u8,
+    pub trust_level: u8,
+    // empty accepts jobs with any tags
+    #[serde(default)]
+    pub allowed_domain_tags: Vec<String>,
+    pub roles: Vec<String>,
+}
+
+impl SegmentSpec {
+    /// Why `actor` may not run `job` here; empty when it may. Without a job
+    /// only the actor is checked.
+    pub fn exclusion_reasons(
+        &self,
+        actor: &ActorProfile,
+        job: Option<&EcologicalJobSpec>,
+    ) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if !self.roles.iter().any(|r| r == ANY_ROLE || actor.roles.contains(r)) {
+            reasons.push(format!(
+                "admits roles [{}], the actor has [{}]",
+                self.roles.join(", "),
+                actor.roles.join(", ")
+            ));
+        }
+        if actor.clearance_level < self.required_clearance {
+            reasons.push(format!(
+                "requires clearance {}, the actor has {}",
+                self.required_clearance, actor.clearance_level
+            ));
+        }
+        let Some(job) = job else {
+            return reasons;
+        };
+        if tier_rank(&job.requested_tier) > tier_rank(&self.max_tier) {
+            reasons.push(format!(
+                "runs at most {:?}, the job requests {:?}",
+                self.max_tier, job.requested_tier
+            ));
+        }
+        let missing: Vec<&str> = job
+            .required_capabilities
+            .iter()
+            .filter(|c| !self.capabilities.contains(c))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            reasons.push(format!("lacks capabilities [{}]", missing.join(", ")));
+        }
+        if !self.allowed_domain_tags.is_empty() {
+            let refused: Vec<&str> = job
+                .domain_tags
+                .iter()
+                .filter(|t| !self.allowed_domain_tags.contains(t))
+                .map(String::as_str)
+                .collect();
+            if !refused.is_empty() {
+                reasons.push(format!("does not accept domain tags [{}]", refused.join(", ")));
+            }
+        }
+        reasons
+    }
+
+    fn names_role_of(&self, actor: &ActorProfile) -> bool {
+        self.roles.iter().any(|r| actor.roles.contains(r))
+    }
+}
+
+fn tier_rank(tier: &CapabilityTier) -> u8 {
+    match tier {
+        CapabilityTier::Tier1 => 1,
+        CapabilityTier::Tier2 => 2,
+        CapabilityTier::Tier3 => 3,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SegmentRegistry {
+    #[serde(default)]
+    segments: Vec<SegmentSpec>,
+}
+
+impl SegmentRegistry {
+    /// Fails on blank or duplicate segment ids and on segments that admit no
+    /// roles.
+    pub fn new(segments: Vec<SegmentSpec>) -> Result<Self> {
+        let mut errors = Vec::new();
+        for (i, spec) in segments.iter().enumerate() {
+            let id = &spec.segment_id.0;
+            if id.trim().is_empty() {
+                errors.push(format!("segment {} has no segment_id", i));
+            } else if segments[..i].iter().any(|s| s.segment_id.0 == *id) {
+                errors.push(format!("segment {} is declared twice", id));
+            }
+            if spec.roles.is_empty() {
+                errors.push(format!("segment {} admits no roles (use \"{}\")", id, ANY_ROLE));
+            }
+        }
+        if !errors.is_empty() {
+            anyhow::bail!("invalid segment registry: {}", errors.join("; "));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn parse(text: &str, format: PolicyFormat) -> Result<Self> {
+        let registry: SegmentRegistry = match format {
+            PolicyFormat::Toml => toml::from_str(text)?,
+            PolicyFormat::Json => serde_json::from_str(text)?,
+            PolicyFormat::Yaml => serde_yaml::from_str(text)?,
+        };
+        Self::new(registry.segments)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading segment registry {}", path.display()))?;
+        Self::parse(&text, PolicyFormat::from_path(path)?)
+            .with_context(|| format!("parsing segment registry {}", path.display()))
+    }
+
+    pub fn segments(&self) -> &[SegmentSpec] {
+        &self.segments
+    }
+
+    pub fn get(&self, segment_id: &SegmentId) -> Option<&SegmentSpec> {
+        self.segments.iter().find(|s| s.segment_id.0 == segment_id.0)
+    }
+
+    /// Splits the registry into segments `actor` may use for `job` and ones
+    /// it may not. Segments that name one of the actor's roles are preferred
+    /// over ones open to any role; otherwise registry order is kept.
+    pub fn eligibility(
+        &self,
+        actor: &ActorProfile,
+        job: Option<&EcologicalJobSpec>,
+    ) -> ZoneEligibility {
+        let mut named = Vec::new();
+        let mut open = Vec::new();
+        let mut excluded = Vec::new();
+        for spec in &self.segments {
+            let reasons = spec.exclusion_reasons(actor, job);
+            if !reasons.is_empty() {
+                excluded.push(ZoneExclusion {
+                    segment_id: spec.segment_id.clone(),
+                    reasons,
+                });
+                continue;
+            }
+            let zone = ZoneResolution {
+                segment_id: spec.segment_id.clone(),
+                trust_level: spec.trust_level,
+            };
+            if spec.names_role_of(actor) {
+                named.push(zone);
+            } else {
+                open.push(zone);
+            }
+        }
+        named.extend(open);
+        ZoneEligibility {
+            eligible: named,
+            excluded,
+        }
+    }
+}
+
+/// `ZoneResolver` backed by a `SegmentRegistry`, which can be swapped while
+/// the resolver is in use (e.g. after re-reading it from Postgres).
+pub struct RegistryZoneResolver {
+    registry: RwLock<Arc<SegmentRegistry>>,
+}
+
+impl RegistryZoneResolver {
+    pub fn new(registry: SegmentRegistry) -> Self {
+        Self {
+            registry: RwLock::new(Arc::new(registry)),
+        }
+    }
+
+    pub fn registry(&self) -> Arc<SegmentRegistry> {
+        self.registry.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, registry: SegmentRegistry) {
+        *self.registry.write().unwrap() = Arc::new(registry);
+    }
+}
+
+fn no_segment(actor: &ActorProfile, eligibility: &ZoneEligibility) -> OrchestratorError {
+    OrchestratorError::Forbidden(format!(
+        "no segment is open to actor {} ({})",
+        actor.actor_id.0,
+        eligibility.explain_exclusions()
+    ))
+}
+
+#[async_trait::async_trait]
+impl ZoneResolver for RegistryZoneResolver {
+    async fn resolve_zone(&self, actor: &ActorProfile) -> Result<ZoneResolution> {
+        Ok(self.resolve_zones(actor).await?.remove(0))
+    }
+
+    async fn resolve_zones(&self, actor: &ActorProfile) -> Result<Vec<ZoneResolution>> {
+        let eligibility = self.registry().eligibility(actor, None);
+        if eligibility.eligible.is_empty() {
+            return Err(no_segment(actor, &eligibility).into());
+        }
+        Ok(eligibility.eligible)
+    }
+
+    async fn resolve_for_job(
+        &self,
+        actor: &ActorProfile,
+        job: &EcologicalJobSpec,
+    ) -> Result<ZoneEligibility> {
+        Ok(self.registry().eligibility(actor, Some(job)))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::eol::approval::{ApprovalRequest, ApprovalStore, ReviewRecord};
+use crate::eol::error::OrchestratorError;
+use crate::eol::types::{ApprovalStatus, ReservationId};
+use anyhow::Result;
+use deadpool_postgres::{Pool, Transaction};
+
+// eol_approvals (reservation_id UUID PRIMARY KEY, status TEXT, request JSONB,
+// created_at TIMESTAMPTZ)
+pub struct PgApprovalStore {
+    pool: Pool,
+}
+
+impl PgApprovalStore {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    fn status(request: &ApprovalRequest) -> &'static str {
+        match request.status {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Rejected => "rejected",
+        }
+    }
+
+    // The row lock keeps two reviewers from both completing the quorum, and a
+    // withdrawal from racing a review.
+    async fn lock(tx: &Transaction<'_>, reservation_id: &ReservationId) -> Result<ApprovalRequest> {
+        let row = tx
+            .query_opt(
+                "SELECT request FROM eol_approvals WHERE reservation_id = $1 FOR UPDATE",
+                &[&reservation_id.0],
+            )
+            .await?
+            .ok_or_else(|| no_approval(reservation_id))?;
+        Ok(serde_json::from_value(row.get("request"))?)
+    }
+
+    async fn save(tx: &Transaction<'_>, request: &ApprovalRequest) -> Result<()> {
+        tx.execute(
+            "UPDATE eol_approvals SET status = $2, request = $3::jsonb
+             WHERE reservation_id = $1",
+            &[
+                &request.reservation_id.0,
+                &Self::status(request),
+                &serde_json::to_value(request)?,
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+fn no_approval(reservation_id: &ReservationId) -> OrchestratorError {
+    OrchestratorError::NotFound(format!("no approval for reservation {}", reservation_id.0))
+}
+
+#[async_trait::async_trait]
+impl ApprovalStore for PgApprovalStore {
+    async fn insert(&self, request: &ApprovalRequest) -> Result<()> {
+        let client = self.pool.get().await?;
+        let json = serde_json::to_value(request)?;
+        client
+            .execute(
+                "INSERT INTO eol_approvals (reservation_id, status, request, created_at)
+                 VALUES ($1, $2, $3::jsonb, $4)",
+                &[
+                    &request.reservation_id.0,
+                    &Self::status(request),
+                    &json,
+                    &request.created_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, reservation_id: &ReservationId) -> Result<ApprovalRequest> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT request FROM eol_approvals WHERE reservation_id = $1",
+                &[&reservation_id.0],
+            )
+            .await?
+            .ok_or_else(|| no_approval(reservation_id))?;
+        Ok(serde_json::from_value(row.get("request"))?)
+    }
+
+    async fn list_pending(&self) -> Result<Vec<ApprovalRequest>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT request FROM eol_approvals
+                 WHERE status = 'pending'
+                 ORDER BY created_at",
+                &[],
+            )
+            .await?;
+        rows.into_iter()
+            .map(|row| Ok(serde_json::from_value(row.get("request"))?))
+            .collect()
+    }
+
+    async fn record_review(
+        &self,
+        reservation_id: &ReservationId,
+        review: ReviewRecord,
+    ) -> Result<ApprovalRequest> {
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
+        let mut request = Self::lock(&tx, reservation_id).await?;
+        request.apply_review(review)?;
+        Self::save(&tx, &request).await?;
+        tx.commit().await?;
+        Ok(request)
+    }
+
+    async fn withdraw(
+        &self,
+        reservation_id: &ReservationId,
+        reason: &str,
+    ) -> Result<ApprovalRequest> {
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
+        let mut request = Self::lock(&tx, reservation_id).await?;
+        request.withdraw(reason)?;
+        Self::save(&tx, &request).await?;
+        tx.commit().await?;
+        Ok(request)
+    }
+}
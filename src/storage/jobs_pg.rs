@@ -0,0 +1,89 @@
+use crate::eol::error::OrchestratorError;
+use crate::eol::jobs::{JobRecord, JobStatus, JobStore};
+use crate::eol::types::ReservationId;
+use anyhow::Result;
+use deadpool_postgres::Pool;
+
+// eol_jobs (reservation_id UUID PRIMARY KEY, status TEXT, record JSONB,
+// planned_at TIMESTAMPTZ)
+pub struct PgJobStore {
+    pool: Pool,
+}
+
+impl PgJobStore {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl JobStore for PgJobStore {
+    async fn insert(&self, record: &JobRecord) -> Result<()> {
+        let client = self.pool.get().await?;
+        client
+            .execute(
+                "INSERT INTO eol_jobs (reservation_id, status, record, planned_at)
+                 VALUES ($1, $2, $3::jsonb, $4)",
+                &[
+                    &record.reservation_id().0,
+                    &record.status.as_str(),
+                    &serde_json::to_value(record)?,
+                    &record.planned_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, reservation_id: &ReservationId) -> Result<JobRecord> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT record FROM eol_jobs WHERE reservation_id = $1",
+                &[&reservation_id.0],
+            )
+            .await?
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("no job for reservation {}", reservation_id.0))
+            })?;
+        Ok(serde_json::from_value(row.get("record"))?)
+    }
+
+    async fn list_by_status(&self, status: JobStatus) -> Result<Vec<JobRecord>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT record FROM eol_jobs WHERE status = $1 ORDER BY planned_at",
+                &[&status.as_str()],
+            )
+            .await?;
+        rows.into_iter()
+            .map(|row| Ok(serde_json::from_value(row.get("record"))?))
+            .collect()
+    }
+
+    async fn replace(&self, record: &JobRecord, expected: JobStatus) -> Result<()> {
+        let client = self.pool.get().await?;
+        let updated = client
+            .execute(
+                "UPDATE eol_jobs SET status = $2, record = $3::jsonb
+                 WHERE reservation_id = $1 AND status = $4",
+                &[
+                    &record.reservation_id().0,
+                    &record.status.as_str(),
+                    &serde_json::to_value(record)?,
+                    &expected.as_str(),
+                ],
+            )
+            .await?;
+        if updated == 0 {
+            return Err(OrchestratorError::Conflict(format!(
+                "job {} is no longer {}",
+                record.reservation_id().0,
+                expected.as_str()
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
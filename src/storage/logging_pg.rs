@@ -1,7 +1,15 @@
-use crate::eol::logging::{EcologicalLogEvent, ImmutableLogger};
+use crate::eol::logging::{ChainedLogEvent, EcologicalLogEvent, ImmutableLogger};
 use anyhow::Result;
 use deadpool_postgres::Pool;
+use std::time::SystemTime;
 
+// eol_logs (sequence BIGINT PRIMARY KEY, recorded_at TIMESTAMPTZ NOT NULL,
+// prev_hash TEXT NOT NULL, hash TEXT NOT NULL, event TEXT NOT NULL)
+//
+// `event` is TEXT rather than JSONB because JSONB normalizes key order and
+// numbers, which would change the hashed bytes; cast it (`event::jsonb`) to
+// query. Revoke UPDATE/DELETE on the table from application roles; anything
+// that slips through is caught by `verify_chain`.
 pub struct PgImmutableLogger {
     pool: Pool,
 }
@@ -12,19 +20,65 @@ impl PgImmutableLogger {
     }
 }
 
+#[async_trait::async_trait]
 impl ImmutableLogger for PgImmutableLogger {
-    fn append(&self, event: &EcologicalLogEvent) -> Result<()> {
-        let client = self.pool.get()?;
-        let json = serde_json::to_value(event)?;
-        tokio::runtime::Handle::current().block_on(async {
-            client
-                .execute(
-                    "INSERT INTO eol_logs (event)
-                     VALUES ($1::jsonb)",
-                    &[&json],
-                )
-                .await
-        })?;
+    async fn append(&self, event: &EcologicalLogEvent) -> Result<()> {
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
+
+        // Serializes appenders (readers are not blocked) so two events can
+        // never claim the same head.
+        tx.execute("LOCK TABLE eol_logs IN EXCLUSIVE MODE", &[]).await?;
+        let head = tx
+            .query_opt(
+                "SELECT sequence, hash FROM eol_logs ORDER BY sequence DESC LIMIT 1",
+                &[],
+            )
+            .await?
+            .map(|row| (row.get::<_, i64>("sequence") as u64, row.get::<_, String>("hash")));
+
+        let entry = ChainedLogEvent::link(
+            head.as_ref().map(|(sequence, hash)| (*sequence, hash.as_str())),
+            event,
+            SystemTime::now(),
+        )?;
+        tx.execute(
+            "INSERT INTO eol_logs (sequence, recorded_at, prev_hash, hash, event)
+             VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &(entry.sequence as i64),
+                &entry.recorded_at,
+                &entry.prev_hash,
+                &entry.hash,
+                &entry.event_json,
+            ],
+        )
+        .await?;
+        tx.commit().await?;
         Ok(())
     }
+
+    async fn entries(&self, from_sequence: u64, limit: usize) -> Result<Vec<ChainedLogEvent>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT sequence, recorded_at, prev_hash, hash, event
+                 FROM eol_logs
+                 WHERE sequence >= $1
+                 ORDER BY sequence
+                 LIMIT $2",
+                &[&(from_sequence as i64), &(limit as i64)],
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| ChainedLogEvent {
+                sequence: row.get::<_, i64>("sequence") as u64,
+                recorded_at: row.get("recorded_at"),
+                prev_hash: row.get("prev_hash"),
+                hash: row.get("hash"),
+                event_json: row.get("event"),
+            })
+            .collect())
+    }
 }
@@ -1,11 +1,77 @@
-use crate::eol::quota::QuotaStore;
+use crate::eol::error::OrchestratorError;
+use crate::eol::quota::{
+    ensure_headroom, BudgetPath, QuotaStore, Reservation, ReservationActuals, ReservationState,
+};
 use crate::eol::types::{
-    ActorId, CapabilityTier, ComputeEnergyAllowance, UsageSnapshot, UsageWindowId,
+    ActorId, BudgetLevel, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec, ReservationId,
+    UsageSnapshot, UsageWindowId,
 };
 use anyhow::Result;
-use deadpool_postgres::Pool;
+use deadpool_postgres::{Pool, Transaction};
+use std::time::{Duration, SystemTime};
 use tokio_postgres::Row;
 
+// Committed usage plus outstanding holds for ($1 actor, $2 window). A window
+// with no usage row yet reads as zero.
+const USAGE_SQL: &str = "
+    SELECT $2::text AS window_id,
+           COALESCE(u.flops_used, 0) AS flops_used,
+           COALESCE(u.energy_kwh_used, 0) AS energy_kwh_used,
+           COALESCE(u.carbon_kg_emitted, 0) AS carbon_kg_emitted,
+           COALESCE(r.flops, 0) AS flops_reserved,
+           COALESCE(r.energy_kwh, 0) AS energy_kwh_reserved,
+           COALESCE(r.carbon_kg, 0) AS carbon_kg_reserved
+    FROM (SELECT 1) AS one
+    LEFT JOIN eol_usage u ON u.actor_id = $1 AND u.window_id = $2
+    LEFT JOIN (
+        SELECT SUM(expected_flops) AS flops,
+               SUM(expected_energy_kwh) AS energy_kwh,
+               SUM(expected_carbon_kg) AS carbon_kg
+        FROM eol_reservations
+        WHERE actor_id = $1 AND window_id = $2 AND state IN ('pending', 'active')
+    ) r ON true";
+
+// USAGE_SQL summed over the members of ($1 level, $2 group) for ($3 window):
+// a project's actors, or the actors of every project in an organisation.
+const GROUP_USAGE_SQL: &str = "
+    WITH members AS (
+        SELECT m.actor_id
+        FROM eol_project_members m
+        JOIN eol_projects p ON p.project_id = m.project_id
+        WHERE ($1::text = 'project' AND m.project_id = $2)
+           OR ($1::text = 'organisation' AND p.organisation_id = $2)
+    )
+    SELECT $3::text AS window_id,
+           COALESCE(u.flops_used, 0) AS flops_used,
+           COALESCE(u.energy_kwh_used, 0) AS energy_kwh_used,
+           COALESCE(u.carbon_kg_emitted, 0) AS carbon_kg_emitted,
+           COALESCE(r.flops, 0) AS flops_reserved,
+           COALESCE(r.energy_kwh, 0) AS energy_kwh_reserved,
+           COALESCE(r.carbon_kg, 0) AS carbon_kg_reserved
+    FROM (
+        SELECT SUM(flops_used) AS flops_used,
+               SUM(energy_kwh_used) AS energy_kwh_used,
+               SUM(carbon_kg_emitted) AS carbon_kg_emitted
+        FROM eol_usage
+        WHERE window_id = $3 AND actor_id IN (SELECT actor_id FROM members)
+    ) u
+    CROSS JOIN (
+        SELECT SUM(expected_flops) AS flops,
+               SUM(expected_energy_kwh) AS energy_kwh,
+               SUM(expected_carbon_kg) AS carbon_kg
+        FROM eol_reservations
+        WHERE window_id = $3 AND state IN ('pending', 'active')
+          AND actor_id IN (SELECT actor_id FROM members)
+    ) r";
+
+const GROUP_ALLOWANCE_SQL: &str = "
+    SELECT max_flops, max_energy_kwh, max_carbon_kg, max_tier, valid_until
+    FROM eol_group_allowances
+    WHERE level = $1 AND group_id = $2 AND window_id = $3";
+
+const RESERVATION_COLUMNS: &str = "id, actor_id, window_id, expected_flops, expected_energy_kwh,
+     expected_carbon_kg, tier, max_duration_secs, state, created_at, expires_at";
+
 pub struct PgQuotaStore {
     pool: Pool,
 }
@@ -23,6 +89,56 @@ impl PgQuotaStore {
             _ => CapabilityTier::Tier1,
         }
     }
+
+    fn tier_to_i16(tier: &CapabilityTier) -> i16 {
+        match tier {
+            CapabilityTier::Tier1 => 1,
+            CapabilityTier::Tier2 => 2,
+            CapabilityTier::Tier3 => 3,
+        }
+    }
+
+    /// Locks the reservation row and moves it to `next` if the state machine
+    /// allows it.
+    async fn transition(
+        tx: &Transaction<'_>,
+        reservation_id: &ReservationId,
+        next: ReservationState,
+    ) -> Result<Reservation> {
+        let row = tx
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM eol_reservations WHERE id = $1 FOR UPDATE",
+                    RESERVATION_COLUMNS
+                ),
+                &[&reservation_id.0],
+            )
+            .await?
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("unknown reservation {}", reservation_id.0))
+            })?;
+        let mut reservation = Reservation::try_from(row)?;
+
+        if !reservation.state.can_transition_to(next) {
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} cannot move from {:?} to {:?}",
+                reservation_id.0, reservation.state, next
+            ))
+            .into());
+        }
+
+        tx.execute(
+            "UPDATE eol_reservations SET state = $2 WHERE id = $1",
+            &[&reservation_id.0, &next.as_str()],
+        )
+        .await?;
+        reservation.state = next;
+        Ok(reservation)
+    }
+}
+
+fn no_allowance(actor: &ActorId, window: &UsageWindowId) -> OrchestratorError {
+    OrchestratorError::Forbidden(format!("no allowance for {} in {}", actor.0, window.0))
 }
 
 impl From<Row> for ComputeEnergyAllowance {
@@ -44,68 +160,431 @@ impl From<Row> for UsageSnapshot {
             flops_used: row.get("flops_used"),
             energy_kwh_used: row.get("energy_kwh_used"),
             carbon_kg_emitted: row.get("carbon_kg_emitted"),
+            flops_reserved: row.get("flops_reserved"),
+            energy_kwh_reserved: row.get("energy_kwh_reserved"),
+            carbon_kg_reserved: row.get("carbon_kg_reserved"),
         }
     }
 }
 
+impl TryFrom<Row> for Reservation {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Reservation {
+            id: ReservationId(row.get("id")),
+            actor_id: ActorId(row.get("actor_id")),
+            window_id: UsageWindowId(row.get("window_id")),
+            expected_flops: row.get("expected_flops"),
+            expected_energy_kwh: row.get("expected_energy_kwh"),
+            expected_carbon_kg: row.get("expected_carbon_kg"),
+            tier: PgQuotaStore::tier_from_i16(row.get("tier")),
+            max_duration: Duration::from_secs_f64(row.get("max_duration_secs")),
+            state: ReservationState::parse(row.get("state"))?,
+            created_at: row.get("created_at"),
+            expires_at: row.get("expires_at"),
+        })
+    }
+}
+
+#[async_trait::async_trait]
 impl QuotaStore for PgQuotaStore {
-    fn get_allowance(
+    async fn get_allowance(
         &self,
         actor: &ActorId,
         window: &UsageWindowId,
     ) -> Result<ComputeEnergyAllowance> {
-        let client = self.pool.get()?;
-        let row = tokio::runtime::Handle::current().block_on(async {
-            client
-                .query_one(
-                    "SELECT max_flops, max_energy_kwh, max_carbon_kg, max_tier, valid_until
-                     FROM eol_allowances
-                     WHERE actor_id = $1 AND window_id = $2",
-                    &[&actor.0, &window.0],
-                )
-                .await
-        })?;
+        self.find_allowance(actor, window)
+            .await?
+            .ok_or_else(|| no_allowance(actor, window).into())
+    }
+
+    async fn find_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT max_flops, max_energy_kwh, max_carbon_kg, max_tier, valid_until
+                 FROM eol_allowances
+                 WHERE actor_id = $1 AND window_id = $2",
+                &[&actor.0, &window.0],
+            )
+            .await?;
+        Ok(row.map(Into::into))
+    }
+
+    async fn provision_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool> {
+        let client = self.pool.get().await?;
+        let inserted = client
+            .execute(
+                "INSERT INTO eol_allowances
+                    (actor_id, window_id, max_flops, max_energy_kwh, max_carbon_kg, max_tier,
+                     valid_until)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (actor_id, window_id) DO NOTHING",
+                &[
+                    &actor.0,
+                    &window.0,
+                    &allowance.max_flops,
+                    &allowance.max_energy_kwh,
+                    &allowance.max_carbon_kg,
+                    &Self::tier_to_i16(&allowance.max_tier),
+                    &allowance.valid_until,
+                ],
+            )
+            .await?;
+        Ok(inserted == 1)
+    }
+
+    async fn get_usage(&self, actor: &ActorId, window: &UsageWindowId) -> Result<UsageSnapshot> {
+        let client = self.pool.get().await?;
+        let row = client.query_one(USAGE_SQL, &[&actor.0, &window.0]).await?;
         Ok(row.into())
     }
 
-    fn get_usage(&self, actor: &ActorId, window: &UsageWindowId) -> Result<UsageSnapshot> {
-        let client = self.pool.get()?;
-        let row = tokio::runtime::Handle::current().block_on(async {
-            client
-                .query_one(
-                    "SELECT window_id, flops_used, energy_kwh_used, carbon_kg_emitted
-                     FROM eol_usage
-                     WHERE actor_id = $1 AND window_id = $2",
-                    &[&actor.0, &window.0],
-                )
-                .await
-        })?;
+    async fn budget_path(&self, actor: &ActorId) -> Result<BudgetPath> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT m.project_id, p.organisation_id
+                 FROM eol_project_members m
+                 LEFT JOIN eol_projects p ON p.project_id = m.project_id
+                 WHERE m.actor_id = $1",
+                &[&actor.0],
+            )
+            .await?;
+        Ok(row.map_or_else(BudgetPath::default, |row| BudgetPath {
+            organisation: row.get("organisation_id"),
+            project: row.get("project_id"),
+        }))
+    }
+
+    async fn find_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(GROUP_ALLOWANCE_SQL, &[&level.as_str(), &group_id, &window.0])
+            .await?;
+        Ok(row.map(Into::into))
+    }
+
+    async fn provision_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool> {
+        let client = self.pool.get().await?;
+        let inserted = client
+            .execute(
+                "INSERT INTO eol_group_allowances
+                    (level, group_id, window_id, max_flops, max_energy_kwh, max_carbon_kg,
+                     max_tier, valid_until)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (level, group_id, window_id) DO NOTHING",
+                &[
+                    &level.as_str(),
+                    &group_id,
+                    &window.0,
+                    &allowance.max_flops,
+                    &allowance.max_energy_kwh,
+                    &allowance.max_carbon_kg,
+                    &Self::tier_to_i16(&allowance.max_tier),
+                    &allowance.valid_until,
+                ],
+            )
+            .await?;
+        Ok(inserted == 1)
+    }
+
+    async fn get_group_usage(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<UsageSnapshot> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_one(GROUP_USAGE_SQL, &[&level.as_str(), &group_id, &window.0])
+            .await?;
         Ok(row.into())
     }
 
-    fn reserve_quota(
+    async fn budget_children(&self, level: BudgetLevel, group_id: &str) -> Result<Vec<String>> {
+        let sql = match level {
+            BudgetLevel::Organisation => {
+                "SELECT project_id AS id FROM eol_projects
+                 WHERE organisation_id = $1 ORDER BY project_id"
+            }
+            BudgetLevel::Project => {
+                "SELECT actor_id AS id FROM eol_project_members
+                 WHERE project_id = $1 ORDER BY actor_id"
+            }
+            BudgetLevel::Actor => return Ok(Vec::new()),
+        };
+        let client = self.pool.get().await?;
+        let rows = client.query(sql, &[&group_id]).await?;
+        Ok(rows.into_iter().map(|r| r.get("id")).collect())
+    }
+
+    async fn reserve_quota(
         &self,
         actor: &ActorId,
         window: &UsageWindowId,
-        expected_flops: f64,
+        job: &EcologicalJobSpec,
         expected_energy_kwh: f64,
         expected_carbon_kg: f64,
-        _tier: &CapabilityTier,
-    ) -> Result<crate::eol::types::ReservationId> {
-        let client = self.pool.get()?;
-        let id = uuid::Uuid::new_v4();
+    ) -> Result<ReservationId> {
+        let path = self.budget_path(actor).await?;
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
 
-        tokio::runtime::Handle::current().block_on(async {
-            client
-                .execute(
-                    "INSERT INTO eol_reservations
-                        (id, actor_id, window_id, expected_flops, expected_energy_kwh, expected_carbon_kg)
-                     VALUES ($1, $2, $3, $4, $5, $6)",
-                    &[&id, &actor.0, &window.0, &expected_flops, &expected_energy_kwh, &expected_carbon_kg],
+        // Group allowance rows are locked top-down, before the actor's, so
+        // members of one project serialize on it without deadlocking.
+        for (level, group) in [
+            (BudgetLevel::Organisation, path.organisation),
+            (BudgetLevel::Project, path.project),
+        ] {
+            let Some(group) = group else { continue };
+            let row = tx
+                .query_opt(
+                    &format!("{} FOR UPDATE", GROUP_ALLOWANCE_SQL),
+                    &[&level.as_str(), &group, &window.0],
                 )
-                .await
-        })?;
+                .await?;
+            if let Some(row) = row {
+                let usage: UsageSnapshot = tx
+                    .query_one(GROUP_USAGE_SQL, &[&level.as_str(), &group, &window.0])
+                    .await?
+                    .into();
+                ensure_headroom(
+                    level,
+                    &row.into(),
+                    &usage,
+                    job.expected_flops,
+                    expected_energy_kwh,
+                    expected_carbon_kg,
+                )?;
+            }
+        }
+
+        // Locking the allowance row serializes concurrent reservations for the
+        // same actor/window until this transaction commits or rolls back.
+        let allowance: ComputeEnergyAllowance = tx
+            .query_opt(
+                "SELECT max_flops, max_energy_kwh, max_carbon_kg, max_tier, valid_until
+                 FROM eol_allowances
+                 WHERE actor_id = $1 AND window_id = $2
+                 FOR UPDATE",
+                &[&actor.0, &window.0],
+            )
+            .await?
+            .ok_or_else(|| no_allowance(actor, window))?
+            .into();
+        let usage: UsageSnapshot = tx.query_one(USAGE_SQL, &[&actor.0, &window.0]).await?.into();
+
+        ensure_headroom(
+            BudgetLevel::Actor,
+            &allowance,
+            &usage,
+            job.expected_flops,
+            expected_energy_kwh,
+            expected_carbon_kg,
+        )?;
+
+        let id = uuid::Uuid::new_v4();
+        let max_duration_secs = job.max_duration.as_secs_f64();
+        tx.execute(
+            "INSERT INTO eol_reservations
+                (id, actor_id, window_id, expected_flops, expected_energy_kwh, expected_carbon_kg,
+                 tier, max_duration_secs, state, created_at, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'pending', now(),
+                     GREATEST(now(), $9) + make_interval(secs => $8))",
+            &[
+                &id,
+                &actor.0,
+                &window.0,
+                &job.expected_flops,
+                &expected_energy_kwh,
+                &expected_carbon_kg,
+                &Self::tier_to_i16(&job.requested_tier),
+                &max_duration_secs,
+                &job.deadline,
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(ReservationId(id))
+    }
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                &format!("SELECT {} FROM eol_reservations WHERE id = $1", RESERVATION_COLUMNS),
+                &[&reservation_id.0],
+            )
+            .await?
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("unknown reservation {}", reservation_id.0))
+            })?;
+        Reservation::try_from(row)
+    }
+
+    async fn outstanding_reservations(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Vec<Reservation>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT {} FROM eol_reservations
+                     WHERE actor_id = $1 AND window_id = $2 AND state IN ('pending', 'active')
+                     ORDER BY created_at",
+                    RESERVATION_COLUMNS
+                ),
+                &[&actor.0, &window.0],
+            )
+            .await?;
+        rows.into_iter().map(Reservation::try_from).collect()
+    }
+
+    async fn activate(&self, reservation_id: &ReservationId) -> Result<()> {
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
+        Self::transition(&tx, reservation_id, ReservationState::Active).await?;
+        tx.execute(
+            "UPDATE eol_reservations
+             SET expires_at = now() + make_interval(secs => max_duration_secs)
+             WHERE id = $1",
+            &[&reservation_id.0],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn extend_hold(&self, reservation_id: &ReservationId, until: SystemTime) -> Result<()> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "UPDATE eol_reservations
+                 SET expires_at = GREATEST(expires_at, $2)
+                 WHERE id = $1 AND state = 'pending'
+                 RETURNING id",
+                &[&reservation_id.0, &until],
+            )
+            .await?;
+        if row.is_none() {
+            // Unknown, or no longer pending: report which.
+            let reservation = self.get_reservation(reservation_id).await?;
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} is {:?}, not pending",
+                reservation_id.0, reservation.state
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn shrink_hold(
+        &self,
+        reservation_id: &ReservationId,
+        tier: &CapabilityTier,
+        expected_energy_kwh: f64,
+        expected_carbon_kg: f64,
+    ) -> Result<()> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "UPDATE eol_reservations
+                 SET tier = $2,
+                     expected_energy_kwh = LEAST(expected_energy_kwh, $3),
+                     expected_carbon_kg = LEAST(expected_carbon_kg, $4)
+                 WHERE id = $1 AND state IN ('pending', 'active')
+                 RETURNING id",
+                &[
+                    &reservation_id.0,
+                    &Self::tier_to_i16(tier),
+                    &expected_energy_kwh,
+                    &expected_carbon_kg,
+                ],
+            )
+            .await?;
+        if row.is_none() {
+            let reservation = self.get_reservation(reservation_id).await?;
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} is {:?}, not outstanding",
+                reservation_id.0, reservation.state
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn commit_usage(
+        &self,
+        reservation_id: &ReservationId,
+        actuals: &ReservationActuals,
+    ) -> Result<()> {
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
+        let reservation = Self::transition(&tx, reservation_id, ReservationState::Committed).await?;
+        tx.execute(
+            "INSERT INTO eol_usage
+                (actor_id, window_id, flops_used, energy_kwh_used, carbon_kg_emitted)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (actor_id, window_id) DO UPDATE SET
+                flops_used = eol_usage.flops_used + EXCLUDED.flops_used,
+                energy_kwh_used = eol_usage.energy_kwh_used + EXCLUDED.energy_kwh_used,
+                carbon_kg_emitted = eol_usage.carbon_kg_emitted + EXCLUDED.carbon_kg_emitted",
+            &[
+                &reservation.actor_id.0,
+                &reservation.window_id.0,
+                &actuals.flops_used,
+                &actuals.energy_kwh_used,
+                &actuals.carbon_kg_emitted,
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn release(&self, reservation_id: &ReservationId) -> Result<()> {
+        let mut client = self.pool.get().await?;
+        let tx = client.transaction().await?;
+        Self::transition(&tx, reservation_id, ReservationState::Released).await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        Ok(crate::eol::types::ReservationId(id))
+    async fn expire_reservations(&self, cutoff: SystemTime) -> Result<Vec<ReservationId>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "UPDATE eol_reservations SET state = 'expired'
+                 WHERE state IN ('pending', 'active') AND expires_at < $1
+                 RETURNING id",
+                &[&cutoff],
+            )
+            .await?;
+        Ok(rows.into_iter().map(|r| ReservationId(r.get("id"))).collect())
     }
 }
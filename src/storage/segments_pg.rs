@@ -0,0 +1,52 @@
+use crate::eol::segments::{SegmentRegistry, SegmentSpec};
+use crate::eol::types::SegmentId;
+use anyhow::Result;
+use deadpool_postgres::Pool;
+
+// eol_segments (segment_id TEXT PRIMARY KEY, position INTEGER, spec JSONB).
+// Segments are listed by position, which orders the registry.
+pub struct PgSegmentRegistryStore {
+    pool: Pool,
+}
+
+impl PgSegmentRegistryStore {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn load(&self) -> Result<SegmentRegistry> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query("SELECT spec FROM eol_segments ORDER BY position, segment_id", &[])
+            .await?;
+        let segments = rows
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(row.get("spec"))?))
+            .collect::<Result<Vec<SegmentSpec>>>()?;
+        SegmentRegistry::new(segments)
+    }
+
+    pub async fn upsert(&self, position: i32, spec: &SegmentSpec) -> Result<()> {
+        let client = self.pool.get().await?;
+        let json = serde_json::to_value(spec)?;
+        client
+            .execute(
+                "INSERT INTO eol_segments (segment_id, position, spec)
+                 VALUES ($1, $2, $3::jsonb)
+                 ON CONFLICT (segment_id)
+                 DO UPDATE SET position = EXCLUDED.position, spec = EXCLUDED.spec",
+                &[&spec.segment_id.0, &position, &json],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns whether the segment was registered.
+    pub async fn remove(&self, segment_id: &SegmentId) -> Result<bool> {
+        let client = self.pool.get().await?;
+        let removed = client
+            .execute("DELETE FROM eol_segments WHERE segment_id = $1", &[&segment_id.0])
+            .await?;
+        Ok(removed > 0)
+    }
+}
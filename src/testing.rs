@@ -0,0 +1,907 @@
+//! In-memory reference implementations of the orchestrator traits, for tests
+//! and demos that should not depend on Postgres, Prometheus or an identity
+//! service. Enabled with the `testing` feature.
+//!
+//! Every type is cheap to clone and clones share state, so a test can hand one
+//! copy to `EcologicalOrchestrator` and keep another to inspect or mutate.
+
+#![cfg(any(test, feature = "testing"))]
+
+use crate::approval::{ApprovalRequest, ApprovalStore, ReviewRecord};
+use crate::energy::SegmentTelemetry;
+use crate::error::OrchestratorError;
+use crate::jobs::{JobRecord, JobStatus, JobStore};
+use crate::identity::{ActorProfile, IdentityResolver, ZoneResolution, ZoneResolver};
+use crate::logging::{ChainedLogEvent, EcologicalLogEvent, ImmutableLogger};
+use crate::preemption::{PreemptionExecutor, PreemptionSignal};
+use crate::quota::{
+    ensure_headroom, BudgetPath, QuotaStore, Reservation, ReservationActuals, ReservationState,
+};
+use crate::types::{
+    ActorId, ApprovalStatus, BudgetLevel, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec,
+    ReservationId, SegmentId, SegmentLoad, UsageSnapshot, UsageWindowId,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+type WindowKey = (String, String);
+
+fn key(actor: &ActorId, window: &UsageWindowId) -> WindowKey {
+    (actor.0.clone(), window.0.clone())
+}
+
+fn no_allowance(actor: &ActorId, window: &UsageWindowId) -> OrchestratorError {
+    OrchestratorError::Forbidden(format!("no allowance for {} in {}", actor.0, window.0))
+}
+
+#[derive(Default)]
+struct QuotaState {
+    allowances: HashMap<WindowKey, ComputeEnergyAllowance>,
+    // committed (flops, energy_kwh, carbon_kg) per actor/window
+    usage: HashMap<WindowKey, (f64, f64, f64)>,
+    reservations: HashMap<Uuid, Reservation>,
+    // project -> organisation, actor -> project
+    projects: HashMap<String, String>,
+    members: HashMap<String, String>,
+    group_allowances: HashMap<(BudgetLevel, String, String), ComputeEnergyAllowance>,
+    next_id: u128,
+}
+
+impl QuotaState {
+    fn path(&self, actor: &ActorId) -> BudgetPath {
+        let project = self.members.get(&actor.0).cloned();
+        BudgetPath {
+            organisation: project.as_ref().and_then(|p| self.projects.get(p).cloned()),
+            project,
+        }
+    }
+
+    fn group_members(&self, level: BudgetLevel, group_id: &str) -> Vec<ActorId> {
+        let mut actors: Vec<ActorId> = self
+            .members
+            .iter()
+            .filter(|(_, project)| match level {
+                BudgetLevel::Organisation => {
+                    self.projects.get(*project).map(String::as_str) == Some(group_id)
+                }
+                BudgetLevel::Project => project.as_str() == group_id,
+                BudgetLevel::Actor => false,
+            })
+            .map(|(actor, _)| ActorId(actor.clone()))
+            .collect();
+        actors.sort_by(|a, b| a.0.cmp(&b.0));
+        actors
+    }
+
+    fn group_snapshot(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> UsageSnapshot {
+        let mut total = UsageSnapshot {
+            window_id: window.clone(),
+            flops_used: 0.0,
+            energy_kwh_used: 0.0,
+            carbon_kg_emitted: 0.0,
+            flops_reserved: 0.0,
+            energy_kwh_reserved: 0.0,
+            carbon_kg_reserved: 0.0,
+        };
+        for actor in self.group_members(level, group_id) {
+            let s = self.snapshot(&actor, window);
+            total.flops_used += s.flops_used;
+            total.energy_kwh_used += s.energy_kwh_used;
+            total.carbon_kg_emitted += s.carbon_kg_emitted;
+            total.flops_reserved += s.flops_reserved;
+            total.energy_kwh_reserved += s.energy_kwh_reserved;
+            total.carbon_kg_reserved += s.carbon_kg_reserved;
+        }
+        total
+    }
+
+    fn snapshot(&self, actor: &ActorId, window: &UsageWindowId) -> UsageSnapshot {
+        let (flops_used, energy_kwh_used, carbon_kg_emitted) = self
+            .usage
+            .get(&key(actor, window))
+            .copied()
+            .unwrap_or_default();
+        let mut snapshot = UsageSnapshot {
+            window_id: window.clone(),
+            flops_used,
+            energy_kwh_used,
+            carbon_kg_emitted,
+            flops_reserved: 0.0,
+            energy_kwh_reserved: 0.0,
+            carbon_kg_reserved: 0.0,
+        };
+        for r in self.reservations.values() {
+            if r.state.is_outstanding() && r.actor_id.0 == actor.0 && r.window_id.0 == window.0 {
+                snapshot.flops_reserved += r.expected_flops;
+                snapshot.energy_kwh_reserved += r.expected_energy_kwh;
+                snapshot.carbon_kg_reserved += r.expected_carbon_kg;
+            }
+        }
+        snapshot
+    }
+
+    fn transition(
+        &mut self,
+        reservation_id: &ReservationId,
+        next: ReservationState,
+    ) -> Result<&mut Reservation> {
+        let reservation = self
+            .reservations
+            .get_mut(&reservation_id.0)
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("unknown reservation {}", reservation_id.0))
+            })?;
+        if !reservation.state.can_transition_to(next) {
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} cannot move from {:?} to {:?}",
+                reservation_id.0, reservation.state, next
+            ))
+            .into());
+        }
+        reservation.state = next;
+        Ok(reservation)
+    }
+}
+
+/// `QuotaStore` backed by a mutex-guarded map. Reservation ids are issued
+/// sequentially (`Uuid::from_u128(1)`, `2`, ...) so runs are reproducible.
+#[derive(Clone, Default)]
+pub struct InMemoryQuotaStore {
+    state: Arc<Mutex<QuotaState>>,
+}
+
+impl InMemoryQuotaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_allowance(
+        self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        allowance: ComputeEnergyAllowance,
+    ) -> Self {
+        self.set_allowance(actor, window, allowance);
+        self
+    }
+
+    pub fn set_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        allowance: ComputeEnergyAllowance,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.allowances.insert(key(actor, window), allowance);
+    }
+
+    /// Places `project` under `organisation`.
+    pub fn with_project(self, project: &str, organisation: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .projects
+            .insert(project.to_string(), organisation.to_string());
+        self
+    }
+
+    /// Makes `actor` a member of `project`.
+    pub fn with_member(self, actor: &ActorId, project: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .members
+            .insert(actor.0.clone(), project.to_string());
+        self
+    }
+
+    /// Caps an organisation or project for the window.
+    pub fn with_group_allowance(
+        self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+        allowance: ComputeEnergyAllowance,
+    ) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .group_allowances
+            .insert((level, group_id.to_string(), window.0.clone()), allowance);
+        self
+    }
+
+    /// Overwrites committed usage, e.g. to start a test part-way through a
+    /// window.
+    pub fn set_usage(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        flops_used: f64,
+        energy_kwh_used: f64,
+        carbon_kg_emitted: f64,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .usage
+            .insert(key(actor, window), (flops_used, energy_kwh_used, carbon_kg_emitted));
+    }
+
+    pub fn reservations(&self) -> Vec<Reservation> {
+        let state = self.state.lock().unwrap();
+        let mut all: Vec<_> = state.reservations.values().cloned().collect();
+        all.sort_by_key(|r| r.id.0);
+        all
+    }
+}
+
+#[async_trait::async_trait]
+impl QuotaStore for InMemoryQuotaStore {
+    async fn get_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<ComputeEnergyAllowance> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .allowances
+            .get(&key(actor, window))
+            .cloned()
+            .ok_or_else(|| no_allowance(actor, window))?)
+    }
+
+    async fn find_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.allowances.get(&key(actor, window)).cloned())
+    }
+
+    async fn provision_allowance(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        if state.allowances.contains_key(&key(actor, window)) {
+            return Ok(false);
+        }
+        state.allowances.insert(key(actor, window), allowance.clone());
+        Ok(true)
+    }
+
+    async fn get_usage(&self, actor: &ActorId, window: &UsageWindowId) -> Result<UsageSnapshot> {
+        let state = self.state.lock().unwrap();
+        Ok(state.snapshot(actor, window))
+    }
+
+    async fn budget_path(&self, actor: &ActorId) -> Result<BudgetPath> {
+        let state = self.state.lock().unwrap();
+        Ok(state.path(actor))
+    }
+
+    async fn find_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<Option<ComputeEnergyAllowance>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .group_allowances
+            .get(&(level, group_id.to_string(), window.0.clone()))
+            .cloned())
+    }
+
+    async fn provision_group_allowance(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+        allowance: &ComputeEnergyAllowance,
+    ) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        let key = (level, group_id.to_string(), window.0.clone());
+        if state.group_allowances.contains_key(&key) {
+            return Ok(false);
+        }
+        state.group_allowances.insert(key, allowance.clone());
+        Ok(true)
+    }
+
+    async fn get_group_usage(
+        &self,
+        level: BudgetLevel,
+        group_id: &str,
+        window: &UsageWindowId,
+    ) -> Result<UsageSnapshot> {
+        let state = self.state.lock().unwrap();
+        Ok(state.group_snapshot(level, group_id, window))
+    }
+
+    async fn budget_children(&self, level: BudgetLevel, group_id: &str) -> Result<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        let mut children: Vec<String> = match level {
+            BudgetLevel::Organisation => state
+                .projects
+                .iter()
+                .filter(|(_, organisation)| organisation.as_str() == group_id)
+                .map(|(project, _)| project.clone())
+                .collect(),
+            BudgetLevel::Project => state
+                .group_members(level, group_id)
+                .into_iter()
+                .map(|actor| actor.0)
+                .collect(),
+            BudgetLevel::Actor => Vec::new(),
+        };
+        children.sort();
+        Ok(children)
+    }
+
+    async fn reserve_quota(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+        job: &EcologicalJobSpec,
+        expected_energy_kwh: f64,
+        expected_carbon_kg: f64,
+    ) -> Result<ReservationId> {
+        let mut state = self.state.lock().unwrap();
+        let path = state.path(actor);
+        for (level, group) in [
+            (BudgetLevel::Organisation, path.organisation),
+            (BudgetLevel::Project, path.project),
+        ] {
+            let Some(group) = group else { continue };
+            if let Some(allowance) =
+                state.group_allowances.get(&(level, group.clone(), window.0.clone()))
+            {
+                ensure_headroom(
+                    level,
+                    allowance,
+                    &state.group_snapshot(level, &group, window),
+                    job.expected_flops,
+                    expected_energy_kwh,
+                    expected_carbon_kg,
+                )?;
+            }
+        }
+        let allowance = state
+            .allowances
+            .get(&key(actor, window))
+            .ok_or_else(|| no_allowance(actor, window))?;
+        ensure_headroom(
+            BudgetLevel::Actor,
+            allowance,
+            &state.snapshot(actor, window),
+            job.expected_flops,
+            expected_energy_kwh,
+            expected_carbon_kg,
+        )?;
+
+        state.next_id += 1;
+        let id = Uuid::from_u128(state.next_id);
+        let now = SystemTime::now();
+        let hold_from = job.deadline.map_or(now, |d| d.max(now));
+        state.reservations.insert(
+            id,
+            Reservation {
+                id: ReservationId(id),
+                actor_id: actor.clone(),
+                window_id: window.clone(),
+                expected_flops: job.expected_flops,
+                expected_energy_kwh,
+                expected_carbon_kg,
+                tier: job.requested_tier.clone(),
+                max_duration: job.max_duration,
+                state: ReservationState::Pending,
+                created_at: now,
+                expires_at: hold_from + job.max_duration,
+            },
+        );
+        Ok(ReservationId(id))
+    }
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .reservations
+            .get(&reservation_id.0)
+            .cloned()
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("unknown reservation {}", reservation_id.0))
+            })?)
+    }
+
+    async fn outstanding_reservations(
+        &self,
+        actor: &ActorId,
+        window: &UsageWindowId,
+    ) -> Result<Vec<Reservation>> {
+        let state = self.state.lock().unwrap();
+        let mut outstanding: Vec<Reservation> = state
+            .reservations
+            .values()
+            .filter(|r| {
+                r.state.is_outstanding() && r.actor_id.0 == actor.0 && r.window_id.0 == window.0
+            })
+            .cloned()
+            .collect();
+        outstanding.sort_by_key(|r| r.created_at);
+        Ok(outstanding)
+    }
+
+    async fn activate(&self, reservation_id: &ReservationId) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let reservation = state.transition(reservation_id, ReservationState::Active)?;
+        reservation.expires_at = SystemTime::now() + reservation.max_duration;
+        Ok(())
+    }
+
+    async fn extend_hold(&self, reservation_id: &ReservationId, until: SystemTime) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let reservation = state
+            .reservations
+            .get_mut(&reservation_id.0)
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("unknown reservation {}", reservation_id.0))
+            })?;
+        if reservation.state != ReservationState::Pending {
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} is {:?}, not pending",
+                reservation_id.0, reservation.state
+            ))
+            .into());
+        }
+        reservation.expires_at = reservation.expires_at.max(until);
+        Ok(())
+    }
+
+    async fn shrink_hold(
+        &self,
+        reservation_id: &ReservationId,
+        tier: &CapabilityTier,
+        expected_energy_kwh: f64,
+        expected_carbon_kg: f64,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let reservation = state
+            .reservations
+            .get_mut(&reservation_id.0)
+            .ok_or_else(|| {
+                OrchestratorError::NotFound(format!("unknown reservation {}", reservation_id.0))
+            })?;
+        if !reservation.state.is_outstanding() {
+            return Err(OrchestratorError::Conflict(format!(
+                "reservation {} is {:?}, not outstanding",
+                reservation_id.0, reservation.state
+            ))
+            .into());
+        }
+        reservation.tier = tier.clone();
+        reservation.expected_energy_kwh = reservation.expected_energy_kwh.min(expected_energy_kwh);
+        reservation.expected_carbon_kg = reservation.expected_carbon_kg.min(expected_carbon_kg);
+        Ok(())
+    }
+
+    async fn commit_usage(
+        &self,
+        reservation_id: &ReservationId,
+        actuals: &ReservationActuals,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let reservation = state.transition(reservation_id, ReservationState::Committed)?;
+        let k = key(&reservation.actor_id, &reservation.window_id);
+        let used = state.usage.entry(k).or_default();
+        used.0 += actuals.flops_used;
+        used.1 += actuals.energy_kwh_used;
+        used.2 += actuals.carbon_kg_emitted;
+        Ok(())
+    }
+
+    async fn release(&self, reservation_id: &ReservationId) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.transition(reservation_id, ReservationState::Released)?;
+        Ok(())
+    }
+
+    async fn expire_reservations(&self, cutoff: SystemTime) -> Result<Vec<ReservationId>> {
+        let mut state = self.state.lock().unwrap();
+        let mut expired: Vec<_> = state
+            .reservations
+            .values_mut()
+            .filter(|r| r.state.is_outstanding() && r.expires_at < cutoff)
+            .map(|r| {
+                r.state = ReservationState::Expired;
+                r.id.clone()
+            })
+            .collect();
+        expired.sort_by_key(|id| id.0);
+        Ok(expired)
+    }
+}
+
+/// `ImmutableLogger` that keeps the hash chain in memory.
+#[derive(Clone, Default)]
+pub struct InMemoryLogger {
+    entries: Arc<Mutex<Vec<ChainedLogEvent>>>,
+}
+
+impl InMemoryLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<EcologicalLogEvent> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.event().expect("logged events round-trip"))
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl ImmutableLogger for InMemoryLogger {
+    async fn append(&self, event: &EcologicalLogEvent) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let head = entries.last().map(|e| (e.sequence, e.hash.as_str()));
+        let entry = ChainedLogEvent::link(head, event, SystemTime::now())?;
+        entries.push(entry);
+        Ok(())
+    }
+
+    async fn entries(&self, from_sequence: u64, limit: usize) -> Result<Vec<ChainedLogEvent>> {
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.sequence >= from_sequence)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+fn no_approval(reservation_id: &ReservationId) -> OrchestratorError {
+    OrchestratorError::NotFound(format!("no approval for reservation {}", reservation_id.0))
+}
+
+/// `ApprovalStore` keyed by reservation id.
+#[derive(Clone, Default)]
+pub struct InMemoryApprovalStore {
+    requests: Arc<Mutex<HashMap<Uuid, ApprovalRequest>>>,
+}
+
+impl InMemoryApprovalStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalStore for InMemoryApprovalStore {
+    async fn insert(&self, request: &ApprovalRequest) -> Result<()> {
+        let mut requests = self.requests.lock().unwrap();
+        if requests.contains_key(&request.reservation_id.0) {
+            anyhow::bail!("approval for {} already exists", request.reservation_id.0);
+        }
+        requests.insert(request.reservation_id.0, request.clone());
+        Ok(())
+    }
+
+    async fn get(&self, reservation_id: &ReservationId) -> Result<ApprovalRequest> {
+        Ok(self
+            .requests
+            .lock()
+            .unwrap()
+            .get(&reservation_id.0)
+            .cloned()
+            .ok_or_else(|| no_approval(reservation_id))?)
+    }
+
+    async fn list_pending(&self) -> Result<Vec<ApprovalRequest>> {
+        let mut pending: Vec<ApprovalRequest> = self
+            .requests
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|r| r.status == ApprovalStatus::Pending)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|r| r.created_at);
+        Ok(pending)
+    }
+
+    async fn record_review(
+        &self,
+        reservation_id: &ReservationId,
+        review: ReviewRecord,
+    ) -> Result<ApprovalRequest> {
+        let mut requests = self.requests.lock().unwrap();
+        let request = requests
+            .get_mut(&reservation_id.0)
+            .ok_or_else(|| no_approval(reservation_id))?;
+        request.apply_review(review)?;
+        Ok(request.clone())
+    }
+
+    async fn withdraw(
+        &self,
+        reservation_id: &ReservationId,
+        reason: &str,
+    ) -> Result<ApprovalRequest> {
+        let mut requests = self.requests.lock().unwrap();
+        let request = requests
+            .get_mut(&reservation_id.0)
+            .ok_or_else(|| no_approval(reservation_id))?;
+        request.withdraw(reason)?;
+        Ok(request.clone())
+    }
+}
+
+fn no_job(reservation_id: &ReservationId) -> OrchestratorError {
+    OrchestratorError::NotFound(format!("no job for reservation {}", reservation_id.0))
+}
+
+/// `JobStore` keyed by reservation id.
+#[derive(Clone, Default)]
+pub struct InMemoryJobStore {
+    jobs: Arc<Mutex<HashMap<Uuid, JobRecord>>>,
+}
+
+impl InMemoryJobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl JobStore for InMemoryJobStore {
+    async fn insert(&self, record: &JobRecord) -> Result<()> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.contains_key(&record.reservation_id().0) {
+            anyhow::bail!("job {} already exists", record.reservation_id().0);
+        }
+        jobs.insert(record.reservation_id().0, record.clone());
+        Ok(())
+    }
+
+    async fn get(&self, reservation_id: &ReservationId) -> Result<JobRecord> {
+        Ok(self
+            .jobs
+            .lock()
+            .unwrap()
+            .get(&reservation_id.0)
+            .cloned()
+            .ok_or_else(|| no_job(reservation_id))?)
+    }
+
+    async fn list_by_status(&self, status: JobStatus) -> Result<Vec<JobRecord>> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut matching: Vec<JobRecord> =
+            jobs.values().filter(|r| r.status == status).cloned().collect();
+        matching.sort_by_key(|r| r.planned_at);
+        Ok(matching)
+    }
+
+    async fn replace(&self, record: &JobRecord, expected: JobStatus) -> Result<()> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let stored = jobs
+            .get_mut(&record.reservation_id().0)
+            .ok_or_else(|| no_job(record.reservation_id()))?;
+        if stored.status != expected {
+            return Err(OrchestratorError::Conflict(format!(
+                "job {} is {}, not {}",
+                record.reservation_id().0,
+                stored.status.as_str(),
+                expected.as_str()
+            ))
+            .into());
+        }
+        *stored = record.clone();
+        Ok(())
+    }
+}
+
+/// `PreemptionExecutor` that accepts every signal and remembers it.
+#[derive(Clone, Default)]
+pub struct RecordingPreemptionExecutor {
+    signals: Arc<Mutex<Vec<(ReservationId, PreemptionSignal)>>>,
+}
+
+impl RecordingPreemptionExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn signals(&self) -> Vec<(ReservationId, PreemptionSignal)> {
+        self.signals.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl PreemptionExecutor for RecordingPreemptionExecutor {
+    async fn signal(
+        &self,
+        record: &JobRecord,
+        signal: &PreemptionSignal,
+        _rationale: &str,
+    ) -> Result<()> {
+        self.signals
+            .lock()
+            .unwrap()
+            .push((record.reservation_id().clone(), signal.clone()));
+        Ok(())
+    }
+}
+
+/// `SegmentTelemetry` serving fixed loads per segment. Loads can be replaced
+/// at any time to simulate changing grid conditions.
+#[derive(Clone, Default)]
+pub struct StaticTelemetry {
+    loads: Arc<RwLock<HashMap<String, SegmentLoad>>>,
+}
+
+impl StaticTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_load(self, load: SegmentLoad) -> Self {
+        self.set_load(load);
+        self
+    }
+
+    pub fn set_load(&self, load: SegmentLoad) {
+        let mut loads = self.loads.write().unwrap();
+        loads.insert(load.segment_id.0.clone(), load);
+    }
+}
+
+#[async_trait::async_trait]
+impl SegmentTelemetry for StaticTelemetry {
+    async fn get_segment_load(&self, segment_id: &SegmentId) -> Result<SegmentLoad> {
+        let loads = self.loads.read().unwrap();
+        loads
+            .get(&segment_id.0)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("unknown segment_id {}", segment_id.0))
+    }
+}
+
+/// `IdentityResolver` mapping fixed session tokens to actor profiles.
+#[derive(Clone, Default)]
+pub struct StaticIdentityResolver {
+    sessions: Arc<RwLock<HashMap<String, ActorProfile>>>,
+}
+
+impl StaticIdentityResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_session(self, session_token: &str, actor: ActorProfile) -> Self {
+        self.sessions
+            .write()
+            .unwrap()
+            .insert(session_token.to_string(), actor);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl IdentityResolver for StaticIdentityResolver {
+    async fn resolve_actor(&self, session_token: &str) -> Result<ActorProfile> {
+        let sessions = self.sessions.read().unwrap();
+        Ok(sessions.get(session_token).cloned().ok_or_else(|| {
+            OrchestratorError::AuthenticationFailed("unknown session token".into())
+        })?)
+    }
+}
+
+fn no_zone(actor: &ActorProfile) -> OrchestratorError {
+    OrchestratorError::Forbidden(format!("no zone for actor {}", actor.actor_id.0))
+}
+
+/// `ZoneResolver` driven by a lookup table. Actor-specific entries win, then
+/// role entries in insertion order, then the default zone if one is set.
+#[derive(Clone, Default)]
+pub struct TableZoneResolver {
+    by_actor: HashMap<String, ZoneResolution>,
+    by_role: Vec<(String, ZoneResolution)>,
+    default_zone: Option<ZoneResolution>,
+}
+
+impl TableZoneResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_actor(mut self, actor: &ActorId, segment: SegmentId, trust_level: u8) -> Self {
+        self.by_actor.insert(
+            actor.0.clone(),
+            ZoneResolution {
+                segment_id: segment,
+                trust_level,
+            },
+        );
+        self
+    }
+
+    pub fn with_role(mut self, role: &str, segment: SegmentId, trust_level: u8) -> Self {
+        self.by_role.push((
+            role.to_string(),
+            ZoneResolution {
+                segment_id: segment,
+                trust_level,
+            },
+        ));
+        self
+    }
+
+    pub fn with_default(mut self, segment: SegmentId, trust_level: u8) -> Self {
+        self.default_zone = Some(ZoneResolution {
+            segment_id: segment,
+            trust_level,
+        });
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ZoneResolver for TableZoneResolver {
+    async fn resolve_zone(&self, actor: &ActorProfile) -> Result<ZoneResolution> {
+        if let Some(zone) = self.by_actor.get(&actor.actor_id.0) {
+            return Ok(zone.clone());
+        }
+        Ok(self
+            .by_role
+            .iter()
+            .find(|(role, _)| actor.roles.contains(role))
+            .map(|(_, zone)| zone.clone())
+            .or_else(|| self.default_zone.clone())
+            .ok_or_else(|| no_zone(actor))?)
+    }
+
+    /// Every matching entry (actor, then roles, then default), first match
+    /// per segment wins.
+    async fn resolve_zones(&self, actor: &ActorProfile) -> Result<Vec<ZoneResolution>> {
+        let candidates = self
+            .by_actor
+            .get(&actor.actor_id.0)
+            .into_iter()
+            .chain(
+                self.by_role
+                    .iter()
+                    .filter(|(role, _)| actor.roles.contains(role))
+                    .map(|(_, zone)| zone),
+            )
+            .chain(self.default_zone.iter());
+
+        let mut zones: Vec<ZoneResolution> = Vec::new();
+        for zone in candidates {
+            if !zones.iter().any(|z| z.segment_id.0 == zone.segment_id.0) {
+                zones.push(zone.clone());
+            }
+        }
+        if zones.is_empty() {
+            return Err(no_zone(actor).into());
+        }
+        Ok(zones)
+    }
+}
@@ -8,7 +8,7 @@ pub struct ActorId(pub String); // DID string
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentId(pub String); // ALN segment / xr-grid cluster
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CapabilityTier {
     Tier1,
     Tier2,
@@ -24,6 +24,35 @@ pub struct ComputeEnergyAllowance {
     pub valid_until: SystemTime,
 }
 
+/// Levels of the budget hierarchy: organisations are split into projects,
+/// projects into actors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLevel {
+    Organisation,
+    Project,
+    Actor,
+}
+
+impl BudgetLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BudgetLevel::Organisation => "organisation",
+            BudgetLevel::Project => "project",
+            BudgetLevel::Actor => "actor",
+        }
+    }
+
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "organisation" => BudgetLevel::Organisation,
+            "project" => BudgetLevel::Project,
+            "actor" => BudgetLevel::Actor,
+            other => anyhow::bail!("unknown budget level {:?}", other),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageWindowId(pub String); // e.g. "2026-02-08T00Z_daily"
 
@@ -33,6 +62,10 @@ pub struct UsageSnapshot {
     pub flops_used: f64,
     pub energy_kwh_used: f64,
     pub carbon_kg_emitted: f64,
+    // held by outstanding (pending/active) reservations
+    pub flops_reserved: f64,
+    pub energy_kwh_reserved: f64,
+    pub carbon_kg_reserved: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +86,12 @@ pub struct EcologicalJobSpec {
     pub max_duration: Duration,
     pub purpose: String,      // human-readable purpose
     pub domain_tags: Vec<String>, // e.g. ["climate", "watershed", "biodiversity"]
+    // segment capabilities the job needs, e.g. ["gpu"]; empty runs anywhere
+    #[serde(default)]
+    pub required_capabilities: Vec<String>,
+    // latest acceptable start if the job has to wait for a greener/cooler window
+    #[serde(default)]
+    pub deadline: Option<SystemTime>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +111,57 @@ pub struct JobExecutionPlan {
     pub approved_segment: SegmentId,
     pub approved_tier: CapabilityTier,
     pub stability_decision: StabilityDecision,
+    pub estimated_energy_kwh: f64,
+    pub estimated_carbon_kg: f64,
+    // set when the job was queued for a later window instead of starting now
+    pub deferral: Option<DeferralStatus>,
+    // other trusted segments, best first
+    pub alternatives: Vec<SegmentCandidate>,
+    // set when the reservation is held until human reviewers sign off
+    pub approval: Option<ApprovalStatus>,
+    // how the actor's priority weighed in, when fair sharing is enabled
+    pub fair_share: Option<FairShareDecision>,
+}
+
+/// An actor's fair-share standing when its job was planned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FairShareDecision {
+    pub priority_score: f64,
+    pub recent_usage_flops: f64, // decayed
+    pub weight: f64,
+    pub contended: bool,
+    pub burst_flops: f64,
+    // false when the job is larger than its burst on a contended segment
+    pub within_burst: bool,
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentCandidate {
+    pub segment_id: SegmentId,
+    pub score: f64,
+    pub renewable_share_pct: f64,
+    pub thermal_margin_pct: f64,
+    pub headroom: f64, // 0.0 (saturated) ..= 1.0 (idle)
+    pub trust_level: u8,
+    pub hinted: bool,
+    pub stability_decision: StabilityDecision,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferralStatus {
+    pub queue_position: usize,
+    pub estimated_start: SystemTime,
+    pub deadline: SystemTime,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,4 +177,7 @@ pub struct FairUseReceipt {
     pub allowance_remaining_energy_kwh: f64,
     pub allowance_remaining_carbon_kg: f64,
     pub explanation: String,
+    // omitted when empty so receipts signed before fair sharing still verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fair_share: Option<FairShareDecision>,
 }
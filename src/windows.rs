@@ -0,0 +1,329 @@
+//! Usage windows derived from server time.
+//!
+//! Window ids name the UTC start date and the period, e.g.
+//! `2026-02-08T00Z_daily`, `2026-02-02T00Z_weekly` (weeks start on Monday) or
+//! `2026-02-01T00Z_monthly`. Usage is recorded per window id, so a new window
+//! starts from zero; its allowance is provisioned from the actor's
+//! `WindowPolicy` the first time it is needed, optionally topped up with what
+//! the actor left unused in the window before. Organisation and project caps
+//! roll over the same way from their own policies.
+
+use crate::error::{OrchestratorError, RemainingAllowance};
+use crate::quota::{remaining_allowance, QuotaStore};
+use crate::types::{ActorId, BudgetLevel, CapabilityTier, ComputeEnergyAllowance, UsageWindowId};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY_SECS: u64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl WindowPeriod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WindowPeriod::Daily => "daily",
+            WindowPeriod::Weekly => "weekly",
+            WindowPeriod::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "daily" => WindowPeriod::Daily,
+            "weekly" => WindowPeriod::Weekly,
+            "monthly" => WindowPeriod::Monthly,
+            other => anyhow::bail!("unknown window period {:?}", other),
+        })
+    }
+}
+
+/// A window as a half-open interval `[starts_at, ends_at)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageWindow {
+    pub id: UsageWindowId,
+    pub period: WindowPeriod,
+    pub starts_at: SystemTime,
+    pub ends_at: SystemTime,
+}
+
+impl UsageWindow {
+    pub fn containing(period: WindowPeriod, at: SystemTime) -> Self {
+        let day = (at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / DAY_SECS) as i64;
+        let (start, end) = match period {
+            WindowPeriod::Daily => (day, day + 1),
+            WindowPeriod::Weekly => {
+                // 1970-01-01 was a Thursday
+                let monday = day - (day + 3).rem_euclid(7);
+                (monday, monday + 7)
+            }
+            WindowPeriod::Monthly => {
+                let (year, month, _) = civil_from_days(day);
+                let (next_year, next_month) = match month {
+                    12 => (year + 1, 1),
+                    _ => (year, month + 1),
+                };
+                (days_from_civil(year, month, 1), days_from_civil(next_year, next_month, 1))
+            }
+        };
+        let (year, month, day_of_month) = civil_from_days(start);
+        Self {
+            id: UsageWindowId(format!(
+                "{:04}-{:02}-{:02}T00Z_{}",
+                year,
+                month,
+                day_of_month,
+                period.as_str()
+            )),
+            period,
+            starts_at: day_start(start),
+            ends_at: day_start(end),
+        }
+    }
+
+    /// Parses a window id, rejecting dates that do not start a window of the
+    /// named period (a weekly window on a Wednesday, say).
+    pub fn parse(id: &UsageWindowId) -> Result<Self> {
+        let invalid =
+            || OrchestratorError::InvalidRequest(format!("malformed window id {:?}", id.0));
+        let (date, period) = id.0.split_once("T00Z_").ok_or_else(invalid)?;
+        let period = WindowPeriod::parse(period).map_err(|_| invalid())?;
+        let mut parts = date.splitn(3, '-').map(str::parse::<i64>);
+        let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid().into());
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+            return Err(invalid().into());
+        }
+        let window = Self::containing(period, day_start(days_from_civil(year, month, day)));
+        if window.id.0 != id.0 {
+            return Err(invalid().into());
+        }
+        Ok(window)
+    }
+
+    pub fn previous(&self) -> Self {
+        Self::containing(self.period, self.starts_at - Duration::from_secs(1))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::containing(self.period, self.ends_at)
+    }
+}
+
+fn day_start(day: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(day.max(0) as u64 * DAY_SECS)
+}
+
+// Proleptic Gregorian calendar conversions (Howard Hinnant's algorithms).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Base allowance granted for every window of a policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowanceTemplate {
+    pub max_flops: f64,
+    pub max_energy_kwh: f64,
+    pub max_carbon_kg: f64,
+    pub max_tier: CapabilityTier,
+}
+
+/// Unused allowance carried into the next window. Per dimension, at most
+/// `max_fraction` of the template is carried, so savings cannot snowball.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarryForward {
+    pub max_fraction: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowPolicy {
+    pub period: WindowPeriod,
+    pub allowance: AllowanceTemplate,
+    #[serde(default)]
+    pub carry_forward: Option<CarryForward>,
+}
+
+impl WindowPolicy {
+    /// The allowance for `window`, valid until the window ends. `unused` is
+    /// what the actor left in the previous window, if it had one.
+    pub fn allowance_for(
+        &self,
+        window: &UsageWindow,
+        unused: Option<&RemainingAllowance>,
+    ) -> ComputeEnergyAllowance {
+        let base = &self.allowance;
+        let (fraction, unused) = match (&self.carry_forward, unused) {
+            (Some(carry), Some(unused)) => (carry.max_fraction.max(0.0), unused.clone()),
+            _ => (0.0, RemainingAllowance::default()),
+        };
+        let carried = |left: f64, base: f64| left.clamp(0.0, base * fraction);
+        ComputeEnergyAllowance {
+            max_flops: base.max_flops + carried(unused.flops, base.max_flops),
+            max_energy_kwh: base.max_energy_kwh + carried(unused.energy_kwh, base.max_energy_kwh),
+            max_carbon_kg: base.max_carbon_kg + carried(unused.carbon_kg, base.max_carbon_kg),
+            max_tier: base.max_tier.clone(),
+            valid_until: window.ends_at,
+        }
+    }
+}
+
+/// Picks each actor's window policy and provisions allowances as windows
+/// roll over, for the actor and for the capped groups on its budget path.
+pub struct WindowManager {
+    default_policy: WindowPolicy,
+    by_actor: HashMap<String, WindowPolicy>,
+    by_group: HashMap<(BudgetLevel, String), WindowPolicy>,
+}
+
+impl WindowManager {
+    pub fn new(default_policy: WindowPolicy) -> Self {
+        Self {
+            default_policy,
+            by_actor: HashMap::new(),
+            by_group: HashMap::new(),
+        }
+    }
+
+    pub fn with_actor_policy(mut self, actor: &ActorId, policy: WindowPolicy) -> Self {
+        self.by_actor.insert(actor.0.clone(), policy);
+        self
+    }
+
+    /// Caps an organisation or project in every window. Its period must match
+    /// the windows of its members.
+    pub fn with_group_policy(
+        mut self,
+        level: BudgetLevel,
+        group_id: &str,
+        policy: WindowPolicy,
+    ) -> Self {
+        self.by_group.insert((level, group_id.to_string()), policy);
+        self
+    }
+
+    pub fn policy_for(&self, actor: &ActorId) -> &WindowPolicy {
+        self.by_actor.get(&actor.0).unwrap_or(&self.default_policy)
+    }
+
+    pub fn current_window(&self, actor: &ActorId, now: SystemTime) -> UsageWindow {
+        UsageWindow::containing(self.policy_for(actor).period, now)
+    }
+
+    /// Returns the actor's current window, provisioning its allowance, and
+    /// those of the groups on its budget path, on the first call after a
+    /// boundary. Usage and holds left in the previous window stay there; only
+    /// its unused allowance can carry forward.
+    ///
+    /// A group capped in the previous window but without a policy to cap it
+    /// in this one fails with `AllowanceExpired` rather than going uncapped.
+    pub async fn ensure_current<Q: QuotaStore>(
+        &self,
+        store: &Q,
+        actor: &ActorId,
+        now: SystemTime,
+    ) -> Result<UsageWindow> {
+        let policy = self.policy_for(actor);
+        let window = UsageWindow::containing(policy.period, now);
+        if store.find_allowance(actor, &window.id).await?.is_none() {
+            let previous = window.previous();
+            let unused = match store.find_allowance(actor, &previous.id).await? {
+                Some(allowance) => {
+                    let usage = store.get_usage(actor, &previous.id).await?;
+                    Some(remaining_allowance(&allowance, &usage))
+                }
+                None => None,
+            };
+            // A concurrent request may have provisioned it first; either way
+            // the stored allowance wins.
+            let allowance = policy.allowance_for(&window, unused.as_ref());
+            store.provision_allowance(actor, &window.id, &allowance).await?;
+        }
+
+        let path = store.budget_path(actor).await?;
+        for (level, group) in [
+            (BudgetLevel::Organisation, path.organisation),
+            (BudgetLevel::Project, path.project),
+        ] {
+            if let Some(group) = group {
+                self.ensure_group(store, level, &group, &window).await?;
+            }
+        }
+        Ok(window)
+    }
+
+    async fn ensure_group<Q: QuotaStore>(
+        &self,
+        store: &Q,
+        level: BudgetLevel,
+        group: &str,
+        window: &UsageWindow,
+    ) -> Result<()> {
+        if store.find_group_allowance(level, group, &window.id).await?.is_some() {
+            return Ok(());
+        }
+        let previous = window.previous();
+        let last = store.find_group_allowance(level, group, &previous.id).await?;
+        let Some(policy) = self.by_group.get(&(level, group.to_string())) else {
+            if last.is_none() {
+                return Ok(());
+            }
+            return Err(OrchestratorError::AllowanceExpired(format!(
+                "{} {} was capped in {} but has no allowance or window policy for {}",
+                level.as_str(),
+                group,
+                previous.id.0,
+                window.id.0
+            ))
+            .into());
+        };
+        if policy.period != window.period {
+            anyhow::bail!(
+                "{} {} has {} windows but its members use {} ones",
+                level.as_str(),
+                group,
+                policy.period.as_str(),
+                window.period.as_str()
+            );
+        }
+        let unused = match last {
+            Some(allowance) => {
+                let usage = store.get_group_usage(level, group, &previous.id).await?;
+                Some(remaining_allowance(&allowance, &usage))
+            }
+            None => None,
+        };
+        let allowance = policy.allowance_for(window, unused.as_ref());
+        store
+            .provision_group_allowance(level, group, &window.id, &allowance)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! Hash-chain integrity of the audit log. The Postgres check needs a scratch
+//! database via `EOL_TEST_DATABASE_URL` and is skipped otherwise.
+
+use deadpool_postgres::{Config, Runtime};
+use ecological_orchestrator::eol::logging::{
+    chain_hash, verify_chain, ChainVerifier, ChainedLogEvent, EcologicalLogEvent, ImmutableLogger,
+    LogEventType, GENESIS_HASH,
+};
+use ecological_orchestrator::eol::testing::InMemoryLogger;
+use ecological_orchestrator::eol::types::ActorId;
+use ecological_orchestrator::storage::logging_pg::PgImmutableLogger;
+use std::sync::Arc;
+use tokio_postgres::NoTls;
+
+fn event(n: u32) -> EcologicalLogEvent {
+    EcologicalLogEvent {
+        event_type: LogEventType::JobRequested,
+        reservation_id: None,
+        actor_id: Some(ActorId("did:example:alice".into())),
+        segment_id: None,
+        window_id: None,
+        metadata: serde_json::json!({ "n": n, "expected_flops": 1.5e15, "note": "ünïcode" }),
+    }
+}
+
+fn verify(entries: &[ChainedLogEvent]) -> Option<(u64, String)> {
+    let mut verifier = ChainVerifier::new();
+    for entry in entries {
+        verifier.push(entry);
+    }
+    verifier.finish().first_break.map(|b| (b.sequence, b.reason))
+}
+
+async fn logged(n: u32) -> Vec<ChainedLogEvent> {
+    let logger = InMemoryLogger::new();
+    for i in 0..n {
+        logger.append(&event(i)).await.unwrap();
+    }
+    logger.entries(0, usize::MAX).await.unwrap()
+}
+
+#[tokio::test]
+async fn intact_chain_verifies_across_pages() {
+    let logger = InMemoryLogger::new();
+    for i in 0..10 {
+        logger.append(&event(i)).await.unwrap();
+    }
+
+    let report = verify_chain(&logger, 3).await.unwrap();
+    assert!(report.is_intact());
+    assert_eq!(report.entries_checked, 10);
+
+    let entries = logger.entries(0, usize::MAX).await.unwrap();
+    assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+    assert_eq!(report.head_hash, entries[9].hash);
+}
+
+#[tokio::test]
+async fn edited_event_is_reported_at_its_sequence() {
+    let mut entries = logged(5).await;
+    entries[2].event_json = entries[2].event_json.replace("alice", "mallory");
+    let (sequence, reason) = verify(&entries).unwrap();
+    assert_eq!(sequence, 2);
+    assert!(reason.contains("does not match its hash"));
+
+    // Re-hashing the edited entry does not help: its successor no longer links.
+    let e = &entries[2];
+    entries[2].hash = chain_hash(&e.prev_hash, e.sequence, e.recorded_at, &e.event_json);
+    let (sequence, reason) = verify(&entries).unwrap();
+    assert_eq!(sequence, 3);
+    assert!(reason.contains("prev_hash"));
+}
+
+#[tokio::test]
+async fn deleted_or_reordered_entries_are_reported() {
+    let mut deleted = logged(5).await;
+    deleted.remove(1);
+    assert_eq!(verify(&deleted).unwrap().0, 1);
+
+    let mut reordered = logged(5).await;
+    reordered.swap(3, 4);
+    assert_eq!(verify(&reordered).unwrap().0, 3);
+}
+
+#[tokio::test]
+async fn pg_chain_survives_concurrent_appends_and_catches_tampering() {
+    let Ok(url) = std::env::var("EOL_TEST_DATABASE_URL") else {
+        eprintln!("EOL_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let schema = format!("eol_test_{}", uuid::Uuid::new_v4().simple());
+    let mut cfg = Config::new();
+    cfg.url = Some(url);
+    cfg.options = Some(format!("-c search_path={}", schema));
+    let pool = cfg.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute(&format!(
+            "CREATE SCHEMA {0};
+             CREATE TABLE {0}.eol_logs (
+                 sequence BIGINT PRIMARY KEY,
+                 recorded_at TIMESTAMPTZ NOT NULL,
+                 prev_hash TEXT NOT NULL,
+                 hash TEXT NOT NULL,
+                 event TEXT NOT NULL
+             );",
+            schema
+        ))
+        .await
+        .unwrap();
+
+    let logger = Arc::new(PgImmutableLogger::new(pool.clone()));
+    let tasks: Vec<_> = (0..32)
+        .map(|i| {
+            let logger = logger.clone();
+            tokio::spawn(async move { logger.append(&event(i)).await })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let report = verify_chain(logger.as_ref(), 10).await.unwrap();
+    assert!(report.is_intact(), "{:?}", report.first_break);
+    assert_eq!(report.entries_checked, 32);
+
+    let client = pool.get().await.unwrap();
+    client
+        .execute(
+            "UPDATE eol_logs SET event = replace(event, 'alice', 'mallory') WHERE sequence = 17",
+            &[],
+        )
+        .await
+        .unwrap();
+    let report = verify_chain(logger.as_ref(), 10).await.unwrap();
+    assert_eq!(report.first_break.unwrap().sequence, 17);
+    assert_eq!(report.entries_checked, 17);
+
+    client
+        .batch_execute(&format!("DROP SCHEMA {} CASCADE", schema))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,189 @@
+//! Organisation -> project -> actor budgets: a reservation has to fit at
+//! every capped level, tiers narrow down the hierarchy and usage rolls up.
+
+use ecological_orchestrator::eol::error::{OrchestratorError, QuotaDimension};
+use ecological_orchestrator::eol::quota::{QuotaService, QuotaStore};
+use ecological_orchestrator::eol::testing::InMemoryQuotaStore;
+use ecological_orchestrator::eol::types::{
+    ActorId, BudgetLevel, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec,
+    UsageWindowId,
+};
+use std::time::{Duration, SystemTime};
+
+fn window() -> UsageWindowId {
+    UsageWindowId("2026-02-08T00Z_daily".into())
+}
+
+fn allowance(max_flops: f64, max_tier: CapabilityTier) -> ComputeEnergyAllowance {
+    ComputeEnergyAllowance {
+        max_flops,
+        max_energy_kwh: 100.0,
+        max_carbon_kg: 20.0,
+        max_tier,
+        valid_until: SystemTime::now() + Duration::from_secs(86_400),
+    }
+}
+
+fn job(actor: &ActorId, flops: f64, tier: CapabilityTier) -> EcologicalJobSpec {
+    EcologicalJobSpec {
+        actor_id: actor.clone(),
+        segment_hint: None,
+        requested_tier: tier,
+        expected_flops: flops,
+        max_duration: Duration::from_secs(600),
+        purpose: "budget test".into(),
+        domain_tags: vec![],
+        required_capabilities: Vec::new(),
+        deadline: None,
+    }
+}
+
+fn alice() -> ActorId {
+    ActorId("did:example:alice".into())
+}
+
+fn bob() -> ActorId {
+    ActorId("did:example:bob".into())
+}
+
+fn carol() -> ActorId {
+    ActorId("did:example:carol".into())
+}
+
+/// Alice and Bob share the `watershed` project, Carol works on `grid`; both
+/// projects belong to the `basin` organisation.
+fn store() -> InMemoryQuotaStore {
+    let w = window();
+    InMemoryQuotaStore::new()
+        .with_project("watershed", "basin")
+        .with_project("grid", "basin")
+        .with_member(&alice(), "watershed")
+        .with_member(&bob(), "watershed")
+        .with_member(&carol(), "grid")
+        .with_group_allowance(
+            BudgetLevel::Organisation,
+            "basin",
+            &w,
+            allowance(20.0, CapabilityTier::Tier3),
+        )
+        .with_group_allowance(
+            BudgetLevel::Project,
+            "watershed",
+            &w,
+            allowance(10.0, CapabilityTier::Tier2),
+        )
+        .with_allowance(&alice(), &w, allowance(8.0, CapabilityTier::Tier3))
+        .with_allowance(&bob(), &w, allowance(8.0, CapabilityTier::Tier3))
+        .with_allowance(&carol(), &w, allowance(15.0, CapabilityTier::Tier3))
+}
+
+#[tokio::test]
+async fn reservations_must_fit_every_level() {
+    let service = QuotaService::new(store());
+    let w = window();
+
+    service
+        .check_and_reserve(&alice(), &w, &job(&alice(), 6.0, CapabilityTier::Tier1), 1.0, 0.1)
+        .await
+        .unwrap();
+
+    // Bob is well inside his own 8 FLOPs, but the project only has 4 left.
+    let err = service
+        .check_and_reserve(&bob(), &w, &job(&bob(), 5.0, CapabilityTier::Tier1), 1.0, 0.1)
+        .await
+        .unwrap_err();
+    match OrchestratorError::find(&err) {
+        Some(OrchestratorError::QuotaExceeded {
+            level,
+            dimension,
+            remaining,
+            ..
+        }) => {
+            assert_eq!(*level, BudgetLevel::Project);
+            assert_eq!(*dimension, QuotaDimension::Flops);
+            assert_eq!(remaining.flops, 4.0);
+        }
+        other => panic!("expected a project quota error, got {:?}", other),
+    }
+    service
+        .check_and_reserve(&bob(), &w, &job(&bob(), 4.0, CapabilityTier::Tier1), 1.0, 0.1)
+        .await
+        .unwrap();
+
+    // Carol's project is uncapped, so the organisation is the binding level.
+    let err = service
+        .check_and_reserve(&carol(), &w, &job(&carol(), 11.0, CapabilityTier::Tier1), 1.0, 0.1)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        OrchestratorError::find(&err),
+        Some(OrchestratorError::QuotaExceeded { level: BudgetLevel::Organisation, .. })
+    ));
+    service
+        .check_and_reserve(&carol(), &w, &job(&carol(), 10.0, CapabilityTier::Tier1), 1.0, 0.1)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn max_tier_is_inherited_down_the_hierarchy() {
+    let service = QuotaService::new(store());
+    let w = window();
+
+    // Alice's own allowance says Tier3, but her project stops at Tier2.
+    let err = service
+        .check_and_reserve(&alice(), &w, &job(&alice(), 1.0, CapabilityTier::Tier3), 1.0, 0.1)
+        .await
+        .unwrap_err();
+    match OrchestratorError::find(&err) {
+        Some(OrchestratorError::TierNotAllowed { allowed, reason, .. }) => {
+            assert_eq!(allowed, &vec![CapabilityTier::Tier1, CapabilityTier::Tier2]);
+            assert!(reason.contains("project"), "{reason}");
+        }
+        other => panic!("expected a tier error, got {:?}", other),
+    }
+    service
+        .check_and_reserve(&carol(), &w, &job(&carol(), 1.0, CapabilityTier::Tier3), 1.0, 0.1)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn reports_roll_usage_up_each_level() {
+    let store = store();
+    let service = QuotaService::new(store.clone());
+    let w = window();
+    store.set_usage(&alice(), &w, 2.0, 5.0, 1.0);
+    service
+        .check_and_reserve(&bob(), &w, &job(&bob(), 3.0, CapabilityTier::Tier1), 1.0, 0.1)
+        .await
+        .unwrap();
+    store.set_usage(&carol(), &w, 4.0, 2.0, 0.5);
+
+    let basin = service.budget_report(BudgetLevel::Organisation, "basin", &w).await.unwrap();
+    assert_eq!(basin.usage.flops_used, 6.0);
+    assert_eq!(basin.usage.flops_reserved, 3.0);
+    assert_eq!(basin.remaining.as_ref().unwrap().flops, 11.0);
+    let projects: Vec<_> = basin.children.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(projects, ["grid", "watershed"]);
+
+    let grid = &basin.children[0];
+    assert!(grid.allowance.is_none() && grid.remaining.is_none());
+    assert_eq!(grid.usage.flops_used, 4.0);
+
+    let watershed = &basin.children[1];
+    assert_eq!(watershed.usage.energy_kwh_used, 5.0);
+    assert_eq!(watershed.usage.energy_kwh_reserved, 1.0);
+    assert_eq!(watershed.remaining.as_ref().unwrap().flops, 5.0);
+    let actors: Vec<_> = watershed.children.iter().map(|a| a.id.as_str()).collect();
+    assert_eq!(actors, ["did:example:alice", "did:example:bob"]);
+    assert_eq!(watershed.children[1].remaining.as_ref().unwrap().flops, 5.0);
+
+    let project = service
+        .budget_report(BudgetLevel::Project, "watershed", &w)
+        .await
+        .unwrap();
+    assert_eq!(project.children.len(), 2);
+    assert!(project.children.iter().all(|a| a.children.is_empty()));
+    assert!(store.budget_children(BudgetLevel::Actor, "did:example:bob").await.unwrap().is_empty());
+}
@@ -0,0 +1,218 @@
+//! DID parsing, offline resolution and binding sessions to DIDs through
+//! signed challenges.
+
+use ecological_orchestrator::eol::did::{
+    challenge_message, Did, DidAuthenticator, DidBoundResolver, DidMethod, DidResolver,
+    LocalDidResolver,
+};
+use ecological_orchestrator::eol::error::OrchestratorError;
+use ecological_orchestrator::eol::identity::{ActorProfile, IdentityResolver};
+use ecological_orchestrator::eol::testing::StaticIdentityResolver;
+use ecological_orchestrator::eol::types::ActorId;
+use ed25519_dalek::{Signer, SigningKey};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn multibase(key: &SigningKey) -> String {
+    let mut bytes = vec![0xed, 0x01];
+    bytes.extend_from_slice(key.verifying_key().as_bytes());
+    format!("z{}", bs58::encode(bytes).into_string())
+}
+
+fn did_key(key: &SigningKey) -> String {
+    format!("did:key:{}", multibase(key))
+}
+
+fn alice_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+const ALICE_WEB: &str = "did:web:example.org:labs:alice";
+
+/// A cache directory holding Alice's did:web document.
+fn cache_dir() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("eol-dids-{}", uuid::Uuid::new_v4()));
+    let dir = root.join("example.org").join("labs").join("alice");
+    std::fs::create_dir_all(&dir).unwrap();
+    let document = serde_json::json!({
+        "@context": ["https://www.w3.org/ns/did/v1"],
+        "id": ALICE_WEB,
+        "verificationMethod": [{
+            "id": "#key-1",
+            "type": "Multikey",
+            "controller": ALICE_WEB,
+            "publicKeyMultibase": multibase(&alice_key()),
+        }],
+        "authentication": ["#key-1"],
+    });
+    std::fs::write(dir.join("did.json"), document.to_string()).unwrap();
+    root
+}
+
+fn profile(did: &str) -> ActorProfile {
+    ActorProfile {
+        actor_id: ActorId(did.into()),
+        roles: vec!["climate_lab".into()],
+        clearance_level: 3,
+        ecological_priority_score: 0.8,
+    }
+}
+
+fn auth_failure(err: &anyhow::Error) -> String {
+    match OrchestratorError::find(err) {
+        Some(OrchestratorError::AuthenticationFailed(message)) => message.clone(),
+        other => panic!("expected an authentication failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn did_key_and_did_web_identifiers_are_validated() {
+    let key = Did::parse(&did_key(&alice_key())).unwrap();
+    assert_eq!(key.method(), DidMethod::Key);
+    assert_eq!(Did::parse("did:web:example.org").unwrap().method(), DidMethod::Web);
+    Did::parse("did:web:grid.example.org%3A8443:labs:alice").unwrap();
+
+    for bad in [
+        "alice",
+        "did:example:alice",
+        "did:key:",
+        "did:key:zabc",
+        // base58 but not an Ed25519 multicodec key
+        "did:key:z6LSbysY2xFMRpGMhb7tFTLMpeuPRaqaWM1yECx2AtzE3KCc",
+        "did:web:",
+        "did:web:Example.org",
+        "did:web:example..org",
+        "did:web:example.org%3A",
+        "did:web:example.org:labs:..",
+        "did:web:example.org:labs:",
+    ] {
+        let err = Did::parse(bad).unwrap_err();
+        assert!(
+            matches!(OrchestratorError::find(&err), Some(OrchestratorError::InvalidRequest(_))),
+            "{bad} should be rejected"
+        );
+    }
+}
+
+#[tokio::test]
+async fn documents_resolve_from_the_local_cache() {
+    let root = cache_dir();
+    let resolver = LocalDidResolver::new(&root);
+
+    let alice = Did::parse(ALICE_WEB).unwrap();
+    assert_eq!(
+        resolver.document_path(&alice).unwrap(),
+        root.join("example.org/labs/alice/did.json")
+    );
+    let document = resolver.resolve(&alice).await.unwrap();
+    let keys = document.authentication_keys();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].0, format!("{}#key-1", ALICE_WEB));
+    assert_eq!(keys[0].1, alice_key().verifying_key());
+
+    // did:key documents need no cache.
+    let key_did = Did::parse(&did_key(&alice_key())).unwrap();
+    let document = resolver.resolve(&key_did).await.unwrap();
+    assert_eq!(document.authentication_keys()[0].1, alice_key().verifying_key());
+
+    let missing = Did::parse("did:web:example.org").unwrap();
+    let err = resolver.resolve(&missing).await.unwrap_err();
+    assert!(matches!(OrchestratorError::find(&err), Some(OrchestratorError::NotFound(_))));
+
+    // A document filed under the wrong DID is not trusted.
+    let bob = root.join("example.org/labs/bob");
+    std::fs::create_dir_all(&bob).unwrap();
+    std::fs::copy(root.join("example.org/labs/alice/did.json"), bob.join("did.json")).unwrap();
+    let bob_did = Did::parse("did:web:example.org:labs:bob").unwrap();
+    assert!(resolver.resolve(&bob_did).await.is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn sessions_resolve_only_after_proving_their_did() {
+    let root = cache_dir();
+    let sessions = StaticIdentityResolver::new()
+        .with_session("token-alice", profile(ALICE_WEB))
+        .with_session("token-mallory", profile(ALICE_WEB))
+        .with_session("token-legacy", profile("alice"));
+    let authenticator = Arc::new(DidAuthenticator::new(
+        LocalDidResolver::new(&root),
+        Arc::new(sessions.clone()),
+    ));
+    let resolver = DidBoundResolver::new(sessions, authenticator.clone());
+    let sign = |key: &SigningKey, message: &str| {
+        hex::encode(key.sign(message.as_bytes()).to_bytes())
+    };
+
+    let err = resolver.resolve_actor("token-alice").await.unwrap_err();
+    assert!(auth_failure(&err).contains("has not proven control"));
+    let err = resolver.resolve_actor("token-legacy").await.unwrap_err();
+    assert!(auth_failure(&err).contains("not a valid DID"));
+
+    // A signature from the wrong key fails and uses up the challenge.
+    let challenge = authenticator.issue_challenge("token-alice", ALICE_WEB).await.unwrap();
+    assert_eq!(challenge.message, challenge_message(ALICE_WEB, &challenge.nonce));
+    let wrong = sign(&SigningKey::from_bytes(&[9; 32]), &challenge.message);
+    let err = authenticator.prove("token-alice", &challenge.nonce, &wrong).await.unwrap_err();
+    assert!(auth_failure(&err).contains("matches no authentication key"));
+    let right = sign(&alice_key(), &challenge.message);
+    let err = authenticator.prove("token-alice", &challenge.nonce, &right).await.unwrap_err();
+    assert!(auth_failure(&err).contains("already answered"));
+
+    // A challenge only counts for the session it was issued to.
+    let challenge = authenticator.issue_challenge("token-alice", ALICE_WEB).await.unwrap();
+    let right = sign(&alice_key(), &challenge.message);
+    let err = authenticator.prove("token-mallory", &challenge.nonce, &right).await.unwrap_err();
+    assert!(auth_failure(&err).contains("another session"));
+
+    let challenge = authenticator.issue_challenge("token-alice", ALICE_WEB).await.unwrap();
+    let right = sign(&alice_key(), &challenge.message);
+    let did = authenticator.prove("token-alice", &challenge.nonce, &right).await.unwrap();
+    assert_eq!(did.as_str(), ALICE_WEB);
+    assert_eq!(resolver.resolve_actor("token-alice").await.unwrap().actor_id.0, ALICE_WEB);
+    assert!(resolver.resolve_actor("token-mallory").await.is_err());
+
+    // Challenges go only to authenticated sessions, for their actor's DID.
+    let other = did_key(&SigningKey::from_bytes(&[9; 32]));
+    let err = authenticator.issue_challenge("token-mallory", &other).await.unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "forbidden");
+    let err = authenticator.issue_challenge("token-nobody", ALICE_WEB).await.unwrap_err();
+    auth_failure(&err);
+
+    authenticator.unbind("token-alice");
+    assert!(resolver.resolve_actor("token-alice").await.is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn pending_challenges_are_capped_per_did_and_overall() {
+    let bob = did_key(&SigningKey::from_bytes(&[8; 32]));
+    let carol = did_key(&SigningKey::from_bytes(&[9; 32]));
+    let sessions = Arc::new(
+        StaticIdentityResolver::new()
+            .with_session("token-alice", profile(ALICE_WEB))
+            .with_session("token-bob", profile(&bob))
+            .with_session("token-carol", profile(&carol)),
+    );
+    let conflict = |err: anyhow::Error| OrchestratorError::find(&err).unwrap().code() == "conflict";
+
+    let documents = LocalDidResolver::new("/nonexistent");
+    let authenticator =
+        DidAuthenticator::new(documents, sessions.clone()).with_challenge_limits(2, 3);
+    for _ in 0..2 {
+        authenticator.issue_challenge("token-alice", ALICE_WEB).await.unwrap();
+    }
+    let err = authenticator.issue_challenge("token-alice", ALICE_WEB).await.unwrap_err();
+    assert!(conflict(err));
+    authenticator.issue_challenge("token-bob", &bob).await.unwrap();
+    let err = authenticator.issue_challenge("token-carol", &carol).await.unwrap_err();
+    assert!(conflict(err));
+
+    // Expired challenges are dropped before the limits are checked.
+    let authenticator = DidAuthenticator::new(LocalDidResolver::new("/nonexistent"), sessions)
+        .with_challenge_limits(1, 1)
+        .with_challenge_ttl(std::time::Duration::ZERO);
+    for _ in 0..3 {
+        authenticator.issue_challenge("token-alice", ALICE_WEB).await.unwrap();
+    }
+}
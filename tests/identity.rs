@@ -0,0 +1,116 @@
+//! Identity claims are checked against their schema before they become a
+//! profile, and resolved profiles are reused only while their TTL lasts. An
+//! identity service that stops answering is reported unavailable.
+
+use ecological_orchestrator::eol::error::OrchestratorError;
+use ecological_orchestrator::eol::identity::{ActorClaims, IdentityResolver, ResolutionCache};
+use ecological_orchestrator::identity_adapters::HttpIdentityResolver;
+use std::time::Duration;
+
+fn claims(json: serde_json::Value) -> serde_json::Result<ActorClaims> {
+    serde_json::from_value(json)
+}
+
+#[test]
+fn claims_become_profiles_with_their_roles() {
+    let profile = claims(serde_json::json!({
+        "actor_id": "did:example:alice",
+        "roles": ["climate_lab", "ethics_board"],
+        "clearance": 4,
+        "eco_priority": 0.7,
+        "display_name": "ignored",
+    }))
+    .unwrap()
+    .into_profile()
+    .unwrap();
+    assert_eq!(profile.actor_id.0, "did:example:alice");
+    assert_eq!(profile.roles, ["climate_lab", "ethics_board"]);
+    assert_eq!(profile.clearance_level, 4);
+    assert!((profile.ecological_priority_score - 0.7).abs() < 1e-6);
+
+    let roleless = claims(serde_json::json!({
+        "actor_id": "did:example:bob",
+        "clearance": 1,
+        "eco_priority": 0.2,
+    }))
+    .unwrap()
+    .into_profile()
+    .unwrap();
+    assert!(roleless.roles.is_empty());
+}
+
+#[test]
+fn claims_that_break_the_schema_are_rejected() {
+    // Required fields and types are enforced when parsing.
+    assert!(claims(serde_json::json!({"clearance": 2, "eco_priority": 0.5})).is_err());
+    assert!(claims(serde_json::json!({
+        "actor_id": "did:example:alice",
+        "clearance": 300,
+        "eco_priority": 0.5,
+    }))
+    .is_err());
+    assert!(claims(serde_json::json!({
+        "actor_id": "did:example:alice",
+        "roles": "climate_lab",
+        "clearance": 2,
+        "eco_priority": 0.5,
+    }))
+    .is_err());
+
+    // The rest fails authentication when the claims are turned into a profile.
+    for bad in [
+        serde_json::json!({"actor_id": "  ", "clearance": 2, "eco_priority": 0.5}),
+        serde_json::json!({"actor_id": "a", "roles": [""], "clearance": 2, "eco_priority": 0.5}),
+        serde_json::json!({"actor_id": "a", "clearance": 2, "eco_priority": 1.5}),
+    ] {
+        let err = claims(bad).unwrap().into_profile().unwrap_err();
+        assert!(matches!(
+            OrchestratorError::find(&err),
+            Some(OrchestratorError::AuthenticationFailed(_))
+        ));
+    }
+}
+
+#[test]
+fn cached_resolutions_expire_after_their_ttl() {
+    let profile = claims(serde_json::json!({
+        "actor_id": "did:example:alice",
+        "clearance": 3,
+        "eco_priority": 0.8,
+    }))
+    .unwrap()
+    .into_profile()
+    .unwrap();
+
+    let cache = ResolutionCache::new(Duration::from_millis(50));
+    assert!(cache.get("token-alice").is_none());
+    cache.insert("token-alice", &profile);
+    assert_eq!(cache.get("token-alice").unwrap().actor_id.0, "did:example:alice");
+    cache.invalidate("token-alice");
+    assert!(cache.get("token-alice").is_none());
+
+    cache.insert("token-alice", &profile);
+    std::thread::sleep(Duration::from_millis(80));
+    assert!(cache.get("token-alice").is_none());
+
+    let uncached = ResolutionCache::new(Duration::ZERO);
+    uncached.insert("token-alice", &profile);
+    assert!(uncached.get("token-alice").is_none());
+}
+
+#[tokio::test]
+async fn an_identity_service_that_never_answers_is_unavailable() {
+    // The listener accepts connections into its backlog but never replies.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let resolver = HttpIdentityResolver::new(base_url)
+        .with_timeouts(Duration::from_millis(200), Duration::from_millis(200));
+
+    let started = std::time::Instant::now();
+    let err = resolver.resolve_actor("token").await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    match OrchestratorError::find(&err) {
+        Some(OrchestratorError::IdentityUnavailable(msg)) => assert!(msg.contains("timed out")),
+        other => panic!("expected identity_unavailable, got {:?}", other),
+    }
+}
@@ -0,0 +1,1312 @@
+//! End-to-end planning checks against the in-memory trait implementations.
+
+use ecological_orchestrator::eol::approval::ReviewVerdict;
+use ecological_orchestrator::eol::clearance::ClearancePolicy;
+use ecological_orchestrator::eol::energy::{EnergyEstimator, StabilityGuard};
+use ecological_orchestrator::eol::error::{OrchestratorError, QuotaDimension};
+use ecological_orchestrator::eol::executor::{
+    ExecutionState, Executor, ExecutorPreemption, LaunchCommand, LocalProcessExecutor,
+    TierLimits,
+};
+use ecological_orchestrator::eol::fair_share::{FairShareAllocator, FairShareConfig};
+use ecological_orchestrator::eol::identity::ActorProfile;
+use ecological_orchestrator::eol::jobs::{JobReceipt, JobStatus, JobStore};
+use ecological_orchestrator::eol::logging::LogEventType;
+use ecological_orchestrator::eol::orchestrator::{EcologicalOrchestrator, PlacementEngine};
+use ecological_orchestrator::eol::policy::SimplePolicyEngine;
+use ecological_orchestrator::eol::preemption::{
+    PreemptionController, PreemptionPolicy, PreemptionSignal,
+};
+use ecological_orchestrator::eol::quota::{ReservationState, ReservationSweeper};
+use ecological_orchestrator::eol::receipts::{verify_receipt, ReceiptSigner};
+use ecological_orchestrator::eol::reconcile::{integrate, PowerSample, PowerSampleLog};
+use ecological_orchestrator::eol::scheduler::{DeferredJob, DeferredScheduler};
+use ecological_orchestrator::eol::testing::{
+    InMemoryApprovalStore, InMemoryJobStore, InMemoryLogger, InMemoryQuotaStore,
+    RecordingPreemptionExecutor, StaticIdentityResolver, StaticTelemetry, TableZoneResolver,
+};
+use ecological_orchestrator::eol::types::{
+    ActorId, ApprovalStatus, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec, ReservationId,
+    SegmentId, SegmentLoad, StabilityDecision, UsageWindowId,
+};
+use ecological_orchestrator::eol::windows::{
+    AllowanceTemplate, UsageWindow, WindowManager, WindowPeriod, WindowPolicy,
+};
+use std::time::{Duration, SystemTime};
+
+fn actor() -> ActorProfile {
+    ActorProfile {
+        actor_id: ActorId("did:example:alice".into()),
+        roles: vec!["climate_lab".into()],
+        clearance_level: 3,
+        ecological_priority_score: 0.8,
+    }
+}
+
+fn reviewer(did: &str, clearance_level: u8) -> ActorProfile {
+    ActorProfile {
+        actor_id: ActorId(did.into()),
+        roles: vec!["ethics_board".into()],
+        clearance_level,
+        ecological_priority_score: 0.5,
+    }
+}
+
+fn window() -> UsageWindowId {
+    UsageWindowId("2026-02-08T00Z_daily".into())
+}
+
+fn load(renewable_share_pct: f64) -> SegmentLoad {
+    SegmentLoad {
+        segment_id: SegmentId("segment_climate_hpc".into()),
+        current_flops: 1e12,
+        energy_rate_kw: 40.0,
+        thermal_margin_pct: 35.0,
+        renewable_share_pct,
+    }
+}
+
+fn job(tier: CapabilityTier) -> EcologicalJobSpec {
+    EcologicalJobSpec {
+        actor_id: actor().actor_id,
+        segment_hint: None,
+        requested_tier: tier,
+        expected_flops: 1e15,
+        max_duration: Duration::from_secs(3600),
+        purpose: "watershed runoff ensemble".into(),
+        domain_tags: vec!["climate".into(), "watershed".into()],
+        required_capabilities: Vec::new(),
+        deadline: None,
+    }
+}
+
+struct Fixture {
+    quota: InMemoryQuotaStore,
+    telemetry: StaticTelemetry,
+    logger: InMemoryLogger,
+    approvals: InMemoryApprovalStore,
+    jobs: InMemoryJobStore,
+}
+
+impl Fixture {
+    fn new(renewable_share_pct: f64) -> Self {
+        let quota = InMemoryQuotaStore::new().with_allowance(
+            &actor().actor_id,
+            &window(),
+            ComputeEnergyAllowance {
+                max_flops: 1e16,
+                max_energy_kwh: 500.0,
+                max_carbon_kg: 100.0,
+                max_tier: CapabilityTier::Tier3,
+                valid_until: SystemTime::now() + Duration::from_secs(86_400),
+            },
+        );
+        Self {
+            quota,
+            telemetry: StaticTelemetry::new().with_load(load(renewable_share_pct)),
+            logger: InMemoryLogger::new(),
+            approvals: InMemoryApprovalStore::new(),
+            jobs: InMemoryJobStore::new(),
+        }
+    }
+
+    fn orchestrator(
+        &self,
+    ) -> EcologicalOrchestrator<
+        StaticIdentityResolver,
+        TableZoneResolver,
+        InMemoryQuotaStore,
+        StaticTelemetry,
+        SimplePolicyEngine,
+        InMemoryLogger,
+        InMemoryApprovalStore,
+        InMemoryJobStore,
+    > {
+        EcologicalOrchestrator::new(
+            StaticIdentityResolver::new()
+                .with_session("token-alice", actor())
+                .with_session("token-bob", reviewer("did:example:bob", 4))
+                .with_session("token-carol", reviewer("did:example:carol", 5))
+                .with_session("token-dave", reviewer("did:example:dave", 2)),
+            TableZoneResolver::new()
+                .with_role("climate_lab", SegmentId("segment_climate_hpc".into()), 3)
+                .with_default(SegmentId("segment_general_research".into()), 2),
+            self.quota.clone(),
+            StabilityGuard::new(self.telemetry.clone(), 10.0, 40.0),
+            SimplePolicyEngine,
+            self.logger.clone(),
+            self.approvals.clone(),
+            self.jobs.clone(),
+        )
+    }
+}
+
+#[tokio::test]
+async fn plan_job_reserves_and_activates_quota() {
+    let fixture = Fixture::new(80.0);
+    let plan = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier2), None, None)
+        .await
+        .unwrap();
+
+    assert!(matches!(plan.stability_decision, StabilityDecision::Ok));
+    assert_eq!(plan.approved_segment.0, "segment_climate_hpc");
+    // 40 kW at 1 TFLOP/s is ~11.1 kWh per PFLOP, plus the 10% Tier2 overhead.
+    assert!((plan.estimated_energy_kwh - 12.222).abs() < 1e-3);
+
+    let reservations = fixture.quota.reservations();
+    assert_eq!(reservations.len(), 1);
+    assert_eq!(reservations[0].state, ReservationState::Active);
+
+    let events: Vec<_> = fixture
+        .logger
+        .events()
+        .into_iter()
+        .map(|e| e.event_type)
+        .collect();
+    assert!(matches!(
+        events.as_slice(),
+        [
+            LogEventType::JobRequested,
+            LogEventType::PolicyEvaluated,
+            LogEventType::QuotaReserved,
+            LogEventType::StabilityChecked
+        ]
+    ));
+}
+
+#[tokio::test]
+async fn denied_plan_releases_its_reservation() {
+    let fixture = Fixture::new(5.0);
+    let plan = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+
+    assert!(matches!(plan.stability_decision, StabilityDecision::Deny { .. }));
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Released);
+}
+
+#[tokio::test]
+async fn downgraded_plans_are_estimated_and_held_at_the_lower_tier() {
+    let fixture = Fixture::new(5.0);
+    let plan = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier2), None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_tier, CapabilityTier::Tier1);
+
+    let expected = EnergyEstimator::default().estimate(
+        &job(CapabilityTier::Tier1),
+        &CapabilityTier::Tier1,
+        &load(5.0),
+    );
+    let held = &fixture.quota.reservations()[0];
+    assert_eq!(held.tier, CapabilityTier::Tier1);
+    assert!((held.expected_energy_kwh - expected.energy_kwh).abs() < 1e-9);
+    assert!((held.expected_carbon_kg - expected.carbon_kg).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn unknown_session_is_rejected() {
+    let fixture = Fixture::new(80.0);
+    let result = fixture
+        .orchestrator()
+        .plan_job("token-mallory", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await;
+
+    assert!(result.is_err());
+    assert!(fixture.quota.reservations().is_empty());
+}
+
+#[tokio::test]
+async fn declared_energy_far_from_estimate_is_rejected() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture
+        .orchestrator()
+        .with_energy_estimator(EnergyEstimator::default().with_max_discrepancy(0.5));
+
+    let gamed = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), Some(1.0), None)
+        .await;
+    assert!(gamed.is_err());
+    assert!(fixture.quota.reservations().is_empty());
+
+    let honest = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), Some(10.0), None)
+        .await;
+    assert!(honest.is_ok());
+}
+
+#[tokio::test]
+async fn low_renewable_job_with_deadline_waits_for_greener_window() {
+    let fixture = Fixture::new(5.0);
+    let orchestrator = fixture.orchestrator();
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.deadline = Some(SystemTime::now() + Duration::from_secs(6 * 3600));
+
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    let deferral = plan.deferral.expect("job should be deferred");
+    assert_eq!(deferral.queue_position, 0);
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Pending);
+
+    // Still carbon-heavy: the job keeps waiting.
+    orchestrator.run_scheduler_once().await.unwrap();
+    assert!(orchestrator.deferral_status(&plan.reservation_id).is_some());
+    // Only the owner learns where the job stands in the queue.
+    let id = &plan.reservation_id;
+    assert_eq!(orchestrator.deferral("token-alice", id).await.unwrap().queue_position, 0);
+    let err = orchestrator.deferral("token-bob", id).await.unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "not_found");
+    assert!(orchestrator.deferral("token-nobody", id).await.is_err());
+
+    fixture.telemetry.set_load(load(75.0));
+    orchestrator.run_scheduler_once().await.unwrap();
+    assert!(orchestrator.deferral_status(&plan.reservation_id).is_none());
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Active);
+}
+
+#[tokio::test]
+async fn deferred_job_released_under_a_downgrade_runs_at_the_lower_tier() {
+    let fixture = Fixture::new(80.0);
+    // A burst allowance well below the job keeps it waiting while the
+    // segment is contended.
+    let segment = SegmentId("segment_climate_hpc".into());
+    let orchestrator = fixture
+        .orchestrator()
+        .with_scheduler(DeferredScheduler::new(Duration::ZERO, 4))
+        .with_placement_engine(PlacementEngine::default().with_capacity(&segment, 1.1e12))
+        .with_fair_share(FairShareAllocator::new(FairShareConfig {
+            base_burst_flops: 1e14,
+            ..FairShareConfig::default()
+        }));
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier2), None, None)
+        .await
+        .unwrap();
+    assert!(plan.deferral.is_some());
+    assert_eq!(plan.approved_tier, CapabilityTier::Tier2);
+
+    // Still contended on the next tick: the job is weighed again and waits.
+    orchestrator.run_scheduler_once().await.unwrap();
+    assert!(orchestrator.deferral_status(&plan.reservation_id).is_some());
+    let requeued = fixture.logger.events().pop().unwrap();
+    assert_eq!(requeued.metadata["deferred"], "requeued");
+    assert!(!requeued.metadata["fair_share"]["within_burst"].as_bool().unwrap());
+
+    // Contention eases, and renewables drop below the threshold before the
+    // job is released.
+    fixture.telemetry.set_load(SegmentLoad {
+        current_flops: 5e11,
+        ..load(20.0)
+    });
+    orchestrator.run_scheduler_once().await.unwrap();
+    let view = orchestrator.plan("token-alice", &plan.reservation_id).await.unwrap();
+    assert!(view.deferral.is_none());
+    assert_eq!(view.reservation_state, ReservationState::Active);
+    assert_eq!(view.job.plan.approved_tier, CapabilityTier::Tier1);
+    assert!(matches!(
+        view.job.plan.stability_decision,
+        StabilityDecision::Downgrade { .. }
+    ));
+}
+
+#[tokio::test]
+async fn deadlines_already_past_are_rejected() {
+    let fixture = Fixture::new(5.0);
+    let orchestrator = fixture.orchestrator();
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.deadline = Some(SystemTime::now() - Duration::from_secs(1));
+
+    let err = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "invalid_request");
+    assert!(fixture.quota.reservations().is_empty());
+}
+
+#[tokio::test]
+async fn deferred_jobs_are_queued_again_after_a_restart() {
+    let fixture = Fixture::new(5.0);
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.deadline = Some(SystemTime::now() + Duration::from_secs(3600));
+    let plan = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    let deferral = plan.deferral.unwrap();
+
+    // A fresh orchestrator starts with an empty queue and rebuilds it from
+    // the stored plans and their pending holds.
+    let restarted = fixture.orchestrator();
+    assert!(restarted.deferral_status(&plan.reservation_id).is_none());
+    assert_eq!(restarted.restore_deferred().await.unwrap(), 1);
+    let restored = restarted.deferral_status(&plan.reservation_id).unwrap();
+    assert_eq!(restored.deadline, deferral.deadline);
+    assert_eq!(restored.reason, deferral.reason);
+    assert_eq!(restarted.restore_deferred().await.unwrap(), 0);
+
+    // The segment recovers and the restored job is released as usual.
+    fixture.telemetry.set_load(load(80.0));
+    restarted.run_scheduler_once().await.unwrap();
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Active);
+    assert_eq!(restarted.restore_deferred().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn greenest_trusted_segment_is_chosen() {
+    let fixture = Fixture::new(45.0);
+    fixture.telemetry.set_load(SegmentLoad {
+        segment_id: SegmentId("segment_general_research".into()),
+        current_flops: 5e11,
+        energy_rate_kw: 20.0,
+        thermal_margin_pct: 40.0,
+        renewable_share_pct: 90.0,
+    });
+
+    let plan = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_segment.0, "segment_general_research");
+    assert_eq!(plan.alternatives.len(), 1);
+    assert_eq!(plan.alternatives[0].segment_id.0, "segment_climate_hpc");
+
+    // A hint is a tie-breaker, not an override of a much greener segment.
+    let mut hinted = job(CapabilityTier::Tier1);
+    hinted.segment_hint = Some(SegmentId("segment_climate_hpc".into()));
+    let plan = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), hinted, None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_segment.0, "segment_general_research");
+    assert!(plan.alternatives[0].hinted);
+}
+
+#[tokio::test]
+async fn geoengineering_job_waits_for_multi_party_approval() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let mut spec = job(CapabilityTier::Tier2);
+    spec.domain_tags.push("geoengineering".into());
+
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approval, Some(ApprovalStatus::Pending));
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Pending);
+
+    let id = plan.reservation_id;
+    let approve = |token: &'static str| {
+        orchestrator.review_plan(token, &id, ReviewVerdict::Approve, String::new())
+    };
+    assert!(approve("token-alice").await.is_err(), "requester cannot approve");
+    assert!(approve("token-dave").await.is_err(), "clearance too low");
+    assert!(orchestrator.pending_approvals("token-dave").await.unwrap().is_empty());
+
+    let first = approve("token-bob").await.unwrap();
+    assert_eq!(first.status, ApprovalStatus::Pending);
+    assert!(approve("token-bob").await.is_err(), "one vote per reviewer");
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Pending);
+
+    let second = approve("token-carol").await.unwrap();
+    assert_eq!(second.status, ApprovalStatus::Approved);
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Active);
+    assert!(matches!(
+        fixture.logger.events().last().unwrap().event_type,
+        LogEventType::ApprovalReviewed
+    ));
+}
+
+#[tokio::test]
+async fn held_plans_keep_their_hold_for_the_review_ttl_only() {
+    let fixture = Fixture::new(80.0);
+    let sweeper = ReservationSweeper::new(fixture.quota.clone(), Duration::ZERO, Duration::ZERO);
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.domain_tags.push("critical_infrastructure".into());
+    spec.max_duration = Duration::from_millis(10);
+
+    // The review TTL outlasts the job's own expiry.
+    let orchestrator = fixture.orchestrator();
+    let kept = orchestrator
+        .plan_job("token-alice", Some(window()), spec.clone(), None, None)
+        .await
+        .unwrap();
+    let orchestrator = orchestrator.with_review_ttl(Duration::from_millis(30));
+    let lapsed = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    let expired = sweeper.sweep_once().await.unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].0, lapsed.reservation_id.0);
+
+    // A review landing after expiry finds the request rejected.
+    let err = orchestrator
+        .review_plan("token-bob", &lapsed.reservation_id, ReviewVerdict::Approve, String::new())
+        .await
+        .unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "conflict");
+    let view = orchestrator.plan("token-alice", &lapsed.reservation_id).await.unwrap();
+    assert_eq!(view.approval.unwrap().status, ApprovalStatus::Rejected);
+    assert_eq!(view.reservation_state, ReservationState::Expired);
+
+    let pending = orchestrator.pending_approvals("token-bob").await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].reservation_id.0, kept.reservation_id.0);
+    let approved = orchestrator
+        .review_plan("token-bob", &kept.reservation_id, ReviewVerdict::Approve, String::new())
+        .await
+        .unwrap();
+    assert_eq!(approved.status, ApprovalStatus::Approved);
+    let view = orchestrator.plan("token-alice", &kept.reservation_id).await.unwrap();
+    assert_eq!(view.reservation_state, ReservationState::Active);
+}
+
+#[tokio::test]
+async fn stale_approvals_are_withdrawn_before_reviewers_see_them() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator().with_review_ttl(Duration::ZERO);
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.domain_tags.push("critical_infrastructure".into());
+    spec.max_duration = Duration::from_millis(10);
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    // Not swept yet: the lapsed hold is handed back when reviewers look.
+    assert!(orchestrator.pending_approvals("token-bob").await.unwrap().is_empty());
+    let view = orchestrator.plan("token-alice", &plan.reservation_id).await.unwrap();
+    assert_eq!(view.reservation_state, ReservationState::Released);
+    assert_eq!(view.approval.unwrap().status, ApprovalStatus::Rejected);
+    let withdrawn = fixture.logger.events().pop().unwrap();
+    assert!(matches!(withdrawn.event_type, LogEventType::ApprovalReviewed));
+    assert!(withdrawn.metadata["withdrawn"].as_str().unwrap().contains("expired"));
+}
+
+#[tokio::test]
+async fn rejected_plan_gives_back_its_hold() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.domain_tags.push("critical_infrastructure".into());
+
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    let pending = orchestrator.pending_approvals("token-bob").await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].required_approvals, 1);
+
+    let rejected = orchestrator
+        .review_plan(
+            "token-bob",
+            &plan.reservation_id,
+            ReviewVerdict::Reject,
+            "needs a smaller ensemble".into(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rejected.status, ApprovalStatus::Rejected);
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Released);
+    assert!(orchestrator
+        .review_plan("token-carol", &plan.reservation_id, ReviewVerdict::Approve, String::new())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn sensitive_work_needs_clearance_and_a_trusted_segment() {
+    let fixture = Fixture::new(60.0);
+    fixture.telemetry.set_load(SegmentLoad {
+        segment_id: SegmentId("segment_general_research".into()),
+        current_flops: 5e11,
+        energy_rate_kw: 20.0,
+        thermal_margin_pct: 40.0,
+        renewable_share_pct: 90.0,
+    });
+    let orchestrator = fixture.orchestrator().with_clearance_policy(
+        ClearancePolicy::new()
+            .with_tier(CapabilityTier::Tier3, 4, 3)
+            .with_domain_tag("geoengineering", 3, 3)
+            .with_domain_tag("solar_geoengineering", 0, 5),
+    );
+
+    // Alice's clearance of 3 is short of what Tier3 needs.
+    let err = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier3), None, None)
+        .await
+        .unwrap_err();
+    match OrchestratorError::find(&err) {
+        Some(OrchestratorError::PolicyDenied(message)) => {
+            assert!(message.contains("Tier3 requires clearance 4, the actor has 3"))
+        }
+        other => panic!("expected a policy denial, got {:?}", other),
+    }
+    assert!(fixture.quota.reservations().is_empty());
+    let denial = fixture.logger.events().pop().unwrap();
+    assert!(matches!(denial.event_type, LogEventType::PolicyEvaluated));
+    assert_eq!(denial.metadata["denied"], true);
+    assert_eq!(denial.metadata["required_clearance"], 4);
+    assert_eq!(
+        denial.metadata["notes"][0],
+        "Tier3 requires clearance 4, the actor has 3"
+    );
+
+    // Geoengineering is cleared but kept off the greener, less trusted segment.
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.domain_tags.push("geoengineering".into());
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_segment.0, "segment_climate_hpc");
+    assert!(plan.alternatives.is_empty());
+    let evaluated = fixture
+        .logger
+        .events()
+        .into_iter()
+        .rfind(|e| matches!(e.event_type, LogEventType::PolicyEvaluated))
+        .unwrap();
+    let excluded = &evaluated.metadata["excluded_segments"][0];
+    assert_eq!(excluded["segment_id"], "segment_general_research");
+    assert_eq!(excluded["reasons"][0], "trust level 2 is below the 3 this job requires");
+
+    // No segment is trusted enough, and the refusal is on record too.
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.domain_tags.push("solar_geoengineering".into());
+    let err = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "forbidden");
+    let denial = fixture.logger.events().pop().unwrap();
+    assert!(matches!(denial.event_type, LogEventType::PolicyEvaluated));
+    assert_eq!(denial.metadata["denied"], true);
+    assert_eq!(denial.metadata["min_segment_trust"], 5);
+    assert_eq!(denial.metadata["excluded_segments"].as_array().unwrap().len(), 2);
+
+    // Routine work still goes to the greenest segment.
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_segment.0, "segment_general_research");
+}
+
+#[tokio::test]
+async fn completed_job_is_charged_its_share_of_measured_power() {
+    let fixture = Fixture::new(80.0);
+    let now = SystemTime::now();
+    let started_at = now - Duration::from_secs(3600);
+    let power_log = PowerSampleLog::default();
+    power_log.record(&load(80.0), started_at);
+    power_log.record(&load(80.0), now);
+    let signer = ReceiptSigner::from_seed_hex(&"07".repeat(32)).unwrap();
+    let keys = signer.public_keys();
+    let orchestrator = fixture
+        .orchestrator()
+        .with_power_log(power_log)
+        .with_receipt_signer(signer);
+
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+    // The job has been running for an hour.
+    let mut record = orchestrator.start_job("token-alice", id).await.unwrap();
+    record.started_at = Some(started_at);
+    fixture.jobs.replace(&record, JobStatus::Running).await.unwrap();
+    let finished = orchestrator.finish_job("token-alice", id, Some(1.8e15)).await.unwrap();
+    let JobReceipt {
+        receipt,
+        signed_receipt,
+    } = finished.receipt.unwrap();
+
+    // The segment drew 40 kWh while doing 3.6e15 FLOPs; the job did half.
+    assert!((receipt.energy_kwh_used - 20.0).abs() < 1e-6);
+    // 80% renewable: 0.2 * 0.45 + 0.8 * 0.03 = 0.114 kg/kWh
+    assert!((receipt.carbon_kg_emitted - 2.28).abs() < 1e-6);
+    assert!((receipt.allowance_remaining_energy_kwh - 480.0).abs() < 1e-6);
+    assert!((receipt.allowance_remaining_flops - (1e16 - 1.8e15)).abs() < 1.0);
+
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Committed);
+    let completed = fixture.logger.events().pop().unwrap();
+    assert!(matches!(completed.event_type, LogEventType::JobCompleted));
+    let share = completed.metadata["attributed_share"].as_f64().unwrap();
+    assert!((share - 0.5).abs() < 1e-4);
+    assert_eq!(completed.metadata["runtime_secs"], 3600);
+    verify_receipt(&signed_receipt.unwrap(), &keys).unwrap();
+}
+
+#[tokio::test]
+async fn job_runs_through_start_complete_and_receipt() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+
+    // Only the owner can see or drive the job, and it has no receipt yet.
+    assert!(orchestrator.plan("token-bob", id).await.is_err());
+    assert!(orchestrator.receipt("token-alice", id).await.is_err());
+    assert!(orchestrator.finish_job("token-alice", id, None).await.is_err());
+
+    let started = orchestrator.start_job("token-alice", id).await.unwrap();
+    assert_eq!(started.status, JobStatus::Running);
+    assert!(orchestrator.start_job("token-alice", id).await.is_err());
+
+    let finished = orchestrator.finish_job("token-alice", id, Some(5e14)).await.unwrap();
+    assert_eq!(finished.status, JobStatus::Completed);
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.reservation_state, ReservationState::Committed);
+    let receipt = orchestrator.receipt("token-alice", id).await.unwrap();
+    assert!((receipt.receipt.allowance_remaining_flops - (1e16 - 5e14)).abs() < 1.0);
+    assert!(orchestrator.cancel_job("token-alice", id, "late".into(), None).await.is_err());
+
+    let events: Vec<_> = fixture
+        .logger
+        .events()
+        .into_iter()
+        .map(|e| e.event_type)
+        .collect();
+    assert!(matches!(
+        events.as_slice(),
+        [.., LogEventType::JobStarted, LogEventType::JobCompleted]
+    ));
+}
+
+#[tokio::test]
+async fn job_outrunning_its_hold_is_still_charged() {
+    let fixture = Fixture::new(80.0);
+    let sweeper = ReservationSweeper::new(fixture.quota.clone(), Duration::ZERO, Duration::ZERO);
+    let orchestrator = fixture.orchestrator();
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.max_duration = Duration::from_millis(10);
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.start_job("token-alice", id).await.unwrap();
+
+    // The sweeper expires the hold while the job is still running.
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(sweeper.sweep_once().await.unwrap().len(), 1);
+
+    let finished = orchestrator.finish_job("token-alice", id, Some(2e15)).await.unwrap();
+    assert_eq!(finished.status, JobStatus::Completed);
+    assert!(finished.receipt.is_some());
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.reservation_state, ReservationState::Committed);
+    let receipt = orchestrator.receipt("token-alice", id).await.unwrap();
+    assert!((receipt.receipt.allowance_remaining_flops - (1e16 - 2e15)).abs() < 1.0);
+}
+
+#[tokio::test]
+async fn receipts_never_report_negative_headroom() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.start_job("token-alice", id).await.unwrap();
+
+    // The executor counted more than the whole window allows.
+    let finished = orchestrator.finish_job("token-alice", id, Some(3e16)).await.unwrap();
+    let receipt = finished.receipt.unwrap().receipt;
+    assert_eq!(receipt.flops_used, 3e16);
+    assert_eq!(receipt.allowance_remaining_flops, 0.0);
+}
+
+#[tokio::test]
+async fn cancelling_a_running_job_cannot_report_its_usage_away() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let mut spec = job(CapabilityTier::Tier1);
+    spec.max_duration = Duration::from_millis(20);
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), spec, None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.start_job("token-alice", id).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    for bogus in [-1.0, f64::NAN, f64::INFINITY] {
+        let err = orchestrator
+            .cancel_job("token-alice", id, "oops".into(), Some(bogus))
+            .await
+            .unwrap_err();
+        assert_eq!(OrchestratorError::find(&err).unwrap().code(), "invalid_request");
+    }
+
+    // It ran past its time limit, so it pays for every expected FLOP.
+    let cancelled = orchestrator
+        .cancel_job("token-alice", id, "done early".into(), Some(0.0))
+        .await
+        .unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    let receipt = cancelled.receipt.unwrap().receipt;
+    assert!((receipt.flops_used - 1e15).abs() < 1.0);
+    assert!((receipt.allowance_remaining_flops - (1e16 - 1e15)).abs() < 1.0);
+}
+
+#[tokio::test]
+async fn cancelling_a_planned_job_releases_its_hold() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+
+    let cancelled = orchestrator
+        .cancel_job("token-alice", &plan.reservation_id, "superseded".into(), None)
+        .await
+        .unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    assert!(cancelled.receipt.is_none());
+    assert_eq!(fixture.quota.reservations()[0].state, ReservationState::Released);
+    assert!(orchestrator.start_job("token-alice", &plan.reservation_id).await.is_err());
+    assert!(matches!(
+        fixture.logger.events().last().unwrap().event_type,
+        LogEventType::JobCancelled
+    ));
+}
+
+#[tokio::test]
+async fn failures_carry_typed_errors() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+
+    let err = orchestrator
+        .plan_job("token-mallory", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        OrchestratorError::find(&err),
+        Some(OrchestratorError::AuthenticationFailed(_))
+    ));
+
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let mut greedy = job(CapabilityTier::Tier1);
+    greedy.expected_flops = 9.5e15;
+    let err = orchestrator
+        .plan_job("token-alice", Some(window()), greedy, None, None)
+        .await
+        .unwrap_err();
+    let body = OrchestratorError::find(&err).unwrap().body();
+    assert_eq!(body.code, "quota_exceeded");
+    assert_eq!(body.dimension, Some(QuotaDimension::Flops));
+    // the first plan still holds 1e15 of the 1e16 FLOPs
+    assert!((body.remaining.unwrap().flops - 9e15).abs() < 1.0);
+
+    let err = orchestrator.plan("token-bob", &plan.reservation_id).await.unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "forbidden");
+    let err = orchestrator.receipt("token-alice", &plan.reservation_id).await.unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "not_found");
+}
+
+#[tokio::test]
+async fn actors_can_see_their_remaining_headroom() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator();
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+
+    let report = orchestrator
+        .window_usage("token-alice", &actor().actor_id, &window())
+        .await
+        .unwrap();
+    assert_eq!(report.outstanding.len(), 1);
+    assert_eq!(report.outstanding[0].id.0, plan.reservation_id.0);
+    assert_eq!(report.usage.flops_reserved, 1e15);
+    assert!((report.remaining.flops - 9e15).abs() < 1.0);
+    assert!((report.remaining.energy_kwh - (500.0 - plan.estimated_energy_kwh)).abs() < 1e-9);
+
+    // Someone else's window is not visible.
+    assert!(orchestrator
+        .window_usage("token-bob", &actor().actor_id, &window())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn server_derives_the_window_and_enforces_validity() {
+    let fixture = Fixture::new(80.0);
+    let orchestrator = fixture.orchestrator().with_window_manager(WindowManager::new(
+        WindowPolicy {
+            period: WindowPeriod::Daily,
+            allowance: AllowanceTemplate {
+                max_flops: 1e16,
+                max_energy_kwh: 500.0,
+                max_carbon_kg: 100.0,
+                max_tier: CapabilityTier::Tier3,
+            },
+            carry_forward: None,
+        },
+    ));
+
+    orchestrator
+        .plan_job("token-alice", None, job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let today = UsageWindow::containing(WindowPeriod::Daily, SystemTime::now());
+    assert_eq!(fixture.quota.reservations()[0].window_id.0, today.id.0);
+
+    // Callers cannot pick another window, e.g. one with budget left over.
+    let err = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "invalid_request");
+
+    // Without a manager, an allowance past its valid_until is refused.
+    fixture.quota.set_allowance(
+        &actor().actor_id,
+        &window(),
+        ComputeEnergyAllowance {
+            max_flops: 1e16,
+            max_energy_kwh: 500.0,
+            max_carbon_kg: 100.0,
+            max_tier: CapabilityTier::Tier3,
+            valid_until: SystemTime::now() - Duration::from_secs(1),
+        },
+    );
+    let err = fixture
+        .orchestrator()
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "allowance_expired");
+}
+
+#[tokio::test]
+async fn fair_share_sizes_bursts_by_priority_on_contended_segments() {
+    let fixture = Fixture::new(80.0);
+    // A single candidate is its own busiest segment, so it has no headroom.
+    let orchestrator = fixture.orchestrator().with_fair_share(FairShareAllocator::new(
+        FairShareConfig {
+            base_burst_flops: 1e15,
+            ..FairShareConfig::default()
+        },
+    ));
+
+    // Priority 0.8 and no recent usage: weight 1.8, bursts up to 1.8e15.
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let decision = plan.fair_share.clone().unwrap();
+    assert!(decision.contended && decision.within_burst);
+    assert!((decision.weight - 1.8).abs() < 1e-6);
+    assert!(plan.deferral.is_none());
+
+    orchestrator.start_job("token-alice", &plan.reservation_id).await.unwrap();
+    orchestrator
+        .finish_job("token-alice", &plan.reservation_id, Some(1e15))
+        .await
+        .unwrap();
+    let receipt = orchestrator.receipt("token-alice", &plan.reservation_id).await.unwrap();
+    assert_eq!(receipt.receipt.fair_share.unwrap().explanation, decision.explanation);
+
+    // The finished job now counts as recent usage, and a job over the burst
+    // allowance waits for the scheduler instead of starting.
+    let mut big = job(CapabilityTier::Tier1);
+    big.expected_flops = 2e15;
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), big, None, None)
+        .await
+        .unwrap();
+    let decision = plan.fair_share.unwrap();
+    assert!(decision.recent_usage_flops > 0.99e15);
+    assert!(decision.weight < 1.8 && !decision.within_burst);
+    assert!(decision.explanation.contains("burst allowance"));
+    assert_eq!(plan.deferral.unwrap().reason, decision.explanation);
+    assert_eq!(fixture.quota.reservations()[1].state, ReservationState::Pending);
+
+    // After a restart the recent usage is rebuilt from the stored receipts.
+    let restarted = fixture.orchestrator().with_fair_share(FairShareAllocator::new(
+        FairShareConfig {
+            base_burst_flops: 1e15,
+            ..FairShareConfig::default()
+        },
+    ));
+    let plan = restarted
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+        .await
+        .unwrap();
+    let rebuilt = plan.fair_share.unwrap();
+    assert!((rebuilt.recent_usage_flops - decision.recent_usage_flops).abs() < 1e12);
+}
+
+#[tokio::test]
+async fn grid_stress_preempts_running_work_until_it_eases() {
+    let fixture = Fixture::new(80.0);
+    let executor = RecordingPreemptionExecutor::new();
+    let orchestrator = fixture.orchestrator().with_preemption(PreemptionController::new(
+        PreemptionPolicy::default(),
+        std::sync::Arc::new(executor.clone()),
+    ));
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier2), None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.start_job("token-alice", id).await.unwrap();
+
+    // The first reading is the baseline; nothing is stressed.
+    orchestrator.run_preemption_once().await.unwrap();
+    assert!(executor.signals().is_empty());
+
+    // Renewables fall 30 points: the Tier2 job drops to Tier1 and keeps going,
+    // and its hold shrinks to the Tier1 estimate.
+    let held = fixture.quota.reservations()[0].clone();
+    fixture.telemetry.set_load(load(50.0));
+    orchestrator.run_preemption_once().await.unwrap();
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Running);
+    assert_eq!(view.job.plan.approved_tier, CapabilityTier::Tier1);
+    assert_eq!(
+        executor.signals()[0].1,
+        PreemptionSignal::Downgrade {
+            tier: CapabilityTier::Tier1
+        }
+    );
+    let shrunk = fixture.quota.reservations()[0].clone();
+    assert_eq!(shrunk.tier, CapabilityTier::Tier1);
+    assert!((shrunk.expected_energy_kwh - held.expected_energy_kwh / 1.1).abs() < 1e-9);
+    assert!(shrunk.expected_carbon_kg <= held.expected_carbon_kg);
+    assert_eq!(view.job.plan.estimated_energy_kwh, shrunk.expected_energy_kwh);
+
+    // The thermal margin collapses: the job is suspended and keeps its hold.
+    fixture.telemetry.set_load(SegmentLoad {
+        thermal_margin_pct: 5.0,
+        ..load(50.0)
+    });
+    orchestrator.run_preemption_once().await.unwrap();
+    assert_eq!(executor.signals()[1].1, PreemptionSignal::Suspend);
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Suspended);
+    assert_eq!(view.reservation_state, ReservationState::Active);
+    assert!(view.job.receipt.is_none());
+
+    let events = fixture.logger.events();
+    let checks: Vec<_> = events
+        .iter()
+        .filter(|e| {
+            matches!(e.event_type, LogEventType::StabilityChecked)
+                && e.metadata["preemption"].is_object()
+        })
+        .collect();
+    assert_eq!(checks.len(), 2);
+    assert!(checks[0].metadata["rationale"].as_str().unwrap().contains("renewable share"));
+    let refunded = checks[0].metadata["refunded"]["energy_kwh"].as_f64().unwrap();
+    assert!((refunded - (held.expected_energy_kwh - shrunk.expected_energy_kwh)).abs() < 1e-9);
+    assert_eq!(checks[1].metadata["preemption"]["action"], "suspend");
+
+    // Still stressed: the job stays suspended.
+    orchestrator.run_preemption_once().await.unwrap();
+    assert_eq!(executor.signals().len(), 2);
+
+    // The segment recovers and the job picks up where it left off.
+    fixture.telemetry.set_load(load(80.0));
+    orchestrator.run_preemption_once().await.unwrap();
+    assert_eq!(executor.signals()[2].1, PreemptionSignal::Resume);
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Running);
+    let last = fixture.logger.events().pop().unwrap();
+    assert_eq!(last.metadata["preemption"]["action"], "resume");
+    assert!(last.metadata.get("error").is_none());
+
+    // A suspended job can still be cancelled, and is charged like a running one.
+    fixture.telemetry.set_load(SegmentLoad {
+        thermal_margin_pct: 5.0,
+        ..load(80.0)
+    });
+    orchestrator.run_preemption_once().await.unwrap();
+    assert_eq!(executor.signals()[3].1, PreemptionSignal::Suspend);
+    orchestrator.cancel_job("token-alice", id, "gave up".into(), None).await.unwrap();
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Cancelled);
+    assert_eq!(view.reservation_state, ReservationState::Committed);
+}
+
+#[test]
+fn recent_usage_decays_and_deferred_jobs_queue_by_weight() {
+    let allocator = FairShareAllocator::new(FairShareConfig {
+        half_life: Duration::from_secs(3600),
+        ..FairShareConfig::default()
+    });
+    let alice = actor().actor_id;
+    let t0 = SystemTime::now();
+    allocator.record_usage(&alice, 8e15, t0);
+    let later = t0 + Duration::from_secs(3600);
+    assert!((allocator.recent_usage(&alice, later) - 4e15).abs() < 1e6);
+    allocator.record_usage(&alice, 1e15, later);
+    let decayed = allocator.recent_usage(&alice, later + Duration::from_secs(7200));
+    assert!((decayed - 1.25e15).abs() < 1e6);
+
+    // Replaying the same usage out of order, as when it is rebuilt after a
+    // restart, lands on the same figure.
+    let replayed = FairShareAllocator::new(allocator.config().clone());
+    replayed.record_usage(&alice, 1e15, later);
+    replayed.record_usage(&alice, 8e15, t0);
+    let decayed = replayed.recent_usage(&alice, later + Duration::from_secs(7200));
+    assert!((decayed - 1.25e15).abs() < 1e6);
+
+    let scheduler = DeferredScheduler::default();
+    let deferred = |n: u128, priority: f64| DeferredJob {
+        reservation_id: ReservationId(uuid::Uuid::from_u128(n)),
+        actor_id: alice.clone(),
+        segment_id: SegmentId("segment_climate_hpc".into()),
+        window_id: window(),
+        tier: CapabilityTier::Tier1,
+        enqueued_at: t0,
+        not_before: t0,
+        deadline: t0 + Duration::from_secs(3600),
+        reason: "contended".into(),
+        priority,
+    };
+    scheduler.enqueue(deferred(1, 1.0));
+    scheduler.enqueue(deferred(2, 1.5));
+    let third = scheduler.enqueue(deferred(3, 1.0));
+    let position = |n| {
+        scheduler
+            .status(&ReservationId(uuid::Uuid::from_u128(n)))
+            .unwrap()
+            .queue_position
+    };
+    assert_eq!((position(2), position(1), third.queue_position), (0, 1, 2));
+}
+
+#[test]
+fn power_is_integrated_linearly_between_samples() {
+    let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let sample = |secs: u64, power_kw: f64| PowerSample {
+        at: t0 + Duration::from_secs(secs),
+        power_kw,
+        renewable_share_pct: 100.0,
+        current_flops: 0.0,
+    };
+    // 0 -> 60 kW over an hour, then flat past the last sample.
+    let samples = [sample(0, 0.0), sample(3600, 60.0)];
+    let integral = integrate(
+        &samples,
+        t0 + Duration::from_secs(1800),
+        t0 + Duration::from_secs(5400),
+        &EnergyEstimator::default(),
+    )
+    .unwrap();
+    // half an hour ramping 30 -> 60 kW, then half an hour at 60 kW
+    assert!((integral.energy_kwh - (22.5 + 30.0)).abs() < 1e-9);
+    assert!((integral.carbon_kg - 52.5 * 0.03).abs() < 1e-9);
+}
+
+async fn wait_until_finished(executor: &LocalProcessExecutor, id: &ReservationId) {
+    for _ in 0..500 {
+        if executor.status(id).await.unwrap().state.is_finished() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("execution {} did not finish", id.0);
+}
+
+#[tokio::test]
+async fn launched_jobs_are_started_and_settled_by_the_executor() {
+    let fixture = Fixture::new(80.0);
+    let executor = LocalProcessExecutor::new()
+        .with_limits(
+            CapabilityTier::Tier1,
+            TierLimits {
+                cpu_secs: 5,
+                wall_time: Duration::from_secs(1),
+            },
+        )
+        .with_allowed_program("true")
+        .with_allowed_program("sleep");
+    let orchestrator = fixture
+        .orchestrator()
+        .with_executor(std::sync::Arc::new(executor.clone()));
+    let command = |program: &str, args: &[&str]| LaunchCommand {
+        program: program.into(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+    };
+    let plan_one = || {
+        orchestrator.plan_job("token-alice", Some(window()), job(CapabilityTier::Tier1), None, None)
+    };
+
+    // Programs outside the allow-list never start, and the job stays planned.
+    let plan = plan_one().await.unwrap();
+    let id = &plan.reservation_id;
+    let err = orchestrator
+        .launch_job("token-alice", id, command("rm", &["-rf", "/tmp/nothing"]))
+        .await
+        .unwrap_err();
+    assert!(matches!(OrchestratorError::find(&err), Some(OrchestratorError::Forbidden(_))));
+    assert_eq!(orchestrator.plan("token-alice", id).await.unwrap().job.status, JobStatus::Planned);
+
+    // A successful run is started and completed by the executor alone.
+    let started = orchestrator.launch_job("token-alice", id, command("true", &[])).await.unwrap();
+    assert_eq!(started.status, JobStatus::Running);
+    wait_until_finished(&executor, id).await;
+    assert_eq!(executor.status(id).await.unwrap().state, ExecutionState::Succeeded);
+    orchestrator.run_executor_once().await.unwrap();
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Completed);
+    assert_eq!(view.reservation_state, ReservationState::Committed);
+    let events: Vec<_> = fixture.logger.events().into_iter().map(|e| e.event_type).collect();
+    assert!(matches!(
+        events.as_slice(),
+        [.., LogEventType::JobStarted, LogEventType::JobCompleted]
+    ));
+
+    // A run past the tier's wall-clock limit is killed, then cancelled and
+    // charged for the part of its time limit it used. The settled run above
+    // is forgotten once the next one is submitted.
+    let settled = id.clone();
+    let plan = plan_one().await.unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.launch_job("token-alice", id, command("sleep", &["30"])).await.unwrap();
+    let err = executor.status(&settled).await.unwrap_err();
+    assert!(matches!(OrchestratorError::find(&err), Some(OrchestratorError::NotFound(_))));
+    executor.suspend(id).await.unwrap();
+    assert_eq!(executor.status(id).await.unwrap().state, ExecutionState::Suspended);
+    executor.resume(id).await.unwrap();
+    orchestrator.run_executor_once().await.unwrap();
+    assert_eq!(orchestrator.plan("token-alice", id).await.unwrap().job.status, JobStatus::Running);
+    wait_until_finished(&executor, id).await;
+    orchestrator.run_executor_once().await.unwrap();
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Cancelled);
+    assert!(view.job.receipt.unwrap().receipt.flops_used < 1e13);
+    let last = fixture.logger.events().pop().unwrap();
+    assert!(matches!(last.event_type, LogEventType::JobCancelled));
+    assert!(last.metadata["reason"].as_str().unwrap().contains("wall-clock limit"));
+
+    // Cancelling through the orchestrator stops the process.
+    let plan = plan_one().await.unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.launch_job("token-alice", id, command("sleep", &["30"])).await.unwrap();
+    orchestrator.cancel_job("token-alice", id, "not needed".into(), None).await.unwrap();
+    wait_until_finished(&executor, id).await;
+    assert_eq!(executor.status(id).await.unwrap().state, ExecutionState::Cancelled);
+
+    // A launched job the executor has lost track of, as after a restart, is
+    // cancelled and charged rather than left running.
+    let plan = plan_one().await.unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.launch_job("token-alice", id, command("sleep", &["30"])).await.unwrap();
+    let restarted = fixture.orchestrator().with_executor(std::sync::Arc::new(
+        LocalProcessExecutor::new().with_allowed_program("sleep"),
+    ));
+    restarted.run_executor_once().await.unwrap();
+    let view = restarted.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Cancelled);
+    assert_eq!(view.reservation_state, ReservationState::Committed);
+    let last = fixture.logger.events().pop().unwrap();
+    assert!(matches!(last.event_type, LogEventType::JobCancelled));
+    assert!(last.metadata["reason"].as_str().unwrap().contains("no record"));
+    executor.cancel(id).await.unwrap();
+
+    // Jobs started by hand are not the executor's to settle.
+    let plan = plan_one().await.unwrap();
+    let id = &plan.reservation_id;
+    orchestrator.start_job("token-alice", id).await.unwrap();
+    orchestrator.run_executor_once().await.unwrap();
+    assert_eq!(orchestrator.plan("token-alice", id).await.unwrap().job.status, JobStatus::Running);
+}
+
+#[tokio::test]
+async fn renewable_stress_suspends_processes_that_cannot_change_tier() {
+    let fixture = Fixture::new(80.0);
+    let executor = LocalProcessExecutor::new()
+        .with_limits(
+            CapabilityTier::Tier2,
+            TierLimits {
+                cpu_secs: 5,
+                wall_time: Duration::from_secs(30),
+            },
+        )
+        .with_allowed_program("sleep");
+    let orchestrator = fixture
+        .orchestrator()
+        .with_executor(std::sync::Arc::new(executor.clone()))
+        .with_preemption(PreemptionController::new(
+            PreemptionPolicy::default(),
+            std::sync::Arc::new(ExecutorPreemption(executor.clone())),
+        ));
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window()), job(CapabilityTier::Tier2), None, None)
+        .await
+        .unwrap();
+    let id = &plan.reservation_id;
+    let command = LaunchCommand {
+        program: "sleep".into(),
+        args: vec!["30".into()],
+    };
+    orchestrator.launch_job("token-alice", id, command).await.unwrap();
+    orchestrator.run_preemption_once().await.unwrap();
+
+    // The process cannot drop to Tier1, so it is stopped instead.
+    fixture.telemetry.set_load(load(50.0));
+    orchestrator.run_preemption_once().await.unwrap();
+    assert_eq!(executor.status(id).await.unwrap().state, ExecutionState::Suspended);
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Suspended);
+    assert_eq!(view.job.plan.approved_tier, CapabilityTier::Tier2);
+    assert_eq!(view.reservation_state, ReservationState::Active);
+
+    let check = fixture
+        .logger
+        .events()
+        .into_iter()
+        .find(|e| e.metadata["preemption"].is_object())
+        .unwrap();
+    assert_eq!(check.metadata["preemption"]["action"], "suspend");
+    assert!(check.metadata["downgrade_refused"].is_string());
+    assert!(check.metadata.get("error").is_none());
+
+    // The share holds steady above its floor on the next reading, so the
+    // process carries on.
+    orchestrator.run_preemption_once().await.unwrap();
+    let actions: Vec<_> = fixture
+        .logger
+        .events()
+        .into_iter()
+        .filter(|e| e.metadata["preemption"].is_object())
+        .map(|e| e.metadata["preemption"]["action"].clone())
+        .collect();
+    assert_eq!(actions, ["suspend", "resume"]);
+    assert_eq!(executor.status(id).await.unwrap().state, ExecutionState::Running);
+    let view = orchestrator.plan("token-alice", id).await.unwrap();
+    assert_eq!(view.job.status, JobStatus::Running);
+    executor.cancel(id).await.unwrap();
+}
@@ -0,0 +1,202 @@
+//! Rule matching, document formats, validation and hot reload for the
+//! declarative policy engine.
+
+use ecological_orchestrator::eol::identity::ActorProfile;
+use ecological_orchestrator::eol::policy::PolicyEngine;
+use ecological_orchestrator::eol::policy_rules::{
+    IssueSeverity, PolicyDocument, PolicyFormat, RuleBasedPolicyEngine,
+};
+use ecological_orchestrator::eol::types::{ActorId, CapabilityTier, EcologicalJobSpec};
+use std::path::Path;
+use std::time::Duration;
+
+const RULES: &str = r#"
+[defaults]
+risk_score = 0.1
+
+[[rules]]
+name = "large_jobs"
+when = { min_flops = 1e18 }
+then = { risk_score = 0.5, allowed_tiers = ["Tier1", "Tier2"], note = "cap tier for very large runs" }
+
+[[rules]]
+name = "untrained_staff"
+when = { max_clearance = 1, purpose_keywords = ["aerosol"] }
+then = { risk_score = 0.7, requires_human_approval = true, min_reviewer_clearance = 3 }
+"#;
+
+fn actor(clearance_level: u8) -> ActorProfile {
+    ActorProfile {
+        actor_id: ActorId("did:example:alice".into()),
+        roles: vec!["climate_lab".into()],
+        clearance_level,
+        ecological_priority_score: 0.8,
+    }
+}
+
+fn job(expected_flops: f64, purpose: &str) -> EcologicalJobSpec {
+    EcologicalJobSpec {
+        actor_id: actor(1).actor_id,
+        segment_hint: None,
+        requested_tier: CapabilityTier::Tier2,
+        expected_flops,
+        max_duration: Duration::from_secs(3600),
+        purpose: purpose.into(),
+        domain_tags: vec!["climate".into()],
+        required_capabilities: Vec::new(),
+        deadline: None,
+    }
+}
+
+#[test]
+fn matching_rules_combine_into_one_decision() {
+    let document = PolicyDocument::parse(RULES, PolicyFormat::Toml).unwrap();
+
+    let quiet = document.evaluate(&job(1e15, "watershed runoff"), &actor(1));
+    assert_eq!(quiet.risk_score, 0.1);
+    assert_eq!(quiet.allowed_tiers.len(), 3);
+    assert!(quiet.notes.is_empty());
+
+    let both = document.evaluate(&job(5e18, "Stratospheric AEROSOL dispersion"), &actor(1));
+    assert_eq!(both.risk_score, 0.7);
+    assert_eq!(both.allowed_tiers, vec![CapabilityTier::Tier1, CapabilityTier::Tier2]);
+    assert!(both.requires_human_approval);
+    assert_eq!((both.required_approvals, both.min_reviewer_clearance), (1, 3));
+    assert_eq!(both.notes[0], "large_jobs: cap tier for very large runs");
+
+    // Actor attributes take part in matching.
+    let cleared = document.evaluate(&job(1e15, "aerosol dispersion"), &actor(3));
+    assert!(!cleared.requires_human_approval);
+}
+
+#[test]
+fn toml_json_and_yaml_documents_are_equivalent() {
+    let toml = PolicyDocument::parse(RULES, PolicyFormat::Toml).unwrap();
+    let json = serde_json::to_string(&toml).unwrap();
+    let yaml = serde_yaml::to_string(&toml).unwrap();
+
+    let spec = job(5e18, "aerosol");
+    let expected = toml.evaluate(&spec, &actor(1));
+    for (text, format) in [(json, PolicyFormat::Json), (yaml, PolicyFormat::Yaml)] {
+        let document = PolicyDocument::parse(&text, format).unwrap();
+        assert_eq!(document.evaluate(&spec, &actor(1)), expected);
+    }
+}
+
+#[test]
+fn shipped_policy_is_clean() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("policies/default.toml");
+    let document = PolicyDocument::load(&path).unwrap();
+    assert!(document.validate().is_empty());
+
+    let mut spec = job(1e15, "solar radiation management");
+    spec.domain_tags.push("geoengineering".into());
+    let decision = document.evaluate(&spec, &actor(3));
+    assert!(decision.requires_human_approval);
+    assert_eq!(decision.required_approvals, 2);
+}
+
+#[test]
+fn validation_reports_conflicts_and_errors() {
+    let document = PolicyDocument::parse(
+        r#"
+        [[rules]]
+        name = "hpc_only"
+        when = { domain_tags_any = ["climate"] }
+        then = { allowed_tiers = ["Tier3"] }
+
+        [[rules]]
+        name = "small_only"
+        when = { roles_any = ["climate_lab"] }
+        then = { allowed_tiers = ["Tier1"] }
+
+        [[rules]]
+        name = "separate_sizes"
+        when = { max_flops = 1e12 }
+        then = { allowed_tiers = ["Tier2"] }
+
+        [[rules]]
+        name = "separate_sizes_too"
+        when = { min_flops = 1e15 }
+        then = { allowed_tiers = ["Tier1"] }
+
+        [[rules]]
+        name = "small_only"
+        when = { min_clearance = 4, max_clearance = 2 }
+        then = { risk_score = 1.5 }
+        "#,
+        PolicyFormat::Toml,
+    )
+    .unwrap();
+
+    let issues = document.validate();
+    let has = |severity, rules: &[&str], needle: &str| {
+        issues.iter().any(|i| {
+            i.severity == severity && i.rules == rules && i.message.contains(needle)
+        })
+    };
+    assert!(has(IssueSeverity::Conflict, &["hpc_only", "small_only"], "disjoint tiers"));
+    assert!(has(IssueSeverity::Error, &["small_only"], "duplicate"));
+    assert!(has(IssueSeverity::Error, &["small_only"], "risk_score"));
+    assert!(has(IssueSeverity::Conflict, &["small_only"], "never match"));
+    // Size ranges that cannot overlap do not conflict.
+    assert!(!has(
+        IssueSeverity::Conflict,
+        &["separate_sizes", "separate_sizes_too"],
+        "disjoint tiers"
+    ));
+
+    assert!(RuleBasedPolicyEngine::new(document).is_err());
+}
+
+#[tokio::test]
+async fn reload_swaps_rules_and_keeps_them_on_bad_edits() {
+    let path = std::env::temp_dir().join(format!("eol-policy-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, RULES).unwrap();
+    let engine = RuleBasedPolicyEngine::from_file(&path).unwrap();
+    let spec = job(5e18, "watershed");
+
+    assert!(!engine.reload().unwrap(), "unchanged file is not re-read");
+    assert_eq!(engine.evaluate(&spec, &actor(3)).await.unwrap().risk_score, 0.5);
+
+    // mtime granularity can be coarse; make sure the edit is visible.
+    std::thread::sleep(Duration::from_millis(20));
+    std::fs::write(&path, RULES.replace("risk_score = 0.5", "risk_score = 0.6")).unwrap();
+    assert!(engine.reload().unwrap());
+    assert_eq!(engine.evaluate(&spec, &actor(3)).await.unwrap().risk_score, 0.6);
+
+    std::thread::sleep(Duration::from_millis(20));
+    std::fs::write(&path, RULES.replace("risk_score = 0.5", "risk_score = 5.0")).unwrap();
+    assert!(engine.reload().is_err());
+    assert!(!engine.reload().unwrap(), "a bad edit is reported once");
+    assert_eq!(engine.evaluate(&spec, &actor(3)).await.unwrap().risk_score, 0.6);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn hot_reload_picks_up_edits_in_the_background() {
+    let path = std::env::temp_dir().join(format!("eol-policy-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, RULES).unwrap();
+    let engine = std::sync::Arc::new(RuleBasedPolicyEngine::from_file(&path).unwrap());
+    let reloader = engine.clone().spawn_hot_reload(Duration::from_millis(10));
+    let spec = job(5e18, "watershed");
+
+    // Push the mtime forward so the edit is visible however coarse the
+    // filesystem's timestamps are.
+    std::fs::write(&path, RULES.replace("risk_score = 0.5", "risk_score = 0.6")).unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while engine.evaluate(&spec, &actor(3)).await.unwrap().risk_score != 0.6 {
+        assert!(tokio::time::Instant::now() < deadline, "edit was never picked up");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    reloader.abort();
+
+    std::fs::remove_file(&path).unwrap();
+}
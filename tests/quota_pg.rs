@@ -0,0 +1,360 @@
+//! Concurrency and lifecycle checks for `PgQuotaStore`. These need a scratch Postgres
+//! database; set `EOL_TEST_DATABASE_URL` to run them, otherwise they are
+//! skipped. Each run creates (and drops) its own schema.
+
+use deadpool_postgres::{Config, Pool, Runtime};
+use ecological_orchestrator::eol::quota::{
+    QuotaService, QuotaStore, ReservationActuals, ReservationState,
+};
+use ecological_orchestrator::eol::error::OrchestratorError;
+use ecological_orchestrator::eol::types::{
+    ActorId, BudgetLevel, CapabilityTier, EcologicalJobSpec, UsageWindowId,
+};
+use ecological_orchestrator::storage::quota_pg::PgQuotaStore;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio_postgres::NoTls;
+
+const SCHEMA: &str = "
+    CREATE TABLE eol_allowances (
+        actor_id TEXT NOT NULL,
+        window_id TEXT NOT NULL,
+        max_flops DOUBLE PRECISION NOT NULL,
+        max_energy_kwh DOUBLE PRECISION NOT NULL,
+        max_carbon_kg DOUBLE PRECISION NOT NULL,
+        max_tier SMALLINT NOT NULL,
+        valid_until TIMESTAMPTZ NOT NULL,
+        PRIMARY KEY (actor_id, window_id)
+    );
+    CREATE TABLE eol_usage (
+        actor_id TEXT NOT NULL,
+        window_id TEXT NOT NULL,
+        flops_used DOUBLE PRECISION NOT NULL DEFAULT 0,
+        energy_kwh_used DOUBLE PRECISION NOT NULL DEFAULT 0,
+        carbon_kg_emitted DOUBLE PRECISION NOT NULL DEFAULT 0,
+        PRIMARY KEY (actor_id, window_id)
+    );
+    CREATE TABLE eol_reservations (
+        id UUID PRIMARY KEY,
+        actor_id TEXT NOT NULL,
+        window_id TEXT NOT NULL,
+        expected_flops DOUBLE PRECISION NOT NULL,
+        expected_energy_kwh DOUBLE PRECISION NOT NULL,
+        expected_carbon_kg DOUBLE PRECISION NOT NULL,
+        tier SMALLINT NOT NULL,
+        max_duration_secs DOUBLE PRECISION NOT NULL,
+        state TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL,
+        expires_at TIMESTAMPTZ NOT NULL
+    );
+    CREATE TABLE eol_projects (
+        project_id TEXT PRIMARY KEY,
+        organisation_id TEXT NOT NULL
+    );
+    CREATE TABLE eol_project_members (
+        actor_id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES eol_projects (project_id)
+    );
+    CREATE TABLE eol_group_allowances (
+        level TEXT NOT NULL,
+        group_id TEXT NOT NULL,
+        window_id TEXT NOT NULL,
+        max_flops DOUBLE PRECISION NOT NULL,
+        max_energy_kwh DOUBLE PRECISION NOT NULL,
+        max_carbon_kg DOUBLE PRECISION NOT NULL,
+        max_tier SMALLINT NOT NULL,
+        valid_until TIMESTAMPTZ NOT NULL,
+        PRIMARY KEY (level, group_id, window_id)
+    );
+";
+
+async fn scratch_pool() -> Option<(Pool, String)> {
+    let url = std::env::var("EOL_TEST_DATABASE_URL").ok()?;
+    let schema = format!("eol_test_{}", uuid::Uuid::new_v4().simple());
+
+    let mut cfg = Config::new();
+    cfg.url = Some(url);
+    cfg.options = Some(format!("-c search_path={}", schema));
+    let pool = cfg.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+
+    let client = pool.get().await.unwrap();
+    client
+        .batch_execute(&format!("CREATE SCHEMA {}; SET search_path = {};", schema, schema))
+        .await
+        .unwrap();
+    client.batch_execute(SCHEMA).await.unwrap();
+    Some((pool, schema))
+}
+
+async fn seed_allowance(pool: &Pool, actor: &ActorId, window: &UsageWindowId, max_flops: f64) {
+    pool.get()
+        .await
+        .unwrap()
+        .execute(
+            "INSERT INTO eol_allowances
+                (actor_id, window_id, max_flops, max_energy_kwh, max_carbon_kg, max_tier, valid_until)
+             VALUES ($1, $2, $3, 1000.0, 1000.0, 3, now() + interval '1 day')",
+            &[&actor.0, &window.0, &max_flops],
+        )
+        .await
+        .unwrap();
+}
+
+async fn drop_schema(pool: &Pool, schema: &str) {
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute(&format!("DROP SCHEMA {} CASCADE", schema))
+        .await
+        .unwrap();
+}
+
+fn job(actor: &ActorId, flops: f64) -> EcologicalJobSpec {
+    EcologicalJobSpec {
+        actor_id: actor.clone(),
+        segment_hint: None,
+        requested_tier: CapabilityTier::Tier1,
+        expected_flops: flops,
+        max_duration: Duration::from_secs(60),
+        purpose: "concurrency test".into(),
+        domain_tags: vec![],
+        required_capabilities: Vec::new(),
+        deadline: None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_reservations_never_overspend_window() {
+    let Some((pool, schema)) = scratch_pool().await else {
+        eprintln!("EOL_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+
+    let actor = ActorId("did:example:hammer".into());
+    let window = UsageWindowId("2026-02-08T00Z_daily".into());
+    seed_allowance(&pool, &actor, &window, 10.0).await;
+
+    let service = Arc::new(QuotaService::new(PgQuotaStore::new(pool.clone())));
+    let tasks: Vec<_> = (0..64)
+        .map(|_| {
+            let service = service.clone();
+            let actor = actor.clone();
+            let window = window.clone();
+            tokio::spawn(async move {
+                service
+                    .check_and_reserve(&actor, &window, &job(&actor, 1.0), 0.1, 0.01)
+                    .await
+                    .is_ok()
+            })
+        })
+        .collect();
+
+    let mut granted = 0;
+    for task in tasks {
+        if task.await.unwrap() {
+            granted += 1;
+        }
+    }
+
+    let usage = service.store().get_usage(&actor, &window).await.unwrap();
+    drop_schema(&pool, &schema).await;
+
+    assert_eq!(granted, 10);
+    assert!(usage.flops_reserved <= 10.0);
+}
+
+#[tokio::test]
+async fn reservations_commit_release_and_expire() {
+    let Some((pool, schema)) = scratch_pool().await else {
+        eprintln!("EOL_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+
+    let actor = ActorId("did:example:lifecycle".into());
+    let window = UsageWindowId("2026-02-08T00Z_daily".into());
+    seed_allowance(&pool, &actor, &window, 10.0).await;
+    let store = PgQuotaStore::new(pool.clone());
+
+    let committed = store
+        .reserve_quota(&actor, &window, &job(&actor, 4.0), 0.1, 0.01)
+        .await
+        .unwrap();
+    let released = store
+        .reserve_quota(&actor, &window, &job(&actor, 3.0), 0.1, 0.01)
+        .await
+        .unwrap();
+    let usage = store.get_usage(&actor, &window).await.unwrap();
+    assert_eq!(usage.flops_reserved, 7.0);
+    let outstanding = store.outstanding_reservations(&actor, &window).await.unwrap();
+    assert_eq!(outstanding.len(), 2);
+    assert_eq!(outstanding[0].id.0, committed.0);
+
+    // Pending holds must be activated before they can be committed.
+    assert!(store
+        .commit_usage(&committed, &ReservationActuals {
+            flops_used: 3.5,
+            energy_kwh_used: 0.1,
+            carbon_kg_emitted: 0.01,
+        })
+        .await
+        .is_err());
+    store.activate(&committed).await.unwrap();
+    store
+        .commit_usage(&committed, &ReservationActuals {
+            flops_used: 3.5,
+            energy_kwh_used: 0.1,
+            carbon_kg_emitted: 0.01,
+        })
+        .await
+        .unwrap();
+    store.release(&released).await.unwrap();
+    assert!(store.release(&released).await.is_err());
+
+    let usage = store.get_usage(&actor, &window).await.unwrap();
+    assert_eq!(usage.flops_used, 3.5);
+    assert_eq!(usage.flops_reserved, 0.0);
+
+    let stale = store
+        .reserve_quota(&actor, &window, &job(&actor, 1.0), 0.1, 0.01)
+        .await
+        .unwrap();
+    // A hold extended for review outlives the sweep; only pending holds extend.
+    let held = store
+        .reserve_quota(&actor, &window, &job(&actor, 1.0), 0.1, 0.01)
+        .await
+        .unwrap();
+    let review_ends = SystemTime::now() + Duration::from_secs(2 * 3600);
+    store.extend_hold(&held, review_ends).await.unwrap();
+    let extend_committed = store.extend_hold(&committed, review_ends).await;
+    // A shrunk hold never grows back.
+    store.shrink_hold(&held, &CapabilityTier::Tier1, 0.05, 0.5).await.unwrap();
+    let shrunk = store.get_reservation(&held).await.unwrap();
+    let shrink_committed = store.shrink_hold(&committed, &CapabilityTier::Tier1, 0.0, 0.0).await;
+    let expired = store
+        .expire_reservations(SystemTime::now() + Duration::from_secs(3600))
+        .await
+        .unwrap();
+    let stale_state = store.get_reservation(&stale).await.unwrap().state;
+    let held_state = store.get_reservation(&held).await.unwrap().state;
+    let mut allowance = store.get_allowance(&actor, &window).await.unwrap();
+    allowance.max_flops = 99.0;
+    let kept = store.provision_allowance(&actor, &window, &allowance).await.unwrap();
+    let next_window = UsageWindowId("2026-02-09T00Z_daily".into());
+    let provisioned = store
+        .provision_allowance(&actor, &next_window, &allowance)
+        .await
+        .unwrap();
+    let current_flops = store.get_allowance(&actor, &window).await.unwrap().max_flops;
+    let next_flops = store.find_allowance(&actor, &next_window).await.unwrap().map(|a| a.max_flops);
+    let mut group_provisioned = Vec::new();
+    for max_flops in [50.0, 60.0] {
+        allowance.max_flops = max_flops;
+        group_provisioned.push(
+            store
+                .provision_group_allowance(BudgetLevel::Project, "p", &next_window, &allowance)
+                .await
+                .unwrap(),
+        );
+    }
+    let group_flops = store
+        .find_group_allowance(BudgetLevel::Project, "p", &next_window)
+        .await
+        .unwrap()
+        .map(|a| a.max_flops);
+    drop_schema(&pool, &schema).await;
+
+    assert_eq!(expired.len(), 1);
+    assert_eq!(stale_state, ReservationState::Expired);
+    assert_eq!(held_state, ReservationState::Pending);
+    assert!(extend_committed.is_err());
+    assert_eq!(shrunk.tier, CapabilityTier::Tier1);
+    assert_eq!((shrunk.expected_energy_kwh, shrunk.expected_carbon_kg), (0.05, 0.01));
+    assert!(shrink_committed.is_err());
+    assert!(!kept && provisioned);
+    assert_eq!(current_flops, 10.0);
+    assert_eq!(next_flops, Some(99.0));
+    assert_eq!(group_provisioned, [true, false]);
+    assert_eq!(group_flops, Some(50.0));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn project_budget_caps_its_members_together() {
+    let Some((pool, schema)) = scratch_pool().await else {
+        eprintln!("EOL_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+
+    let window = UsageWindowId("2026-02-08T00Z_daily".into());
+    let members: Vec<ActorId> = (0..4)
+        .map(|n| ActorId(format!("did:example:member{}", n)))
+        .collect();
+    for actor in &members {
+        seed_allowance(&pool, actor, &window, 10.0).await;
+    }
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute(
+            "INSERT INTO eol_projects VALUES ('watershed', 'basin');
+             INSERT INTO eol_project_members VALUES
+                ('did:example:member0', 'watershed'), ('did:example:member1', 'watershed'),
+                ('did:example:member2', 'watershed'), ('did:example:member3', 'watershed');
+             INSERT INTO eol_group_allowances VALUES
+                ('project', 'watershed', '2026-02-08T00Z_daily', 12.0, 1000.0, 1000.0, 2,
+                 now() + interval '1 day');",
+        )
+        .await
+        .unwrap();
+
+    let service = Arc::new(QuotaService::new(PgQuotaStore::new(pool.clone())));
+    let tasks: Vec<_> = (0..32)
+        .map(|n| {
+            let service = service.clone();
+            let actor = members[n % members.len()].clone();
+            let window = window.clone();
+            tokio::spawn(async move {
+                service
+                    .check_and_reserve(&actor, &window, &job(&actor, 1.0), 0.1, 0.01)
+                    .await
+                    .is_ok()
+            })
+        })
+        .collect();
+    let mut granted = 0;
+    for task in tasks {
+        if task.await.unwrap() {
+            granted += 1;
+        }
+    }
+
+    let mut tier3 = job(&members[0], 0.0);
+    tier3.requested_tier = CapabilityTier::Tier3;
+    let tier_err = service
+        .check_and_reserve(&members[0], &window, &tier3, 0.0, 0.0)
+        .await
+        .unwrap_err();
+    let quota_err = service
+        .check_and_reserve(&members[0], &window, &job(&members[0], 1.0), 0.1, 0.01)
+        .await
+        .unwrap_err();
+    let report = service
+        .budget_report(BudgetLevel::Organisation, "basin", &window)
+        .await
+        .unwrap();
+    drop_schema(&pool, &schema).await;
+
+    assert_eq!(granted, 12);
+    assert!(matches!(
+        OrchestratorError::find(&tier_err),
+        Some(OrchestratorError::TierNotAllowed { .. })
+    ));
+    assert!(matches!(
+        OrchestratorError::find(&quota_err),
+        Some(OrchestratorError::QuotaExceeded { level: BudgetLevel::Project, .. })
+    ));
+    assert!(report.allowance.is_none());
+    assert_eq!(report.usage.flops_reserved, 12.0);
+    assert_eq!(report.children.len(), 1);
+    assert_eq!(report.children[0].remaining.as_ref().unwrap().flops, 0.0);
+    assert_eq!(report.children[0].children.len(), 4);
+}
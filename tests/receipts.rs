@@ -0,0 +1,107 @@
+//! Signing and offline verification of FairUseReceipts.
+
+use ecological_orchestrator::eol::receipts::{
+    canonical_json, verify_receipt, ReceiptSigner, SignedReceipt,
+};
+use ecological_orchestrator::eol::types::{
+    ActorId, FairUseReceipt, ReservationId, SegmentId, UsageWindowId,
+};
+use std::process::Command;
+
+const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+const NEXT_SEED: &str = "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb";
+
+fn receipt() -> FairUseReceipt {
+    FairUseReceipt {
+        reservation_id: ReservationId(uuid::Uuid::from_u128(7)),
+        actor_id: ActorId("did:example:alice".into()),
+        segment_id: SegmentId("segment_climate_hpc".into()),
+        flops_used: 9.5e14,
+        energy_kwh_used: 11.7,
+        carbon_kg_emitted: 0.41,
+        window_id: UsageWindowId("2026-02-08T00Z_daily".into()),
+        allowance_remaining_flops: 9.05e15,
+        allowance_remaining_energy_kwh: 488.3,
+        allowance_remaining_carbon_kg: 99.59,
+        explanation: "within budget".into(),
+        fair_share: None,
+    }
+}
+
+#[test]
+fn signed_receipt_verifies_and_detects_edits() {
+    let signer = ReceiptSigner::from_seed_hex(SEED).unwrap();
+    let signed = signer.sign(receipt()).unwrap();
+    let keys = signer.public_keys();
+    verify_receipt(&signed, &keys).unwrap();
+
+    // Survives a JSON round trip, as it would when handed to an actor.
+    let copy: SignedReceipt =
+        serde_json::from_str(&serde_json::to_string_pretty(&signed).unwrap()).unwrap();
+    verify_receipt(&copy, &keys).unwrap();
+
+    let mut altered = signed.clone();
+    altered.receipt.energy_kwh_used = 1.7;
+    assert!(verify_receipt(&altered, &keys).is_err());
+
+    let mut backdated = signed.clone();
+    backdated.issued_at -= std::time::Duration::from_secs(86_400);
+    assert!(verify_receipt(&backdated, &keys).is_err());
+
+    let other = ReceiptSigner::from_seed_hex(NEXT_SEED).unwrap();
+    assert!(verify_receipt(&signed, &other.public_keys()).is_err());
+}
+
+#[test]
+fn retired_keys_keep_old_receipts_verifiable() {
+    let old = ReceiptSigner::from_seed_hex(SEED).unwrap();
+    let old_receipt = old.sign(receipt()).unwrap();
+
+    let current = ReceiptSigner::from_seed_hex(NEXT_SEED)
+        .unwrap()
+        .with_retired_key(old.public_keys().remove(0));
+    let keys = current.public_keys();
+    assert_eq!(keys[0].key_id, current.key_id());
+    verify_receipt(&old_receipt, &keys).unwrap();
+    verify_receipt(&current.sign(receipt()).unwrap(), &keys).unwrap();
+
+    // A key published under another key's id is refused.
+    let mut mislabelled = keys.clone();
+    mislabelled[1].public_key_hex = keys[0].public_key_hex.clone();
+    assert!(verify_receipt(&old_receipt, &mislabelled).is_err());
+}
+
+#[test]
+fn canonical_json_sorts_keys_without_whitespace() {
+    let value = serde_json::json!({ "b": [1, { "z": true, "a": null }], "a": "x y" });
+    assert_eq!(
+        canonical_json(&value).unwrap(),
+        r#"{"a":"x y","b":[1,{"a":null,"z":true}]}"#
+    );
+}
+
+#[test]
+fn cli_reports_authentic_and_altered_receipts() {
+    let signer = ReceiptSigner::from_seed_hex(SEED).unwrap();
+    let dir = std::env::temp_dir().join(format!("eol-receipts-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let keys_path = dir.join("keys.json");
+    let receipt_path = dir.join("receipt.json");
+    std::fs::write(&keys_path, serde_json::to_string(&signer.public_keys()).unwrap()).unwrap();
+
+    let mut signed = signer.sign(receipt()).unwrap();
+    let run = |signed: &SignedReceipt| {
+        std::fs::write(&receipt_path, serde_json::to_string(signed).unwrap()).unwrap();
+        Command::new(env!("CARGO_BIN_EXE_eol-receipt-verify"))
+            .arg(&receipt_path)
+            .arg(&keys_path)
+            .status()
+            .unwrap()
+            .code()
+    };
+    assert_eq!(run(&signed), Some(0));
+    signed.receipt.carbon_kg_emitted = 0.0;
+    assert_eq!(run(&signed), Some(1));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
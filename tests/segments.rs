@@ -0,0 +1,329 @@
+//! The segment registry: loading it from config or Postgres, and resolving
+//! actors and their jobs against it. The Postgres check needs
+//! `EOL_TEST_DATABASE_URL` and is skipped without it.
+
+use deadpool_postgres::{Config, Runtime};
+use ecological_orchestrator::eol::clearance::ClearancePolicy;
+use ecological_orchestrator::eol::energy::StabilityGuard;
+use ecological_orchestrator::eol::error::OrchestratorError;
+use ecological_orchestrator::eol::identity::{ActorProfile, ZoneResolver};
+use ecological_orchestrator::eol::logging::LogEventType;
+use ecological_orchestrator::eol::orchestrator::EcologicalOrchestrator;
+use ecological_orchestrator::eol::policy::SimplePolicyEngine;
+use ecological_orchestrator::eol::policy_rules::PolicyFormat;
+use ecological_orchestrator::eol::segments::{RegistryZoneResolver, SegmentRegistry};
+use ecological_orchestrator::eol::testing::{
+    InMemoryApprovalStore, InMemoryJobStore, InMemoryLogger, InMemoryQuotaStore,
+    StaticIdentityResolver, StaticTelemetry,
+};
+use ecological_orchestrator::eol::types::{
+    ActorId, CapabilityTier, ComputeEnergyAllowance, EcologicalJobSpec, SegmentId, SegmentLoad,
+    UsageWindowId,
+};
+use ecological_orchestrator::storage::segments_pg::PgSegmentRegistryStore;
+use std::time::{Duration, SystemTime};
+use tokio_postgres::NoTls;
+
+const REGISTRY: &str = r#"
+[[segments]]
+segment_id = "segment_climate_hpc"
+capabilities = ["hpc", "gpu"]
+max_tier = "Tier3"
+required_clearance = 3
+trust_level = 4
+allowed_domain_tags = ["climate", "watershed", "hydrology"]
+roles = ["climate_lab"]
+
+[[segments]]
+segment_id = "segment_general_research"
+capabilities = ["cpu"]
+max_tier = "Tier2"
+trust_level = 2
+roles = ["*"]
+
+[[segments]]
+segment_id = "segment_ethics_vault"
+max_tier = "Tier1"
+required_clearance = 5
+trust_level = 5
+roles = ["ethics_board"]
+"#;
+
+fn registry() -> SegmentRegistry {
+    SegmentRegistry::parse(REGISTRY, PolicyFormat::Toml).unwrap()
+}
+
+fn actor(roles: &[&str], clearance_level: u8) -> ActorProfile {
+    ActorProfile {
+        actor_id: ActorId("did:example:alice".into()),
+        roles: roles.iter().map(|r| r.to_string()).collect(),
+        clearance_level,
+        ecological_priority_score: 0.8,
+    }
+}
+
+fn job(tier: CapabilityTier, tags: &[&str]) -> EcologicalJobSpec {
+    EcologicalJobSpec {
+        actor_id: ActorId("did:example:alice".into()),
+        segment_hint: None,
+        requested_tier: tier,
+        expected_flops: 1e15,
+        max_duration: Duration::from_secs(3600),
+        purpose: "watershed runoff ensemble".into(),
+        domain_tags: tags.iter().map(|t| t.to_string()).collect(),
+        required_capabilities: Vec::new(),
+        deadline: None,
+    }
+}
+
+fn ids(zones: &[ecological_orchestrator::eol::identity::ZoneResolution]) -> Vec<&str> {
+    zones.iter().map(|z| z.segment_id.0.as_str()).collect()
+}
+
+#[test]
+fn registries_load_from_config_and_reject_bad_declarations() {
+    let registry = registry();
+    assert_eq!(registry.segments().len(), 3);
+    let hpc = registry.get(&SegmentId("segment_climate_hpc".into())).unwrap();
+    assert_eq!(hpc.capabilities, ["hpc", "gpu"]);
+    assert_eq!(hpc.trust_level, 4);
+    let general = registry.get(&SegmentId("segment_general_research".into())).unwrap();
+    assert_eq!(general.required_clearance, 0);
+    assert!(general.allowed_domain_tags.is_empty());
+
+    let yaml = "
+segments:
+  - segment_id: segment_edge
+    max_tier: Tier1
+    trust_level: 1
+    roles: ['*']
+";
+    let path = std::env::temp_dir().join(format!("eol-segments-{}.yaml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, yaml).unwrap();
+    assert_eq!(SegmentRegistry::load(&path).unwrap().segments().len(), 1);
+    std::fs::remove_file(&path).unwrap();
+
+    let first = REGISTRY.find("[[segments]]").unwrap();
+    let duplicated = format!("{}\n{}", REGISTRY, &REGISTRY[first..]);
+    let err = SegmentRegistry::parse(&duplicated, PolicyFormat::Toml).unwrap_err();
+    assert!(err.to_string().contains("segment_climate_hpc is declared twice"));
+    let closed = r#"{"segments": [
+        {"segment_id": "segment_x", "max_tier": "Tier1", "trust_level": 1, "roles": []}
+    ]}"#;
+    let err = SegmentRegistry::parse(closed, PolicyFormat::Json).unwrap_err();
+    assert!(err.to_string().contains("segment_x admits no roles"));
+    assert!(SegmentRegistry::parse(r#"{"segments": [{"segment_id": "y"}]}"#, PolicyFormat::Json)
+        .is_err());
+}
+
+#[tokio::test]
+async fn every_eligible_segment_is_returned_with_reasons_for_the_rest() {
+    let resolver = RegistryZoneResolver::new(registry());
+
+    // Segments naming one of the actor's roles come before open ones.
+    let lab = actor(&["climate_lab"], 3);
+    let zones = resolver.resolve_zones(&lab).await.unwrap();
+    assert_eq!(ids(&zones), ["segment_climate_hpc", "segment_general_research"]);
+    assert_eq!(resolver.resolve_zone(&lab).await.unwrap().trust_level, 4);
+
+    let eligibility = resolver
+        .resolve_for_job(&lab, &job(CapabilityTier::Tier3, &["climate", "geoengineering"]))
+        .await
+        .unwrap();
+    assert!(eligibility.eligible.is_empty());
+    let reasons: Vec<(&str, &Vec<String>)> = eligibility
+        .excluded
+        .iter()
+        .map(|e| (e.segment_id.0.as_str(), &e.reasons))
+        .collect();
+    assert_eq!(reasons[0].0, "segment_climate_hpc");
+    assert_eq!(*reasons[0].1, ["does not accept domain tags [geoengineering]"]);
+    assert_eq!(*reasons[1].1, ["runs at most Tier2, the job requests Tier3"]);
+    assert_eq!(reasons[2].0, "segment_ethics_vault");
+    assert_eq!(reasons[2].1.len(), 3);
+    assert!(reasons[2].1[0].contains("admits roles [ethics_board]"));
+    assert_eq!(reasons[2].1[1], "requires clearance 5, the actor has 3");
+
+    // An under-cleared lab member keeps only the open segment.
+    let junior = actor(&["climate_lab"], 2);
+    let eligibility = resolver
+        .resolve_for_job(&junior, &job(CapabilityTier::Tier1, &["climate"]))
+        .await
+        .unwrap();
+    assert_eq!(ids(&eligibility.eligible), ["segment_general_research"]);
+    assert_eq!(
+        eligibility.explain_exclusions(),
+        "segment_climate_hpc: requires clearance 3, the actor has 2 | \
+         segment_ethics_vault: admits roles [ethics_board], the actor has [climate_lab]; \
+         requires clearance 5, the actor has 2"
+    );
+
+    // Jobs needing a capability go only where it is offered.
+    let mut gpu = job(CapabilityTier::Tier1, &["climate"]);
+    gpu.required_capabilities = vec!["gpu".into()];
+    let eligibility = resolver.resolve_for_job(&lab, &gpu).await.unwrap();
+    assert_eq!(ids(&eligibility.eligible), ["segment_climate_hpc"]);
+    assert_eq!(eligibility.excluded[0].segment_id.0, "segment_general_research");
+    assert_eq!(eligibility.excluded[0].reasons, ["lacks capabilities [gpu]"]);
+
+    // Swapping the registry takes effect on the next resolution.
+    resolver.replace(SegmentRegistry::default());
+    let err = resolver.resolve_zones(&lab).await.unwrap_err();
+    assert!(matches!(OrchestratorError::find(&err), Some(OrchestratorError::Forbidden(_))));
+}
+
+#[tokio::test]
+async fn plans_are_placed_only_on_eligible_segments() {
+    let window = UsageWindowId("2026-02-08T00Z_daily".into());
+    let lab = actor(&["climate_lab"], 3);
+    let quota = InMemoryQuotaStore::new().with_allowance(
+        &lab.actor_id,
+        &window,
+        ComputeEnergyAllowance {
+            max_flops: 1e16,
+            max_energy_kwh: 500.0,
+            max_carbon_kg: 100.0,
+            max_tier: CapabilityTier::Tier3,
+            valid_until: SystemTime::now() + Duration::from_secs(86_400),
+        },
+    );
+    let mut telemetry = StaticTelemetry::new();
+    for (segment, renewable_share_pct) in
+        [("segment_climate_hpc", 60.0), ("segment_general_research", 90.0)]
+    {
+        telemetry = telemetry.with_load(SegmentLoad {
+            segment_id: SegmentId(segment.into()),
+            current_flops: 1e12,
+            energy_rate_kw: 40.0,
+            thermal_margin_pct: 35.0,
+            renewable_share_pct,
+        });
+    }
+    let logger = InMemoryLogger::new();
+    let orchestrator = EcologicalOrchestrator::new(
+        StaticIdentityResolver::new().with_session("token-alice", lab),
+        RegistryZoneResolver::new(registry()),
+        quota.clone(),
+        StabilityGuard::new(telemetry, 10.0, 40.0),
+        SimplePolicyEngine,
+        logger.clone(),
+        InMemoryApprovalStore::new(),
+        InMemoryJobStore::new(),
+    );
+
+    let plan = orchestrator
+        .plan_job("token-alice", Some(window.clone()), job(CapabilityTier::Tier1, &[]), None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_segment.0, "segment_general_research");
+    // Only the HPC segment runs Tier3, however green the other is.
+    let plan = orchestrator
+        .plan_job(
+            "token-alice",
+            Some(window.clone()),
+            job(CapabilityTier::Tier3, &["hydrology"]),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(plan.approved_segment.0, "segment_climate_hpc");
+    assert!(plan.alternatives.is_empty());
+    let evaluated = logger
+        .events()
+        .into_iter()
+        .rfind(|e| matches!(e.event_type, LogEventType::PolicyEvaluated))
+        .unwrap();
+    let excluded = evaluated.metadata["excluded_segments"].as_array().unwrap();
+    assert_eq!(excluded.len(), 2);
+    assert_eq!(excluded[0]["segment_id"], "segment_general_research");
+
+    let reserved = quota.reservations().len();
+    let err = orchestrator
+        .plan_job(
+            "token-alice",
+            Some(window),
+            job(CapabilityTier::Tier3, &["geoengineering"]),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    match OrchestratorError::find(&err) {
+        Some(OrchestratorError::Forbidden(message)) => {
+            assert!(message.contains("does not accept domain tags [geoengineering]"));
+            assert!(message.contains("runs at most Tier2"));
+        }
+        other => panic!("expected forbidden, got {:?}", other),
+    }
+    assert_eq!(quota.reservations().len(), reserved);
+}
+
+#[tokio::test]
+async fn registries_round_trip_through_postgres() {
+    let Ok(url) = std::env::var("EOL_TEST_DATABASE_URL") else {
+        eprintln!("EOL_TEST_DATABASE_URL not set; skipping");
+        return;
+    };
+    let schema = format!("eol_test_{}", uuid::Uuid::new_v4().simple());
+    let mut cfg = Config::new();
+    cfg.url = Some(url);
+    cfg.options = Some(format!("-c search_path={}", schema));
+    let pool = cfg.create_pool(Some(Runtime::Tokio1), NoTls).unwrap();
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute(&format!(
+            "CREATE SCHEMA {0};
+             CREATE TABLE {0}.eol_segments (
+                 segment_id TEXT PRIMARY KEY,
+                 position INTEGER NOT NULL,
+                 spec JSONB NOT NULL
+             );",
+            schema
+        ))
+        .await
+        .unwrap();
+
+    let store = PgSegmentRegistryStore::new(pool.clone());
+    // Stored out of order; positions decide the registry order.
+    for (position, spec) in registry().segments().iter().enumerate().rev() {
+        store.upsert(position as i32, spec).await.unwrap();
+    }
+    let mut vault = registry().segments()[2].clone();
+    vault.required_clearance = 4;
+    store.upsert(2, &vault).await.unwrap();
+
+    let loaded = store.load().await.unwrap();
+    let loaded_ids: Vec<&str> = loaded.segments().iter().map(|s| s.segment_id.0.as_str()).collect();
+    assert_eq!(
+        loaded_ids,
+        ["segment_climate_hpc", "segment_general_research", "segment_ethics_vault"]
+    );
+    assert_eq!(loaded.segments()[2].required_clearance, 4);
+    assert!(store.remove(&vault.segment_id).await.unwrap());
+    assert!(!store.remove(&vault.segment_id).await.unwrap());
+    assert_eq!(store.load().await.unwrap().segments().len(), 2);
+
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute(&format!("DROP SCHEMA {} CASCADE", schema))
+        .await
+        .unwrap();
+}
+
+#[test]
+fn tier_clearance_rules_cover_the_tiers_above_them() {
+    let policy = ClearancePolicy::new()
+        .with_tier(CapabilityTier::Tier2, 3, 2)
+        .with_tier(CapabilityTier::Tier3, 2, 4);
+    let actor = actor(&["climate_lab"], 2);
+
+    assert!(policy.assess(&actor, &job(CapabilityTier::Tier1, &[])).is_cleared());
+    // A Tier3 job is held to the Tier2 rule as well as its own.
+    let assessment = policy.assess(&actor, &job(CapabilityTier::Tier3, &[]));
+    assert_eq!(assessment.required_clearance, 3);
+    assert_eq!(assessment.min_segment_trust, 4);
+    assert_eq!(assessment.denials, ["Tier2 requires clearance 3, the actor has 2"]);
+}
@@ -0,0 +1,185 @@
+//! Window derivation from server time and allowance rollover.
+
+use ecological_orchestrator::eol::error::OrchestratorError;
+use ecological_orchestrator::eol::quota::QuotaStore;
+use ecological_orchestrator::eol::testing::InMemoryQuotaStore;
+use ecological_orchestrator::eol::types::{ActorId, BudgetLevel, CapabilityTier, UsageWindowId};
+use ecological_orchestrator::eol::windows::{
+    AllowanceTemplate, CarryForward, UsageWindow, WindowManager, WindowPeriod, WindowPolicy,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn at(unix_secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(unix_secs)
+}
+
+// 2026-02-08T13:00Z, a Sunday
+const SUNDAY_AFTERNOON: u64 = 1_770_555_600;
+
+fn policy(period: WindowPeriod, carry_forward: Option<f64>) -> WindowPolicy {
+    WindowPolicy {
+        period,
+        allowance: AllowanceTemplate {
+            max_flops: 1e16,
+            max_energy_kwh: 100.0,
+            max_carbon_kg: 20.0,
+            max_tier: CapabilityTier::Tier2,
+        },
+        carry_forward: carry_forward.map(|max_fraction| CarryForward { max_fraction }),
+    }
+}
+
+#[test]
+fn windows_are_derived_from_utc_calendar_boundaries() {
+    let now = at(SUNDAY_AFTERNOON);
+    let daily = UsageWindow::containing(WindowPeriod::Daily, now);
+    assert_eq!(daily.id.0, "2026-02-08T00Z_daily");
+    assert_eq!(daily.ends_at.duration_since(daily.starts_at).unwrap().as_secs(), 86_400);
+
+    let weekly = UsageWindow::containing(WindowPeriod::Weekly, now);
+    assert_eq!(weekly.id.0, "2026-02-02T00Z_weekly");
+    assert_eq!(weekly.next().id.0, "2026-02-09T00Z_weekly");
+
+    let monthly = UsageWindow::containing(WindowPeriod::Monthly, now);
+    assert_eq!(monthly.id.0, "2026-02-01T00Z_monthly");
+    assert_eq!(monthly.ends_at, at(1_772_323_200)); // 2026-03-01T00Z
+    assert_eq!(monthly.previous().id.0, "2026-01-01T00Z_monthly");
+
+    // Year boundary: 2024-12-31 is a Tuesday.
+    let new_years_eve = at(1_735_646_400);
+    assert_eq!(
+        UsageWindow::containing(WindowPeriod::Weekly, new_years_eve).id.0,
+        "2024-12-30T00Z_weekly"
+    );
+    assert_eq!(
+        UsageWindow::containing(WindowPeriod::Monthly, new_years_eve).next().id.0,
+        "2025-01-01T00Z_monthly"
+    );
+}
+
+#[test]
+fn window_ids_round_trip_and_reject_misaligned_dates() {
+    let weekly = UsageWindow::parse(&UsageWindowId("2026-02-02T00Z_weekly".into())).unwrap();
+    assert_eq!(weekly.period, WindowPeriod::Weekly);
+    assert!(weekly.starts_at <= at(SUNDAY_AFTERNOON) && at(SUNDAY_AFTERNOON) < weekly.ends_at);
+
+    for id in [
+        "2026-02-04T00Z_weekly",  // a Wednesday
+        "2026-02-08T00Z_monthly", // not the 1st
+        "2026-02-30T00Z_daily",
+        "2026-02-08_daily",
+        "2026-02-08T00Z_hourly",
+    ] {
+        assert!(UsageWindow::parse(&UsageWindowId(id.into())).is_err(), "{id}");
+    }
+}
+
+#[tokio::test]
+async fn rollover_provisions_the_new_window_with_capped_carry_forward() {
+    let actor = ActorId("did:example:alice".into());
+    let store = InMemoryQuotaStore::new();
+    let manager = WindowManager::new(policy(WindowPeriod::Daily, Some(0.25)));
+    let saturday = at(SUNDAY_AFTERNOON - 86_400);
+
+    // The first window ever gets the plain template.
+    let first = manager.ensure_current(&store, &actor, saturday).await.unwrap();
+    let allowance = store.get_allowance(&actor, &first.id).await.unwrap();
+    assert_eq!(allowance.max_energy_kwh, 100.0);
+    assert_eq!(allowance.valid_until, first.ends_at);
+
+    // Use 90 kWh of 100: 10 kWh carries over. Nothing of the 1e16 FLOPs was
+    // used, but only 25% of the template may carry.
+    store.set_usage(&actor, &first.id, 0.0, 90.0, 1.0);
+    let second = manager
+        .ensure_current(&store, &actor, at(SUNDAY_AFTERNOON))
+        .await
+        .unwrap();
+    assert_eq!(second.id.0, "2026-02-08T00Z_daily");
+    let allowance = store.get_allowance(&actor, &second.id).await.unwrap();
+    assert!((allowance.max_energy_kwh - 110.0).abs() < 1e-9);
+    assert!((allowance.max_flops - 1.25e16).abs() < 1.0);
+    assert!((allowance.max_carbon_kg - 25.0).abs() < 1e-9);
+    assert!(store.get_usage(&actor, &second.id).await.unwrap().energy_kwh_used == 0.0);
+
+    // Provisioning happens once per window.
+    store.set_usage(&actor, &first.id, 0.0, 0.0, 0.0);
+    manager
+        .ensure_current(&store, &actor, at(SUNDAY_AFTERNOON + 60))
+        .await
+        .unwrap();
+    let again = store.get_allowance(&actor, &second.id).await.unwrap();
+    assert!((again.max_energy_kwh - 110.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn actors_can_have_their_own_window_period() {
+    let actor = ActorId("did:example:observatory".into());
+    let manager = WindowManager::new(policy(WindowPeriod::Daily, None))
+        .with_actor_policy(&actor, policy(WindowPeriod::Monthly, None));
+
+    let now = at(SUNDAY_AFTERNOON);
+    assert_eq!(manager.current_window(&actor, now).id.0, "2026-02-01T00Z_monthly");
+    assert_eq!(
+        manager
+            .current_window(&ActorId("did:example:alice".into()), now)
+            .id
+            .0,
+        "2026-02-08T00Z_daily"
+    );
+}
+
+#[tokio::test]
+async fn group_caps_roll_over_with_their_members() {
+    let actor = ActorId("did:example:alice".into());
+    let saturday = UsageWindow::containing(WindowPeriod::Daily, at(SUNDAY_AFTERNOON - 86_400));
+    let store = InMemoryQuotaStore::new()
+        .with_project("watershed", "riverlab")
+        .with_member(&actor, "watershed")
+        .with_group_allowance(
+            BudgetLevel::Project,
+            "watershed",
+            &saturday.id,
+            policy(WindowPeriod::Daily, None).allowance_for(&saturday, None),
+        );
+    store.set_usage(&actor, &saturday.id, 0.0, 90.0, 1.0);
+
+    // Without a policy for the project, its cap cannot silently lapse.
+    let manager = WindowManager::new(policy(WindowPeriod::Daily, None));
+    let err = manager
+        .ensure_current(&store, &actor, at(SUNDAY_AFTERNOON))
+        .await
+        .unwrap_err();
+    assert_eq!(OrchestratorError::find(&err).unwrap().code(), "allowance_expired");
+
+    // With one, the project is capped again, carrying forward what its
+    // members left. The uncapped organisation stays uncapped.
+    let manager = manager.with_group_policy(
+        BudgetLevel::Project,
+        "watershed",
+        policy(WindowPeriod::Daily, Some(0.5)),
+    );
+    let sunday = manager
+        .ensure_current(&store, &actor, at(SUNDAY_AFTERNOON))
+        .await
+        .unwrap();
+    let project = store
+        .find_group_allowance(BudgetLevel::Project, "watershed", &sunday.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!((project.max_energy_kwh - 110.0).abs() < 1e-9);
+    assert_eq!(project.valid_until, sunday.ends_at);
+    let organisation = store
+        .find_group_allowance(BudgetLevel::Organisation, "riverlab", &sunday.id)
+        .await
+        .unwrap();
+    assert!(organisation.is_none());
+
+    // A group policy on another period than its members' is refused.
+    let manager = WindowManager::new(policy(WindowPeriod::Daily, None)).with_group_policy(
+        BudgetLevel::Organisation,
+        "riverlab",
+        policy(WindowPeriod::Weekly, None),
+    );
+    assert!(manager.ensure_current(&store, &actor, at(SUNDAY_AFTERNOON)).await.is_err());
+}